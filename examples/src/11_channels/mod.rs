@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+	use crate::simple_test_net::*;
+	use codec::Encode;
+	use frame_support::assert_ok;
+	use polkadot_parachain::primitives::{HrmpChannelId, Id as ParaId};
+	use polkadot_runtime_parachains::hrmp;
+	use xcm::latest::prelude::*;
+	use xcm_simulator::{ProcessMessageError, TestExt};
+
+	/// Message that dispatches `call` on the relay chain with the origin of the sending parachain.
+	fn transact_on_relay(call: hrmp::Call<relay_chain::Runtime>) -> Xcm<()> {
+		Xcm(vec![
+			UnpaidExecution { weight_limit: Unlimited, check_origin: None },
+			Transact {
+				origin_kind: OriginKind::Native,
+				require_weight_at_most: relay_chain::HRMP_CALL_WEIGHT,
+				call: relay_chain::RuntimeCall::Hrmp(call).encode().into(),
+			},
+		])
+	}
+
+	/// Scenario:
+	/// Parachain A requests a channel to parachain B, which accepts it.
+	/// The channel opens at the next session change, after which parachain A can send messages to
+	/// parachain B. Parachain B then closes the channel.
+	/// Both parachains are notified by the relay chain with the HRMP instructions.
+	#[test]
+	fn open_and_close_hrmp_channel() {
+		MockNet::reset_with(default_mock_net().without_hrmp_channels());
+
+		let message: Xcm<()> = Xcm(vec![ClearOrigin]);
+		let max_capacity = 8;
+		let max_message_size = 1024;
+
+		// No channel is open yet
+		ParaA::execute_with(|| {
+			assert!(matches!(
+				ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message.clone()),
+				Err(SendError::Transport(_))
+			));
+
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				Parent,
+				transact_on_relay(hrmp::Call::hrmp_init_open_channel {
+					recipient: 2.into(),
+					proposed_max_capacity: max_capacity,
+					proposed_max_message_size: max_message_size,
+				}),
+			));
+		});
+
+		ParaB::execute_with(|| {
+			assert_eq!(
+				parachain::MsgQueue::received_dmp(),
+				vec![Xcm(vec![HrmpNewChannelOpenRequest {
+					sender: 1,
+					max_message_size,
+					max_capacity
+				}])],
+			);
+
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				Parent,
+				transact_on_relay(hrmp::Call::hrmp_accept_open_channel { sender: 1.into() }),
+			));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				parachain::MsgQueue::received_dmp(),
+				vec![Xcm(vec![HrmpChannelAccepted { recipient: 2 }])],
+			);
+		});
+
+		// The channel is only open after the session change
+		assert!(!hrmp_channel_exists(1, 2));
+		Relay::execute_with(|| relay_chain::enact_hrmp_requests());
+		assert!(hrmp_channel_exists(1, 2));
+		// Channels are unidirectional
+		assert!(!hrmp_channel_exists(2, 1));
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message.clone()));
+		});
+
+		ParaB::execute_with(|| {
+			// The message is delivered, but not executed as it does not pay for its execution
+			assert_eq!(
+				parachain::MsgQueue::received_xcmp(ParaId::from(1)),
+				vec![(Xcm(vec![ClearOrigin]), 1, Outcome::Error(XcmError::Barrier))],
+			);
+
+			let channel_id = HrmpChannelId { sender: 1.into(), recipient: 2.into() };
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				Parent,
+				transact_on_relay(hrmp::Call::hrmp_close_channel { channel_id }),
+			));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				parachain::MsgQueue::received_dmp().last(),
+				Some(&Xcm(vec![HrmpChannelClosing { initiator: 2, sender: 1, recipient: 2 }])),
+			);
+		});
+
+		Relay::execute_with(|| relay_chain::enact_hrmp_requests());
+		assert!(!hrmp_channel_exists(1, 2));
+
+		ParaA::execute_with(|| {
+			assert!(matches!(
+				ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message),
+				Err(SendError::Transport(_))
+			));
+		});
+	}
+
+	/// Scenario:
+	/// Parachain A sends three messages to parachain B over a faulty transport.
+	/// The first message is dropped, the second one is delivered after the third one, which is
+	/// delivered twice.
+	#[test]
+	fn faulty_xcmp_transport() {
+		MockNet::reset();
+
+		inject_fault(Chain::Para(1), Chain::Para(2), 1, Fault::Drop);
+		inject_fault(Chain::Para(1), Chain::Para(2), 2, Fault::Reorder);
+		inject_fault(Chain::Para(1), Chain::Para(2), 3, Fault::Duplicate);
+
+		ParaA::execute_with(|| {
+			for code in 1..=3 {
+				let message = Xcm(vec![Trap(code)]);
+				assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message));
+			}
+		});
+
+		ParaB::execute_with(|| {
+			let received: Vec<_> = parachain::MsgQueue::received_xcmp(ParaId::from(1))
+				.into_iter()
+				.map(|(message, _, _)| message)
+				.collect();
+			assert_eq!(received, vec![Xcm(vec![Trap(3)]), Xcm(vec![Trap(3)]), Xcm(vec![Trap(2)])]);
+		});
+	}
+
+	/// Scenario:
+	/// The relay chain sends parachain A a message delayed by two blocks and a corrupted one.
+	/// Parachain B sends parachain A a message of an unsupported XCM version, and parachain A
+	/// sends the relay chain a corrupted message.
+	/// The corrupted messages are rejected by the message queues of their destination.
+	#[test]
+	fn delayed_and_corrupted_messages() {
+		MockNet::reset();
+		let mark = event_mark::<ParaA>();
+
+		inject_fault(Chain::Relay, Chain::Para(1), 1, Fault::Delay(2));
+		inject_fault(Chain::Relay, Chain::Para(1), 2, Fault::Corrupt(Corruption::InvalidFormat));
+		inject_fault(Chain::Para(2), Chain::Para(1), 1, Fault::Corrupt(Corruption::BadVersion));
+		inject_fault(Chain::Para(1), Chain::Relay, 1, Fault::Corrupt(Corruption::InvalidFormat));
+
+		Relay::execute_with(|| {
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), Xcm(vec![Trap(1)])));
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), Xcm(vec![Trap(2)])));
+		});
+
+		assert_para_event!(ParaA, since mark, MsgQueue::InvalidFormat(_));
+		assert_eq!(held_messages(), 1);
+
+		MockNet::advance_blocks(1);
+		ParaA::execute_with(|| assert!(parachain::MsgQueue::received_dmp().is_empty()));
+		MockNet::advance_blocks(1);
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::MsgQueue::received_dmp(), vec![Xcm(vec![Trap(1)])]);
+		});
+
+		ParaB::execute_with(|| {
+			let message = Xcm(vec![Trap(3)]);
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(1)), message));
+		});
+		assert_para_event!(ParaA, since mark, MsgQueue::BadVersion(_));
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, Xcm(vec![Trap(4)])));
+		});
+		assert_relay_event!(MessageQueue::ProcessingFailed {
+			error: ProcessMessageError::Corrupt,
+			..
+		});
+	}
+
+	/// Scenario:
+	/// The network is stepped block by block.
+	/// Parachain A sends a message to parachain B in block 2, which is delivered at block 3 with
+	/// the relay block it was sent at. Parachain B sends an upward message in block 3, which the
+	/// relay chain processes in block 4.
+	#[test]
+	fn block_by_block_delivery() {
+		MockNet::reset_with(default_mock_net().block_stepping());
+
+		MockNet::advance_blocks(1);
+		assert_eq!(relay_block_number(RELAY_NETWORK), 2);
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::System::block_number(), 2);
+			let message = Xcm(vec![Trap(1)]);
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message));
+		});
+
+		// Nothing is delivered until the next block
+		assert_eq!(scheduled_messages(), 1);
+		ParaB::execute_with(|| {
+			assert!(parachain::MsgQueue::received_xcmp(ParaId::from(1)).is_empty());
+		});
+
+		MockNet::advance_blocks(1);
+		ParaB::execute_with(|| {
+			assert_eq!(
+				parachain::MsgQueue::received_xcmp(ParaId::from(1)),
+				vec![(Xcm(vec![Trap(1)]), 2, Outcome::Error(XcmError::Barrier))],
+			);
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, Xcm(vec![Trap(2)])));
+		});
+
+		let mark = event_mark::<Relay>();
+		MockNet::advance_blocks(1);
+		Relay::execute_with(|| assert_eq!(relay_chain::System::block_number(), 4));
+		assert_relay_event!(since mark, MessageQueue::Processed { success: false, .. });
+	}
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+mod tests {
+	use crate::simple_test_net::*;
+	use frame_support::assert_ok;
+	use xcm::latest::prelude::*;
+	use xcm_simulator::TestExt;
+
+	/// Resets both networks, with relay token derivatives in the sovereign account of parachain A
+	/// on parachain A of the bridged network.
+	fn reset_networks() {
+		let remote_para_a = MultiLocation::new(2, X2(GlobalConsensus(RELAY_NETWORK), Parachain(1)));
+		MockNet::reset();
+		BridgedMockNet::reset_with(
+			TestNetBuilder::new()
+				.network(BRIDGED_RELAY_NETWORK)
+				.parachain_with_genesis(
+					1,
+					ParaKind::Parachain,
+					GenesisSpec::parachain_default(&[BRIDGE_HUB_PARA_ID]).fund_sovereign(
+						Sovereign::Location(remote_para_a),
+						Some(0),
+						INITIAL_BALANCE,
+					),
+				)
+				.parachain(BRIDGE_HUB_PARA_ID, ParaKind::BridgeHub),
+		);
+	}
+
+	/// Message to the bridge hub of the network of parachain A, exporting `remote_message` to
+	/// parachain A of the bridged network.
+	fn export_to_bridged_para_a(remote_message: Xcm<()>) -> Xcm<()> {
+		let bridge_fee = bridge_hub::estimate_message_fee(3);
+		Xcm(vec![
+			WithdrawAsset((Parent, bridge_fee).into()),
+			BuyExecution { fees: (Parent, bridge_fee).into(), weight_limit: Unlimited },
+			ExportMessage {
+				network: BRIDGED_RELAY_NETWORK,
+				destination: X1(Parachain(1)),
+				xcm: remote_message,
+			},
+		])
+	}
+
+	/// Scenario:
+	/// Parachain A sends a message to the bridge hub of its network, which exports a message to
+	/// parachain A of the bridged network with `ExportMessage`.
+	/// The bridge hub of the bridged network dispatches the message, which arrives at parachain A
+	/// with the origin of parachain A in the other consensus system, aliased with
+	/// `UniversalOrigin` and `DescendOrigin`.
+	/// The message withdraws relay token derivatives from the sovereign account of this origin and
+	/// deposits them in the account of ALICE.
+	#[test]
+	fn export_message_to_bridged_network() {
+		reset_networks();
+
+		let amount = 10 * CENTS;
+		// `UniversalOrigin` and `DescendOrigin` are prepended by the bridge hub.
+		let remote_fee = parachain::estimate_message_fee(5);
+		let bridge_fee = bridge_hub::estimate_message_fee(3);
+
+		let remote_message = Xcm(vec![
+			WithdrawAsset((Parent, amount).into()),
+			BuyExecution { fees: (Parent, remote_fee).into(), weight_limit: Unlimited },
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 { network: None, id: ALICE.into() }.into(),
+			},
+		]);
+		let message = export_to_bridged_para_a(remote_message);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				(Parent, Parachain(BRIDGE_HUB_PARA_ID)),
+				message.clone(),
+			));
+		});
+
+		BridgeHub::execute_with(|| {
+			assert_eq!(
+				BridgeHubBalances::free_balance(sibling_sovereign_account_id(1)),
+				INITIAL_BALANCE - bridge_fee
+			);
+		});
+		assert_eq!(pending_bridge_messages(), 1);
+
+		relay_bridge_messages();
+		assert_eq!(pending_bridge_messages(), 0);
+
+		BridgedParaA::execute_with(|| {
+			let sovereign = remote_parachain_sovereign_account_id(RELAY_NETWORK, 1);
+			assert_eq!(ParachainAssets::balance(0, sovereign), INITIAL_BALANCE - amount);
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE + amount - remote_fee);
+		});
+
+		// The balance of ALICE on parachain A of the network of the sender is untouched.
+		ParaA::execute_with(|| {
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE);
+		});
+	}
+
+	/// Scenario:
+	/// Like `export_message_to_bridged_network`, but the exported message aliases the origin of
+	/// parachain A in the bridged network to ALICE on parachain A with `AliasOrigin`.
+	/// The XCM executor of this release rejects `AliasOrigin` with `NoPermission`, so the
+	/// withdrawn assets are trapped and ALICE receives nothing.
+	#[test]
+	fn alias_origin_across_bridge_is_rejected() {
+		reset_networks();
+
+		let amount = 10 * CENTS;
+		let remote_fee = parachain::estimate_message_fee(6);
+		let alice = AccountId32 { network: None, id: ALICE.into() };
+		let remote_message = Xcm(vec![
+			WithdrawAsset((Parent, amount).into()),
+			BuyExecution { fees: (Parent, remote_fee).into(), weight_limit: Unlimited },
+			AliasOrigin(MultiLocation::new(1, X2(Parachain(1), alice))),
+			DepositAsset { assets: All.into(), beneficiary: alice.into() },
+		]);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				(Parent, Parachain(BRIDGE_HUB_PARA_ID)),
+				export_to_bridged_para_a(remote_message),
+			));
+		});
+		relay_bridge_messages();
+
+		assert_para_event!(BridgedParaA, MsgQueue::Fail(_, XcmError::NoPermission));
+		BridgedParaA::execute_with(|| {
+			let sovereign = remote_parachain_sovereign_account_id(RELAY_NETWORK, 1);
+			assert_eq!(ParachainAssets::balance(0, sovereign), INITIAL_BALANCE - amount);
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE);
+		});
+	}
+}
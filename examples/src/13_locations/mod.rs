@@ -0,0 +1,265 @@
+#[cfg(test)]
+mod tests {
+	use crate::simple_test_net::*;
+	use frame_support::assert_ok;
+	use xcm::latest::prelude::*;
+	use xcm_simulator::TestExt;
+
+	/// Scenario:
+	/// Locations of the chains and accounts of the network, as seen from one chain and from
+	/// another.
+	#[test]
+	fn reanchor_locations() {
+		MockNet::reset();
+
+		let alice = AccountId32 { network: None, id: ALICE.into() };
+		let alith = AccountKey20 { network: None, key: ALITH.into() };
+		let asset_hub = Chain::Para(ASSET_HUB_PARA_ID);
+		let evm_para = Chain::Para(EVM_PARA_ID);
+		// (seen from, seen on, location on the first chain, location on the second one)
+		let cases: Vec<(Chain, Chain, MultiLocation, MultiLocation)> = vec![
+			// Relay chain and parachains
+			(Chain::Relay, Chain::Para(1), Here.into(), Parent.into()),
+			(Chain::Para(1), Chain::Relay, Here.into(), Parachain(1).into()),
+			(Chain::Relay, Chain::Para(1), Parachain(1).into(), Here.into()),
+			(Chain::Relay, Chain::Para(1), Parachain(2).into(), (Parent, Parachain(2)).into()),
+			(Chain::Para(1), Chain::Relay, alice.into(), (Parachain(1), alice).into()),
+			(Chain::Relay, Chain::Para(1), alice.into(), (Parent, alice).into()),
+			// Siblings
+			(Chain::Para(1), Chain::Para(2), Here.into(), (Parent, Parachain(1)).into()),
+			(Chain::Para(1), Chain::Para(2), Parent.into(), Parent.into()),
+			(Chain::Para(1), Chain::Para(2), (Parent, Parachain(2)).into(), Here.into()),
+			(Chain::Para(1), Chain::Para(2), alice.into(), (Parent, Parachain(1), alice).into()),
+			(
+				evm_para,
+				Chain::Para(1),
+				alith.into(),
+				MultiLocation::new(1, X2(Parachain(EVM_PARA_ID), alith)),
+			),
+			// System parachains
+			(
+				asset_hub,
+				Chain::Para(1),
+				(PalletInstance(50), GeneralIndex(1)).into(),
+				MultiLocation::new(
+					1,
+					X3(Parachain(ASSET_HUB_PARA_ID), PalletInstance(50), GeneralIndex(1)),
+				),
+			),
+			(
+				Chain::Para(1),
+				asset_hub,
+				(Parent, Parachain(ASSET_HUB_PARA_ID), PalletInstance(50)).into(),
+				PalletInstance(50).into(),
+			),
+			(
+				Chain::Relay,
+				Chain::Para(BRIDGE_HUB_PARA_ID),
+				Parachain(ASSET_HUB_PARA_ID).into(),
+				(Parent, Parachain(ASSET_HUB_PARA_ID)).into(),
+			),
+		];
+
+		for (from, chain, location, expected) in cases {
+			assert_eq!(
+				location_on(chain, from, location),
+				Ok(expected),
+				"{location:?} of {from:?} on {chain:?}"
+			);
+		}
+	}
+
+	/// Scenario:
+	/// Assets as seen from one chain and from another. Fungible amounts and instances of
+	/// non-fungible assets are kept, only the location of the asset class changes.
+	#[test]
+	fn reanchor_assets() {
+		MockNet::reset();
+
+		let asset_hub = Chain::Para(ASSET_HUB_PARA_ID);
+		let usdt = (PalletInstance(50), GeneralIndex(1984));
+		let usdt_on_para = MultiLocation::new(
+			1,
+			X3(Parachain(ASSET_HUB_PARA_ID), PalletInstance(50), GeneralIndex(1984)),
+		);
+		let cases: Vec<(Chain, Chain, MultiAsset, MultiAsset)> = vec![
+			// The relay chain token
+			(Chain::Relay, Chain::Para(1), (Here, 100).into(), (Parent, 100).into()),
+			(Chain::Para(1), Chain::Relay, (Parent, 100).into(), (Here, 100).into()),
+			(Chain::Para(1), Chain::Para(2), (Parent, 100).into(), (Parent, 100).into()),
+			(Chain::Para(1), asset_hub, (Parent, 100).into(), (Parent, 100).into()),
+			// The native token of parachain A
+			(
+				Chain::Para(1),
+				Chain::Para(2),
+				(Here, 100).into(),
+				((Parent, Parachain(1)), 100).into(),
+			),
+			(Chain::Para(1), Chain::Relay, (Here, 100).into(), (Parachain(1), 100).into()),
+			// An asset of the asset hub
+			(asset_hub, Chain::Para(1), (usdt, 100).into(), (usdt_on_para, 100).into()),
+			// A non-fungible asset
+			(
+				Chain::Para(1),
+				Chain::Relay,
+				(PalletInstance(60), NonFungible(Index(1))).into(),
+				((Parachain(1), PalletInstance(60)), NonFungible(Index(1))).into(),
+			),
+		];
+
+		for (from, chain, asset, expected) in cases {
+			let reanchored = asset_on(chain, from, asset.clone());
+			assert_eq!(reanchored, Ok(expected), "{asset:?} of {from:?} on {chain:?}");
+		}
+
+		let assets: MultiAssets = vec![(Here, 100).into(), (Parent, 200).into()].into();
+		let para_a = universal_location(Chain::Para(1));
+		let para_b = universal_location(Chain::Para(2));
+		let mut reanchored =
+			reanchored_assets(assets, para_a, para_b).expect("reachable").into_inner();
+		reanchored.sort();
+		let expected: MultiAssets =
+			vec![(Parent, 200).into(), ((Parent, Parachain(1)), 100).into()].into();
+		assert_eq!(reanchored, expected.into_inner());
+	}
+
+	/// Scenario:
+	/// Locations of one consensus system as seen from the other one, across the bridge.
+	#[test]
+	fn reanchor_across_bridge() {
+		MockNet::reset();
+		BridgedMockNet::reset();
+
+		let alice = AccountId32 { network: None, id: ALICE.into() };
+		let relay = universal_location(Chain::Relay);
+		let para_a = universal_location(Chain::Para(1));
+		let bridge_hub = universal_location(Chain::Para(BRIDGE_HUB_PARA_ID));
+		let bridged_relay = bridged_universal_location(Chain::Relay);
+		let bridged_para_a = bridged_universal_location(Chain::Para(1));
+		// (universal location of the chain seen from, and of the one seen on, location on the
+		// first chain, location on the second one)
+		type Case = (InteriorMultiLocation, InteriorMultiLocation, MultiLocation, MultiLocation);
+		let cases: Vec<Case> = vec![
+			(relay, bridged_relay, Here.into(), (Parent, GlobalConsensus(RELAY_NETWORK)).into()),
+			(
+				para_a,
+				bridged_para_a,
+				Parent.into(),
+				MultiLocation::new(2, X1(GlobalConsensus(RELAY_NETWORK))),
+			),
+			(
+				para_a,
+				bridged_para_a,
+				Here.into(),
+				MultiLocation::new(2, X2(GlobalConsensus(RELAY_NETWORK), Parachain(1))),
+			),
+			(
+				bridged_para_a,
+				bridge_hub,
+				alice.into(),
+				MultiLocation::new(
+					2,
+					X3(GlobalConsensus(BRIDGED_RELAY_NETWORK), Parachain(1), alice),
+				),
+			),
+			(
+				bridged_para_a,
+				para_a,
+				MultiLocation::new(2, X2(GlobalConsensus(RELAY_NETWORK), Parachain(1))),
+				Here.into(),
+			),
+		];
+
+		for (from, to, location, expected) in cases {
+			let reanchored = reanchored(location, from, to);
+			assert_eq!(reanchored, Ok(expected), "{location:?} of {from:?} on {to:?}");
+		}
+
+		let relay_token = reanchored_asset((Here, 100), relay, bridged_para_a);
+		assert_eq!(
+			relay_token,
+			Ok((MultiLocation::new(2, X1(GlobalConsensus(RELAY_NETWORK))), 100).into())
+		);
+	}
+
+	/// Scenario:
+	/// ALICE transfers relay chain tokens from parachain A to parachain B through the relay
+	/// chain, like `transfers/reserve_backed_transfer_para_to_para`. The assets and locations of
+	/// the nested programs are written as seen from parachain A, and reanchored to the chain that
+	/// executes them.
+	#[test]
+	fn reserve_backed_transfer_with_reanchored_locations() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+		let fee_in_source = parachain::estimate_message_fee(3);
+		let fee_in_relay = relay_chain::estimate_message_fee(4);
+		let fee_in_destination = parachain::estimate_message_fee(4);
+
+		let para_a = Chain::Para(1);
+		let relay_token = |chain, amount: Balance| -> MultiAsset {
+			asset_on(chain, para_a, (Parent, amount)).expect("the relay chain is reachable")
+		};
+		let para_b = location_on(Chain::Relay, para_a, (Parent, Parachain(2)))
+			.expect("parachain B is reachable");
+
+		// The same program as the one of `transfers/reserve_backed_transfer_para_to_para`
+		assert_eq!(relay_token(Chain::Relay, fee_in_relay), (Here, fee_in_relay).into());
+		assert_eq!(para_b, Parachain(2).into());
+
+		let message: Xcm<parachain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset(relay_token(para_a, withdraw_amount).into()),
+			BuyExecution {
+				fees: relay_token(para_a, fee_in_source),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			InitiateReserveWithdraw {
+				assets: All.into(),
+				reserve: Parent.into(),
+				xcm: Xcm(vec![
+					BuyExecution {
+						fees: relay_token(Chain::Relay, fee_in_relay),
+						weight_limit: WeightLimit::Unlimited,
+					},
+					DepositReserveAsset {
+						assets: All.into(),
+						dest: para_b,
+						xcm: Xcm(vec![
+							BuyExecution {
+								fees: relay_token(Chain::Para(2), fee_in_destination),
+								weight_limit: WeightLimit::Unlimited,
+							},
+							DepositAsset {
+								assets: All.into(),
+								beneficiary: Junction::AccountId32 {
+									id: ALICE.into(),
+									network: None,
+								}
+								.into(),
+							},
+						]),
+					},
+				]),
+			},
+		]);
+
+		let fee_until_destination = fee_in_source + fee_in_relay + fee_in_destination;
+
+		ParaA::execute_with(|| {
+			assert_ok!(parachain::PolkadotXcm::execute(
+				parachain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into(),
+			));
+
+			assert_eq!(parachain::Assets::balance(0, &ALICE), INITIAL_BALANCE - withdraw_amount);
+		});
+
+		ParaB::execute_with(|| {
+			assert_eq!(
+				parachain::Assets::balance(0, &ALICE),
+				INITIAL_BALANCE + withdraw_amount - fee_until_destination
+			);
+		});
+	}
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+	use crate::simple_test_net::*;
+	use frame_support::assert_ok;
+
+	/// Scenario:
+	/// The scenario file `scenarios/reserve_backed_transfer_relay_to_para.json` funds BOB on the
+	/// relay chain and transfers relay tokens from it to parachain B, like
+	/// `reserve_backed_transfer_relay_to_para`. Its balances, events and received messages hold.
+	#[test]
+	fn run_scenario_file() {
+		let json = include_str!("../../scenarios/reserve_backed_transfer_relay_to_para.json");
+		let scenario = Scenario::from_json(json).unwrap();
+		assert!(scenario.description.unwrap().starts_with("BOB sends 50 CENTS of relay tokens"));
+		assert_eq!(scenario.steps.len(), 1);
+		assert_eq!(scenario.expect.balances.len(), 3);
+
+		assert_ok!(run_scenario(json));
+	}
+
+	/// Scenario:
+	/// The expectations that do not hold are all reported, after the steps ran.
+	#[test]
+	fn scenario_reports_failed_expectations() {
+		let json = r#"{
+			"name": "Wrong expectations",
+			"expect": {
+				"balances": [
+					{ "chain": "relay", "account": "ALICE", "amount": 100000000000 },
+					{ "chain": "para-a", "account": "ALICE", "asset": 0, "amount": 1 }
+				],
+				"events": [{ "chain": "relay", "contains": "Attempted" }]
+			}
+		}"#;
+
+		match run_scenario(json) {
+			Err(ScenarioError::Expectations(failures)) => {
+				assert_eq!(failures.len(), 2);
+				assert!(failures[0].contains("asset 0 is 100000000000, expected 1"));
+				assert!(failures[1].starts_with("relay: no event contains [\"Attempted\"]"));
+			},
+			result => panic!("unexpected result {result:?}"),
+		}
+	}
+
+	/// Scenario:
+	/// A step that cannot be dispatched stops the scenario, with the index of the step.
+	#[test]
+	fn scenario_reports_failed_step() {
+		let json = r#"{
+			"name": "Undecodable call",
+			"steps": [
+				{
+					"chain": "relay",
+					"origin": "Here",
+					"send": { "dest": "Parachain(1)", "xcm": ["ClearOrigin"] }
+				},
+				{ "chain": "relay", "origin": "ALICE", "call": "0xffff" }
+			]
+		}"#;
+
+		match run_scenario(json) {
+			Err(ScenarioError::Step { index: 1, message }) =>
+				assert!(message.starts_with("cannot decode the call")),
+			result => panic!("unexpected result {result:?}"),
+		}
+	}
+
+	/// Scenario:
+	/// Scenarios that do not follow the format are rejected with the path of the mistake, before
+	/// any step runs.
+	#[test]
+	fn scenario_format_errors() {
+		let missing_fees = r#"{
+			"name": "Missing fees",
+			"steps": [{
+				"chain": "para-a",
+				"origin": "ALICE",
+				"execute": { "xcm": [{ "WithdrawAsset": [] }, { "BuyExecution": {} }] }
+			}]
+		}"#;
+		match Scenario::from_json(missing_fees) {
+			Err(ScenarioError::Format { path, message }) => {
+				assert_eq!(path, "steps[0].execute.xcm[1].BuyExecution");
+				assert_eq!(message, "missing `fees`");
+			},
+			result => panic!("unexpected result {result:?}"),
+		}
+
+		let cases = [
+			(r#"{ "name": "Misspelled", "expcet": {} }"#, ""),
+			(r#"{ "name": "Unknown chain", "steps": [{ "chain": "para-d" }] }"#, "steps[0].chain"),
+			(
+				r#"{ "name": "EVM", "genesis": [{ "chain": "evm-para", "account": "ALICE" }] }"#,
+				"genesis[0].chain",
+			),
+			(
+				r#"{ "name": "Sovereign", "genesis": [{
+					"chain": "relay", "account": { "sovereign": "Parachain(1)" }, "amount": 1
+				}] }"#,
+				"genesis[0].account",
+			),
+			(
+				r#"{ "name": "Junction", "steps": [{ "chain": "relay", "origin": "ALICE",
+					"send": { "dest": "Parachain(one)", "xcm": [] } }] }"#,
+				"steps[0].send.dest",
+			),
+		];
+		for (json, expected) in cases {
+			match Scenario::from_json(json) {
+				Err(ScenarioError::Format { path, .. }) => assert_eq!(path, expected),
+				result => panic!("unexpected result {result:?}"),
+			}
+		}
+		assert!(matches!(Scenario::from_json("{"), Err(ScenarioError::Json(_))));
+	}
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod tests {
+	use crate::simple_test_net::*;
+	use codec::Encode;
+	use frame_support::assert_ok;
+	use sp_core::blake2_256;
+	use xcm::latest::prelude::*;
+	use xcm_simulator::TestExt;
+
+	/// Scenario:
+	/// ALICE transfers relay native tokens from parachain A, whose accounts are 32 bytes long, to
+	/// ALITH on the EVM parachain, whose accounts are 20 bytes long.
+	#[test]
+	fn reserve_backed_transfer_para_to_evm_para() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = parachain::estimate_message_fee(3);
+		let fee_in_relay = relay_chain::estimate_message_fee(4);
+		let fee_in_destination = evm_parachain::estimate_message_fee(4);
+
+		let message: Xcm<parachain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Parent, withdraw_amount).into()),
+			BuyExecution {
+				fees: (Parent, fee_in_source).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			InitiateReserveWithdraw {
+				assets: All.into(),
+				reserve: Parent.into(),
+				xcm: Xcm(vec![
+					BuyExecution {
+						fees: (Here, fee_in_relay).into(),
+						weight_limit: WeightLimit::Unlimited,
+					},
+					DepositReserveAsset {
+						assets: All.into(),
+						dest: Parachain(EVM_PARA_ID).into(),
+						xcm: Xcm(vec![
+							BuyExecution {
+								fees: (Parent, fee_in_destination).into(),
+								weight_limit: WeightLimit::Unlimited,
+							},
+							DepositAsset {
+								assets: All.into(),
+								beneficiary: AccountKey20 { key: ALITH.into(), network: None }
+									.into(),
+							},
+						]),
+					},
+				]),
+			},
+		]);
+
+		let fee_until_relay = fee_in_source + fee_in_relay;
+		let fee_until_destination = fee_until_relay + fee_in_destination;
+
+		ParaA::execute_with(|| {
+			assert_ok!(parachain::PolkadotXcm::execute(
+				parachain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into(),
+			));
+
+			assert_eq!(parachain::Assets::balance(0, &ALICE), INITIAL_BALANCE - withdraw_amount);
+		});
+
+		Relay::execute_with(|| {
+			assert_eq!(
+				relay_chain::Balances::free_balance(&parachain_sovereign_account_id(EVM_PARA_ID)),
+				INITIAL_BALANCE + withdraw_amount - fee_until_relay
+			);
+		});
+
+		EvmPara::execute_with(|| {
+			assert_eq!(
+				EvmParaBalances::free_balance(&ALITH),
+				INITIAL_BALANCE + withdraw_amount - fee_until_destination
+			);
+		});
+	}
+
+	/// Scenario:
+	/// ALITH transfers relay native tokens from the EVM parachain to ALICE on parachain A.
+	#[test]
+	fn reserve_backed_transfer_evm_para_to_para() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = evm_parachain::estimate_message_fee(3);
+		let fee_in_relay = relay_chain::estimate_message_fee(4);
+		let fee_in_destination = parachain::estimate_message_fee(4);
+
+		let message: Xcm<evm_parachain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Parent, withdraw_amount).into()),
+			BuyExecution {
+				fees: (Parent, fee_in_source).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			InitiateReserveWithdraw {
+				assets: All.into(),
+				reserve: Parent.into(),
+				xcm: Xcm(vec![
+					BuyExecution {
+						fees: (Here, fee_in_relay).into(),
+						weight_limit: WeightLimit::Unlimited,
+					},
+					DepositReserveAsset {
+						assets: All.into(),
+						dest: Parachain(1).into(),
+						xcm: Xcm(vec![
+							BuyExecution {
+								fees: (Parent, fee_in_destination).into(),
+								weight_limit: WeightLimit::Unlimited,
+							},
+							DepositAsset {
+								assets: All.into(),
+								beneficiary: Junction::AccountId32 {
+									id: ALICE.into(),
+									network: None,
+								}
+								.into(),
+							},
+						]),
+					},
+				]),
+			},
+		]);
+
+		let fee_until_destination = fee_in_source + fee_in_relay + fee_in_destination;
+
+		EvmPara::execute_with(|| {
+			assert_ok!(EvmParaPalletXcm::execute(
+				evm_parachain::RuntimeOrigin::signed(ALITH),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into(),
+			));
+
+			assert_eq!(EvmParaBalances::free_balance(&ALITH), INITIAL_BALANCE - withdraw_amount);
+		});
+
+		Relay::execute_with(|| {
+			assert_eq!(
+				relay_chain::Balances::free_balance(&parachain_sovereign_account_id(EVM_PARA_ID)),
+				INITIAL_BALANCE - (withdraw_amount - fee_in_source)
+			);
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				parachain::Assets::balance(0, &ALICE),
+				INITIAL_BALANCE + withdraw_amount - fee_until_destination
+			);
+		});
+	}
+
+	/// Scenario:
+	/// ALICE funds the alias account of ALITH on parachain A. ALITH sends parachain A a message
+	/// from the EVM parachain, which withdraws the native tokens of parachain A from the alias
+	/// account and deposits them back to ALICE.
+	#[test]
+	fn withdraw_from_alias_of_evm_account() {
+		MockNet::reset();
+
+		let amount = 50 * CENTS;
+		let fee = parachain::estimate_fee_for_weight_in(Here, parachain::estimate_weight(4))
+			.expect("fees can be paid in the native token");
+
+		// The alias of a 20 byte account is derived with its own prefix
+		let alias = sibling_account_key20_sovereign_account_id(ALITH);
+		let entropy =
+			(FOREIGN_CHAIN_PREFIX_PARA_20, EVM_PARA_ID, ALITH.0, 1u8).using_encoded(blake2_256);
+		assert_eq!(alias, entropy.into());
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainBalances::transfer(
+				parachain::RuntimeOrigin::signed(ALICE),
+				alias.clone(),
+				amount
+			));
+		});
+
+		let message = Xcm(vec![
+			// The message is executed on parachain A, whose native token is `Here`
+			WithdrawAsset((Here, amount).into()),
+			BuyExecution { fees: (Here, fee).into(), weight_limit: WeightLimit::Unlimited },
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: Junction::AccountId32 { id: ALICE.into(), network: None }.into(),
+			},
+		]);
+
+		EvmPara::execute_with(|| {
+			// `send` prepends a `DescendOrigin` to the `AccountKey20` of ALITH
+			assert_ok!(EvmParaPalletXcm::send(
+				evm_parachain::RuntimeOrigin::signed(ALITH),
+				Box::new(xcm::VersionedMultiLocation::V3((Parent, Parachain(1)).into())),
+				Box::new(xcm::VersionedXcm::V3(message)),
+			));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(ParachainBalances::free_balance(&alias), 0);
+			assert_eq!(ParachainBalances::free_balance(&ALICE), INITIAL_BALANCE - fee);
+		});
+	}
+}
@@ -1,2 +1,3 @@
+mod evm;
 mod reserve;
 mod teleport;
@@ -81,6 +81,20 @@ mod tests {
 				INITIAL_BALANCE + withdraw_amount - fee_until_destination
 			);
 		});
+
+		// The assets went up to the relay chain and down to parachain B
+		let hops = trace().0;
+		assert_eq!(hops.len(), 2);
+		assert_eq!(
+			(hops[0].kind, hops[0].from, hops[0].to),
+			(HopKind::Ump, Chain::Para(1), Chain::Relay)
+		);
+		assert_eq!(
+			(hops[1].kind, hops[1].from, hops[1].to),
+			(HopKind::Dmp, Chain::Relay, Chain::Para(2))
+		);
+		assert!(hops.iter().all(|hop| matches!(hop.outcome, Some(Outcome::Complete(_)))));
+		assert!(trace().to_mermaid().contains("Para_1->>Relay: Ump WithdrawAsset"));
 	}
 
 	/// Scenario:
@@ -145,6 +159,84 @@ mod tests {
 		});
 	}
 
+	/// Scenario:
+	/// ALICE transfers relay native tokens from parachain A to parachain B, like
+	/// `reserve_backed_transfer_para_to_para`, with the program written with `XcmBuilder`.
+	#[test]
+	fn reserve_backed_transfer_para_to_para_with_builder() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = parachain::estimate_message_fee(3);
+		let fee_in_relay = relay_chain::estimate_message_fee(4);
+		let fee_in_destination = parachain::estimate_message_fee(4);
+
+		let beneficiary = Junction::AccountId32 { id: ALICE.into(), network: None };
+		let message = XcmBuilder::<parachain::RuntimeCall>::new()
+			.withdraw((Parent, withdraw_amount))
+			.buy_execution((Parent, fee_in_source), Unlimited)
+			.initiate_reserve_withdraw(All, Parent, |relay| {
+				relay.buy_execution((Here, fee_in_relay), Unlimited).deposit_reserve_asset(
+					All,
+					Parachain(2),
+					|para_b| {
+						para_b
+							.buy_execution((Parent, fee_in_destination), Unlimited)
+							.deposit(All, beneficiary)
+					},
+				)
+			})
+			.build();
+
+		// The program of `reserve_backed_transfer_para_to_para`
+		assert_eq!(
+			message,
+			Xcm(vec![
+				WithdrawAsset((Parent, withdraw_amount).into()),
+				BuyExecution { fees: (Parent, fee_in_source).into(), weight_limit: Unlimited },
+				InitiateReserveWithdraw {
+					assets: All.into(),
+					reserve: Parent.into(),
+					xcm: Xcm(vec![
+						BuyExecution { fees: (Here, fee_in_relay).into(), weight_limit: Unlimited },
+						DepositReserveAsset {
+							assets: All.into(),
+							dest: Parachain(2).into(),
+							xcm: Xcm(vec![
+								BuyExecution {
+									fees: (Parent, fee_in_destination).into(),
+									weight_limit: Unlimited,
+								},
+								DepositAsset {
+									assets: All.into(),
+									beneficiary: beneficiary.into(),
+								},
+							]),
+						},
+					]),
+				},
+			])
+		);
+
+		let fee_until_destination = fee_in_source + fee_in_relay + fee_in_destination;
+
+		ParaA::execute_with(|| {
+			assert_ok!(parachain::PolkadotXcm::execute(
+				parachain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into(),
+			));
+		});
+
+		ParaB::execute_with(|| {
+			assert_eq!(
+				parachain::Assets::balance(0, &ALICE),
+				INITIAL_BALANCE + withdraw_amount - fee_until_destination
+			);
+		});
+	}
+
 	#[test]
 	fn reserve_backed_transfer_para_to_relay() {
 		MockNet::reset();
@@ -202,4 +294,87 @@ mod tests {
 			);
 		});
 	}
+
+	/// Scenario:
+	/// ALICE transfers relay native tokens from the asset hub to parachain A.
+	/// The relay chain is the reserve of its token, so the assets are withdrawn from the asset hub's
+	/// sovereign account on the relay chain and deposited into parachain A's sovereign account.
+	#[test]
+	fn reserve_backed_transfer_asset_hub_to_para() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = asset_hub::estimate_message_fee(3);
+		let fee_in_relay = relay_chain::estimate_message_fee(4);
+		let fee_in_destination = parachain::estimate_message_fee(4);
+
+		let message: Xcm<asset_hub::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Parent, withdraw_amount).into()),
+			BuyExecution {
+				fees: (Parent, fee_in_source).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			InitiateReserveWithdraw {
+				assets: All.into(),
+				reserve: Parent.into(),
+				xcm: Xcm(vec![
+					BuyExecution {
+						fees: (Here, fee_in_relay).into(),
+						weight_limit: WeightLimit::Unlimited,
+					},
+					DepositReserveAsset {
+						assets: All.into(),
+						dest: Parachain(1).into(),
+						xcm: Xcm(vec![
+							BuyExecution {
+								fees: (Parent, fee_in_destination).into(),
+								weight_limit: WeightLimit::Unlimited,
+							},
+							DepositAsset {
+								assets: All.into(),
+								beneficiary: Junction::AccountId32 {
+									id: ALICE.into(),
+									network: None,
+								}
+								.into(),
+							},
+						]),
+					},
+				]),
+			},
+		]);
+
+		AssetHub::execute_with(|| {
+			assert_ok!(asset_hub::PolkadotXcm::execute(
+				asset_hub::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into(),
+			));
+
+			assert_eq!(AssetHubBalances::free_balance(&ALICE), INITIAL_BALANCE - withdraw_amount);
+		});
+
+		Relay::execute_with(|| {
+			assert_eq!(
+				relay_chain::Balances::free_balance(&parachain_sovereign_account_id(
+					ASSET_HUB_PARA_ID
+				)),
+				INITIAL_BALANCE - (withdraw_amount - fee_in_source)
+			);
+			assert_eq!(
+				relay_chain::Balances::free_balance(&parachain_sovereign_account_id(1)),
+				INITIAL_BALANCE + withdraw_amount - fee_in_source - fee_in_relay
+			);
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				parachain::Assets::balance(0, &ALICE),
+				INITIAL_BALANCE + withdraw_amount -
+					fee_in_source - fee_in_relay -
+					fee_in_destination
+			);
+		});
+	}
 }
@@ -76,4 +76,124 @@ mod tests {
 			);
 		});
 	}
+
+	/// Scenario:
+	/// ALICE teleports her native assets from the relay chain to parachain A, like
+	/// `teleport_fungible`, with the program written with `XcmBuilder`.
+	#[test]
+	fn teleport_fungible_with_builder() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = relay_chain::estimate_message_fee(3);
+		let fee_in_destination = parachain::estimate_message_fee(4);
+
+		let beneficiary = Junction::AccountId32 { network: None, id: ALICE.into() };
+		let message = XcmBuilder::<relay_chain::RuntimeCall>::new()
+			.withdraw((Here, withdraw_amount))
+			.buy_execution((Here, fee_in_source), Unlimited)
+			.initiate_teleport(All, Parachain(1), |para_a| {
+				para_a
+					.buy_execution((Parent, fee_in_destination), Unlimited)
+					.deposit(All, beneficiary)
+			})
+			.build();
+
+		// The program of `teleport_fungible`
+		assert_eq!(
+			message,
+			Xcm(vec![
+				WithdrawAsset((Here, withdraw_amount).into()),
+				BuyExecution { fees: (Here, fee_in_source).into(), weight_limit: Unlimited },
+				InitiateTeleport {
+					assets: All.into(),
+					dest: Parachain(1).into(),
+					xcm: Xcm(vec![
+						BuyExecution {
+							fees: (Parent, fee_in_destination).into(),
+							weight_limit: Unlimited,
+						},
+						DepositAsset { assets: All.into(), beneficiary: beneficiary.into() },
+					]),
+				},
+			])
+		);
+
+		Relay::execute_with(|| {
+			assert_ok!(relay_chain::XcmPallet::execute(
+				relay_chain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into()
+			));
+
+			assert_eq!(
+				relay_chain::Balances::free_balance(ALICE),
+				INITIAL_BALANCE - withdraw_amount
+			);
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				parachain::Assets::balance(0, &ALICE),
+				INITIAL_BALANCE + (withdraw_amount - fee_in_source - fee_in_destination)
+			);
+		});
+	}
+
+	/// Scenario:
+	/// ALICE teleports her native assets from the relay chain to the asset hub.
+	/// The asset hub uses the relay chain token as its native token and the relay chain trusts it as a teleporter.
+	#[test]
+	fn teleport_to_asset_hub() {
+		MockNet::reset();
+
+		let withdraw_amount = 50 * CENTS;
+
+		let fee_in_source = relay_chain::estimate_message_fee(3);
+		let fee_in_destination = asset_hub::estimate_message_fee(4);
+
+		let message: Xcm<relay_chain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Here, withdraw_amount).into()),
+			BuyExecution {
+				fees: (Here, fee_in_source).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			InitiateTeleport {
+				assets: All.into(),
+				dest: Parachain(ASSET_HUB_PARA_ID).into(),
+				xcm: Xcm(vec![
+					BuyExecution {
+						fees: (Parent, fee_in_destination).into(),
+						weight_limit: WeightLimit::Unlimited,
+					},
+					DepositAsset {
+						assets: All.into(),
+						beneficiary: Junction::AccountId32 { network: None, id: ALICE.into() }
+							.into(),
+					},
+				]),
+			},
+		]);
+
+		Relay::execute_with(|| {
+			assert_ok!(relay_chain::XcmPallet::execute(
+				relay_chain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::V3(message.into())),
+				(100_000_000_000, 100_000_000_000).into()
+			));
+
+			assert_eq!(
+				relay_chain::Balances::free_balance(ALICE),
+				INITIAL_BALANCE - withdraw_amount
+			);
+		});
+
+		AssetHub::execute_with(|| {
+			assert_eq!(
+				AssetHubBalances::free_balance(ALICE),
+				INITIAL_BALANCE + (withdraw_amount - fee_in_source - fee_in_destination)
+			);
+		});
+	}
 }
@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
 	use crate::simple_test_net::{parachain::estimate_message_fee, *};
-	use frame_support::assert_ok;
+	use codec::Encode;
+	use frame_support::{assert_noop, assert_ok, weights::Weight};
+	use polkadot_parachain::primitives::{DmpMessageHandler, Id as ParaId};
 	use xcm::latest::prelude::*;
 	use xcm_simulator::TestExt;
 
@@ -49,4 +51,473 @@ mod tests {
 			);
 		})
 	}
+
+	/// A downward message paying its execution on Parachain A with the relay token, encoded like
+	/// the relay chain sends it.
+	fn paid_downward_message() -> Vec<u8> {
+		let message_fee = estimate_message_fee(3);
+		let message: Xcm<parachain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Parent, message_fee).into()),
+			BuyExecution {
+				fees: (Parent, message_fee).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 { network: None, id: relay_sovereign_account_id().into() }
+					.into(),
+			},
+		]);
+		xcm::VersionedXcm::V3(message).encode()
+	}
+
+	/// Scenario:
+	/// Parachain A receives two downward messages in a block with only enough weight for one.
+	/// The first message is executed, the second one stays queued and is executed at the start of
+	/// the next block.
+	#[test]
+	fn deferred_downward_message() {
+		MockNet::reset();
+		let message_fee = estimate_message_fee(3);
+		let data = paid_downward_message();
+		let weight_per_message = parachain::estimate_weight(3);
+
+		ParaA::execute_with(|| {
+			let weight_used = parachain::MsgQueue::handle_dmp_messages(
+				vec![(1, data.clone()), (1, data)].into_iter(),
+				weight_per_message,
+			);
+			assert_eq!(weight_used, weight_per_message);
+			assert_eq!(parachain::MsgQueue::received_dmp().len(), 1);
+			assert_eq!(parachain::MsgQueue::deferred_dmp().len(), 1);
+			assert!(parachain::MsgQueue::overweight(0).is_none());
+			assert_eq!(
+				ParachainAssets::balance(0, relay_sovereign_account_id()),
+				INITIAL_BALANCE - message_fee
+			);
+		});
+
+		MockNet::advance_blocks(1);
+
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::MsgQueue::received_dmp().len(), 2);
+			assert!(parachain::MsgQueue::deferred_dmp().is_empty());
+			assert_eq!(
+				ParachainAssets::balance(0, relay_sovereign_account_id()),
+				INITIAL_BALANCE - 2 * message_fee
+			);
+		});
+	}
+
+	/// Scenario:
+	/// Parachain A receives a downward message in a block giving less weight to messages than
+	/// the message needs. The message is put in the overweight queue, and Root executes it later
+	/// with `service_overweight`.
+	#[test]
+	fn overweight_downward_message() {
+		MockNet::reset();
+		let message_fee = estimate_message_fee(3);
+		let data = paid_downward_message();
+		let weight_per_message = parachain::estimate_weight(3);
+
+		ParaA::execute_with(|| {
+			let weight_used = parachain::MsgQueue::handle_dmp_messages(
+				vec![(1, data)].into_iter(),
+				weight_per_message / 2,
+			);
+			assert_eq!(weight_used, Weight::zero());
+			assert!(parachain::MsgQueue::received_dmp().is_empty());
+			assert!(parachain::MsgQueue::deferred_dmp().is_empty());
+			assert!(parachain::MsgQueue::overweight(0).is_some());
+
+			assert_ok!(parachain::MsgQueue::service_overweight(
+				parachain::RuntimeOrigin::root(),
+				0,
+				weight_per_message,
+			));
+			assert!(parachain::MsgQueue::overweight(0).is_none());
+			assert_eq!(parachain::MsgQueue::received_dmp().len(), 1);
+			assert_eq!(
+				ParachainAssets::balance(0, relay_sovereign_account_id()),
+				INITIAL_BALANCE - message_fee
+			);
+		});
+		assert_para_event!(ParaA, MsgQueue::ExecutedDownward(_, Outcome::Complete(_)));
+	}
+
+	/// A message paying its execution on Parachain B with the relay token, sent by Parachain A.
+	fn paid_horizontal_message() -> Xcm<()> {
+		let message_fee = estimate_message_fee(3);
+		Xcm(vec![
+			WithdrawAsset((Parent, message_fee).into()),
+			BuyExecution {
+				fees: (Parent, message_fee).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 {
+					network: None,
+					id: sibling_sovereign_account_id(1).into(),
+				}
+				.into(),
+			},
+		])
+	}
+
+	/// Scenario:
+	/// Parachain A sends two messages to Parachain B, whose blocks give only enough weight to
+	/// messages for one of them.
+	/// The first message is executed, the second one is deferred and executed at the start of the
+	/// next block.
+	#[test]
+	fn deferred_horizontal_message() {
+		MockNet::reset();
+		let message_fee = estimate_message_fee(3);
+		let weight_per_message = parachain::estimate_weight(3);
+
+		ParaB::execute_with(|| {
+			parachain::MsgQueue::set_block_weight_budget(weight_per_message);
+		});
+		ParaA::execute_with(|| {
+			for _ in 0..2 {
+				let message = paid_horizontal_message();
+				assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message));
+			}
+		});
+
+		ParaB::execute_with(|| {
+			assert_eq!(parachain::MsgQueue::received_xcmp(ParaId::from(1)).len(), 1);
+			assert_eq!(parachain::MsgQueue::deferred_xcmp().len(), 1);
+			assert_eq!(
+				ParachainAssets::balance(0, sibling_sovereign_account_id(1)),
+				INITIAL_BALANCE - message_fee
+			);
+		});
+
+		MockNet::advance_blocks(1);
+
+		ParaB::execute_with(|| {
+			assert_eq!(parachain::MsgQueue::received_xcmp(ParaId::from(1)).len(), 2);
+			assert!(parachain::MsgQueue::deferred_xcmp().is_empty());
+			assert_eq!(
+				ParachainAssets::balance(0, sibling_sovereign_account_id(1)),
+				INITIAL_BALANCE - 2 * message_fee
+			);
+		});
+		// The trace has the outcome of the execution in the next block.
+		let trace = trace();
+		assert!(
+			trace.0.iter().all(|hop| matches!(hop.outcome, Some(Outcome::Complete(_)))),
+			"{trace:?}"
+		);
+	}
+
+	/// Scenario:
+	/// Parachain A sends a message to Parachain B, whose blocks give less weight to messages than
+	/// the message needs. The message is put in the overweight queue, and Root executes it later
+	/// with `service_overweight`.
+	#[test]
+	fn overweight_horizontal_message() {
+		MockNet::reset();
+		let message_fee = estimate_message_fee(3);
+		let weight_per_message = parachain::estimate_weight(3);
+
+		ParaB::execute_with(|| {
+			parachain::MsgQueue::set_block_weight_budget(weight_per_message / 2);
+		});
+		ParaA::execute_with(|| {
+			let message = paid_horizontal_message();
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message));
+		});
+
+		ParaB::execute_with(|| {
+			assert!(parachain::MsgQueue::received_xcmp(ParaId::from(1)).is_empty());
+			assert!(parachain::MsgQueue::deferred_xcmp().is_empty());
+			assert!(parachain::MsgQueue::overweight(0).is_some());
+		});
+		assert_para_event!(ParaB, MsgQueue::OverweightEnqueued(_, _, 0, _));
+
+		// Not executed in the next block either.
+		MockNet::advance_blocks(1);
+
+		ParaB::execute_with(|| {
+			assert!(parachain::MsgQueue::overweight(0).is_some());
+			assert_noop!(
+				parachain::MsgQueue::service_overweight(
+					parachain::RuntimeOrigin::root(),
+					0,
+					weight_per_message / 2,
+				),
+				mock_msg_queue::pallet::Error::<parachain::Runtime>::WeightOverLimit
+			);
+			assert_ok!(parachain::MsgQueue::service_overweight(
+				parachain::RuntimeOrigin::root(),
+				0,
+				weight_per_message,
+			));
+			assert!(parachain::MsgQueue::overweight(0).is_none());
+			assert_eq!(parachain::MsgQueue::received_xcmp(ParaId::from(1)).len(), 1);
+			assert_eq!(
+				ParachainAssets::balance(0, sibling_sovereign_account_id(1)),
+				INITIAL_BALANCE - message_fee
+			);
+		});
+		assert_para_event!(ParaB, MsgQueue::OverweightServiced(0, _));
+	}
+
+	/// Scenario:
+	/// Parachain B sends a XCM to its sibling Parachain A without paying for its execution.
+	/// Parachain A only executes unpaid messages from the relay chain, so the barrier rejects the
+	/// message.
+	#[test]
+	fn unpaid_execution_on_sibling() {
+		MockNet::reset();
+		let mark = event_mark::<ParaA>();
+
+		let message: Xcm<()> =
+			Xcm(vec![UnpaidExecution { weight_limit: Unlimited, check_origin: None }, ClearOrigin]);
+		ParaB::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(1)), message.clone()));
+		});
+
+		assert_para_event!(ParaA, since mark, MsgQueue::Fail(_, XcmError::Barrier));
+	}
+
+	/// Scenario:
+	/// Parachain A sends a XCM to its sibling Parachain B over XCMP.
+	/// Execution is paid with the relay token held by the sovereign account of Parachain A on
+	/// Parachain B.
+	#[test]
+	fn buy_execution_on_sibling() {
+		MockNet::reset();
+		let message_fee = estimate_message_fee(3);
+		let message: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Parent, message_fee).into()),
+			BuyExecution {
+				fees: (Parent, message_fee).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 {
+					network: None,
+					id: sibling_sovereign_account_id(1).into(),
+				}
+				.into(),
+			},
+		]);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message.clone()));
+		});
+
+		ParaB::execute_with(|| {
+			assert_eq!(
+				parachain::MsgQueue::received_xcmp(ParaId::from(1)),
+				vec![(
+					Xcm::<parachain::RuntimeCall>::from(message),
+					1,
+					Outcome::Complete(parachain::estimate_weight(3))
+				)],
+			);
+			assert_eq!(
+				ParachainAssets::balance(0, sibling_sovereign_account_id(1)),
+				INITIAL_BALANCE - message_fee
+			);
+		});
+	}
+
+	/// Scenario:
+	/// Parachain B sends a XCM to Parachain A and pays for its execution with the native token of
+	/// Parachain A instead of the relay token.
+	/// The fee is deposited in the treasury account of Parachain A.
+	#[test]
+	fn buy_execution_in_native_token() {
+		MockNet::reset();
+		let amount = 10 * CENTS;
+		let message_fee = ParaA::execute_with(|| {
+			parachain::estimate_fee_for_weight_in(Here, parachain::estimate_weight(3))
+				.expect("fees can be paid in the native token")
+		});
+		let message: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Here, amount).into()),
+			BuyExecution { fees: (Here, message_fee).into(), weight_limit: Unlimited },
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 { network: None, id: BOB.into() }.into(),
+			},
+		]);
+
+		ParaB::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(1)), message.clone()));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(
+				ParachainBalances::free_balance(sibling_sovereign_account_id(2)),
+				INITIAL_BALANCE - amount
+			);
+			assert_eq!(
+				ParachainBalances::free_balance(BOB),
+				INITIAL_BALANCE + amount - message_fee
+			);
+			assert_eq!(
+				ParachainBalances::free_balance(parachain::TreasuryAccount::get()),
+				INITIAL_BALANCE + message_fee
+			);
+		});
+	}
+
+	/// Scenario:
+	/// ALICE executes a XCM on Parachain A and pays for its execution with the derivative of the
+	/// native token of Parachain B.
+	/// The fee is deposited in the treasury account of Parachain A.
+	#[test]
+	fn buy_execution_in_sibling_token() {
+		MockNet::reset();
+
+		ParaA::execute_with(|| {
+			let weight = parachain::estimate_weight(3);
+			let fee = parachain::estimate_fee_for_weight_in((Parent, Parachain(2)), weight)
+				.expect("fees can be paid in the tokens of siblings");
+			let sibling_token: MultiLocation = (Parent, Parachain(2)).into();
+			let message = Xcm(vec![
+				WithdrawAsset((sibling_token, fee).into()),
+				BuyExecution { fees: (sibling_token, fee).into(), weight_limit: Unlimited },
+				DepositAsset {
+					assets: All.into(),
+					beneficiary: AccountId32 { network: None, id: BOB.into() }.into(),
+				},
+			]);
+
+			assert_ok!(ParachainPalletXcm::execute(
+				parachain::RuntimeOrigin::signed(ALICE),
+				Box::new(xcm::VersionedXcm::from(message)),
+				weight,
+			));
+
+			assert_eq!(ParachainAssets::balance(2, ALICE), INITIAL_BALANCE - fee);
+			assert_eq!(ParachainAssets::balance(2, parachain::TreasuryAccount::get()), fee);
+		});
+	}
+
+	/// Scenario:
+	/// ALICE transfers relay native tokens from parachain A to parachain B, with the relay chain as
+	/// reserve.
+	/// The fees of every hop are estimated from the message instead of counting instructions.
+	#[test]
+	fn estimate_fees_per_hop() {
+		let message: Xcm<parachain::RuntimeCall> = Xcm(vec![
+			WithdrawAsset((Parent, 50 * CENTS).into()),
+			BuyExecution { fees: (Parent, 10 * CENTS).into(), weight_limit: Unlimited },
+			InitiateReserveWithdraw {
+				assets: All.into(),
+				reserve: Parent.into(),
+				xcm: Xcm(vec![
+					BuyExecution { fees: (Here, 10 * CENTS).into(), weight_limit: Unlimited },
+					DepositReserveAsset {
+						assets: All.into(),
+						dest: Parachain(2).into(),
+						xcm: Xcm(vec![
+							BuyExecution {
+								fees: (Parent, 10 * CENTS).into(),
+								weight_limit: Unlimited,
+							},
+							DepositAsset {
+								assets: All.into(),
+								beneficiary: AccountId32 { id: ALICE.into(), network: None }.into(),
+							},
+						]),
+					},
+				]),
+			},
+		]);
+
+		assert_eq!(parachain::estimate_fee_for(&message), estimate_message_fee(3));
+		assert_eq!(
+			estimate_fees(Chain::Para(1), &message),
+			vec![
+				HopFee {
+					chain: Chain::Para(1),
+					weight: parachain::estimate_weight(3),
+					fee: estimate_message_fee(3),
+				},
+				// `WithdrawAsset` and `ClearOrigin` are prepended by `InitiateReserveWithdraw`
+				HopFee {
+					chain: Chain::Relay,
+					weight: relay_chain::estimate_message_weight(4),
+					fee: relay_chain::estimate_message_fee(4),
+				},
+				// `ReserveAssetDeposited` and `ClearOrigin` are prepended by `DepositReserveAsset`
+				HopFee {
+					chain: Chain::Para(2),
+					weight: parachain::estimate_weight(4),
+					fee: estimate_message_fee(4),
+				},
+			],
+		);
+	}
+
+	/// Scenario:
+	/// Parachain A sends a XCM to the relay chain that clears its origin before withdrawing the
+	/// fees. The linter reports that no barrier lets the program through, and the barrier of the
+	/// relay chain rejects it. The same program, in the order the barrier expects, has no
+	/// diagnostics.
+	#[test]
+	fn lint_program_rejected_by_barrier() {
+		MockNet::reset();
+
+		let message_fee = relay_chain::estimate_message_fee(3);
+		let withdraw = WithdrawAsset((Here, message_fee).into());
+		let buy_execution =
+			BuyExecution { fees: (Here, message_fee).into(), weight_limit: Unlimited };
+		let message: Xcm<()> = Xcm(vec![ClearOrigin, withdraw.clone(), buy_execution.clone()]);
+
+		let diagnostics = lint(&message);
+		assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+		assert_eq!((diagnostics[0].path.clone(), diagnostics[0].lint), (vec![0], Lint::Barrier));
+
+		let mark = event_mark::<Relay>();
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message));
+		});
+
+		expect_relay_processed(mark, false);
+
+		let reordered: Xcm<()> = Xcm(vec![withdraw, ClearOrigin, buy_execution]);
+		assert_eq!(lint(&reordered), vec![]);
+	}
+
+	/// Scenario:
+	/// Parachain A sends a XCM to Parachain B that buys its execution with more than it withdraws.
+	/// The linter reports the fees, and the executor of Parachain B fails with `NotHoldingFees`.
+	#[test]
+	fn lint_fees_larger_than_holding() {
+		MockNet::reset();
+		let mark = event_mark::<ParaB>();
+
+		let message_fee = estimate_message_fee(3);
+		let message: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Parent, message_fee).into()),
+			BuyExecution { fees: (Parent, 2 * message_fee).into(), weight_limit: Unlimited },
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 { network: None, id: ALICE.into() }.into(),
+			},
+		]);
+
+		let diagnostics = lint(&message);
+		assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+		assert_eq!(
+			(diagnostics[0].path.clone(), diagnostics[0].lint),
+			(vec![1], Lint::FeesExceedHolding)
+		);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message));
+		});
+
+		assert_para_event!(ParaB, since mark, MsgQueue::Fail(_, XcmError::NotHoldingFees));
+	}
 }
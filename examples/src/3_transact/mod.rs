@@ -105,4 +105,49 @@ mod tests {
 			assert_eq!(relay_chain::Uniques::owner(1u32, 1u32), Some(ALICE));
 		});
 	}
+
+	/// Scenario:
+	/// Parachain(1) mints an NFT on the relay chain in a collection it already owns.
+	/// The collection is part of the genesis of the relay chain instead of being created by the
+	/// message.
+	#[test]
+	fn transact_mint_nft_in_genesis_collection() {
+		MockNet::reset_with(default_mock_net().relay(GenesisSpec::relay_default().collection(
+			UniquesCollection {
+				id: 1,
+				owner: parachain_sovereign_account_id(1),
+				items: vec![(1, BOB)],
+			},
+		)));
+
+		let message_fee = relay_chain::estimate_message_fee(3);
+		let mint_nft_weight_estimation = Weight::from_parts(1_000_000_000, 10_000);
+		let fees = message_fee + relay_chain::estimate_fee_for_weight(mint_nft_weight_estimation);
+
+		let mint =
+			relay_chain::RuntimeCall::Uniques(pallet_uniques::Call::<relay_chain::Runtime>::mint {
+				collection: 1u32,
+				item: 2u32,
+				owner: ALICE,
+			});
+
+		let message = Xcm(vec![
+			WithdrawAsset((Here, fees).into()),
+			BuyExecution { fees: (Here, fees).into(), weight_limit: WeightLimit::Unlimited },
+			Transact {
+				origin_kind: OriginKind::SovereignAccount,
+				require_weight_at_most: mint_nft_weight_estimation,
+				call: mint.encode().into(),
+			},
+		]);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message.clone()));
+		});
+
+		Relay::execute_with(|| {
+			assert_eq!(relay_chain::Uniques::owner(1u32, 1u32), Some(BOB));
+			assert_eq!(relay_chain::Uniques::owner(1u32, 2u32), Some(ALICE));
+		});
+	}
 }
@@ -3,6 +3,7 @@ mod tests {
 	use crate::simple_test_net::*;
 	use frame_support::{assert_ok, pallet_prelude::Weight};
 	use xcm::latest::prelude::*;
+	use xcm_executor::traits::Convert;
 	use xcm_simulator::TestExt;
 
 	const QUERY_ID: u64 = 1234;
@@ -11,6 +12,7 @@ mod tests {
 	#[test]
 	fn descend_origin() {
 		MockNet::reset();
+		let mark = event_mark::<Relay>();
 		ParaA::execute_with(|| {
 			let message_fee = parachain::estimate_message_fee(6);
 			let message = Xcm(vec![
@@ -33,12 +35,170 @@ mod tests {
 			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message.clone(),));
 		});
 
-		Relay::execute_with(|| {
-			assert!(relay_successful_execution());
-		});
+		expect_relay_processed(mark, true);
 
 		// Check that message queue is empty.
 		// The ExpectOrigin instruction passed so we should not receive an error response.
 		ParaA::execute_with(|| assert_eq!(parachain::MsgQueue::received_dmp(), vec![]));
 	}
+
+	/// Scenario:
+	/// The relay chain derives the sovereign account of ALICE on parachain A by hashing her
+	/// location, when it funds the account at genesis. The registry of derivations maps the
+	/// account back to the location, which the `SovereignAccountOf` of the relay chain could not
+	/// reverse on its own. The registry is kept per chain: parachain B did not derive the account.
+	#[test]
+	fn reverse_sovereign_account() {
+		MockNet::reset();
+
+		let account = parachain_account_sovereign_account_id(1, ALICE);
+		type RelaySovereignAccountOf = relay_chain::SovereignAccountOf;
+		let location = Relay::execute_with(|| {
+			<RelaySovereignAccountOf as Convert<_, relay_chain::AccountId>>::reverse_ref(&account)
+		});
+		assert!(matches!(
+			location,
+			Ok(MultiLocation { parents: 0, interior: X2(Parachain(1), AccountId32 { id, .. }) })
+				if id == <[u8; 32]>::from(ALICE)
+		));
+		let expected = format!("{} = Parachain(1)/AccountId32(ALICE)", account);
+		assert_eq!(describe_account(Chain::Relay, &account), expected);
+		assert_eq!(location_of(Chain::Para(2), &account), None);
+
+		// Accounts no chain derived are not known
+		let unknown = sp_runtime::AccountId32::new([42u8; 32]);
+		assert_eq!(location_of(Chain::Relay, &unknown), None);
+		assert_eq!(describe_account(Chain::Relay, &unknown), unknown.to_string());
+	}
+
+	/// Scenario:
+	/// Parachain A sends the relay chain a message on behalf of BOB, which withdraws from the
+	/// account of BOB on the relay chain. Executing the message derives the account, so the
+	/// registry of derivations knows which location it stands for, even though the account
+	/// holds no funds and the message fails.
+	#[test]
+	fn record_derivation_during_execution() {
+		MockNet::reset();
+
+		let location =
+			MultiLocation::new(0, X2(Parachain(1), AccountId32 { network: None, id: BOB.into() }));
+		let derived = || {
+			derived_accounts()
+				.into_iter()
+				.any(|(chain, _, derived_from)| chain == Chain::Relay && derived_from == location)
+		};
+		assert!(!derived());
+
+		let mark = event_mark::<Relay>();
+		ParaA::execute_with(|| {
+			let message = Xcm(vec![
+				DescendOrigin(AccountId32 { network: None, id: BOB.into() }.into()),
+				WithdrawAsset((Here, CENTS).into()),
+				BuyExecution { fees: (Here, CENTS).into(), weight_limit: WeightLimit::Unlimited },
+			]);
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message));
+		});
+
+		expect_relay_processed(mark, false);
+		assert!(derived());
+		let account = parachain_account_sovereign_account_id(1, BOB);
+		assert_eq!(location_of(Chain::Relay, &account), Some(location));
+	}
+
+	/// Scenario:
+	/// The sovereign accounts of a few locations, each seen from the chain it is on, on the
+	/// chains of the network, and the converter of `SovereignAccountOf` that derives them.
+	#[test]
+	fn sovereign_accounts_across_chains() {
+		MockNet::reset();
+
+		let alice = AccountId32 { network: None, id: ALICE.into() };
+		let alith = AccountKey20 { network: None, key: ALITH.into() };
+		let cases: Vec<(Chain, Chain, MultiLocation, AnyAccountId, &str)> = vec![
+			(
+				Chain::Relay,
+				Chain::Para(1),
+				Here.into(),
+				parachain_sovereign_account_id(1).into(),
+				"ChildParachainConvertsVia",
+			),
+			(
+				Chain::Relay,
+				Chain::Para(1),
+				alice.into(),
+				parachain_account_sovereign_account_id(1, ALICE).into(),
+				"ForeignChainAliasAccount",
+			),
+			(
+				Chain::Para(2),
+				Chain::Para(1),
+				Here.into(),
+				sibling_sovereign_account_id(1).into(),
+				"SiblingParachainConvertsVia",
+			),
+			(
+				Chain::Para(2),
+				Chain::Para(1),
+				alice.into(),
+				sibling_account_sovereign_account_id(1, ALICE).into(),
+				"ForeignChainAliasAccount",
+			),
+			(
+				Chain::Para(1),
+				Chain::Relay,
+				Here.into(),
+				relay_sovereign_account_id().into(),
+				"ParentIsPreset",
+			),
+			(
+				Chain::Para(1),
+				Chain::Relay,
+				alice.into(),
+				relay_account_sovereign_account_id(ALICE).into(),
+				"ForeignChainAliasAccount",
+			),
+			(
+				Chain::Para(1),
+				Chain::Para(EVM_PARA_ID),
+				alith.into(),
+				sibling_account_key20_sovereign_account_id(ALITH).into(),
+				"ForeignChainAliasAccount",
+			),
+			(
+				Chain::Para(EVM_PARA_ID),
+				Chain::Para(EVM_PARA_ID),
+				alith.into(),
+				ALITH.into(),
+				"AccountKey20Aliases",
+			),
+			(Chain::Para(1), Chain::Para(1), alice.into(), ALICE.into(), "AccountId32Aliases"),
+		];
+
+		for (chain, from, location, account, converter) in cases {
+			let sovereign = sovereign_account_seen_from(chain, from, location)
+				.unwrap_or_else(|e| panic!("{location:?} of {from:?} on {chain:?}: {e:?}"));
+			assert_eq!(sovereign.account, account, "{location:?} of {from:?} on {chain:?}");
+			assert_eq!(sovereign.converter, converter, "{location:?} of {from:?} on {chain:?}");
+		}
+	}
+
+	/// Scenario:
+	/// A location is reanchored to the chain its sovereign account is derived on, and fails with
+	/// the reanchored location if no converter of that chain matches it.
+	#[test]
+	fn sovereign_account_of_reanchored_location() {
+		MockNet::reset();
+
+		let sovereign =
+			sovereign_account_seen_from(Chain::Para(2), Chain::Para(1), PalletInstance(10));
+		assert_eq!(
+			sovereign,
+			Err(SovereignAccountError::NoConverter(
+				(Parent, Parachain(1), PalletInstance(10)).into()
+			))
+		);
+
+		let sovereign = sovereign_account_on(Chain::Relay, (Parachain(2), PalletInstance(10)));
+		assert!(matches!(sovereign, Err(SovereignAccountError::NoConverter(_))));
+	}
 }
@@ -42,29 +42,23 @@ mod tests {
 		});
 	}
 
-	/// Scenario:
-	/// The relay chain sends an XCM to Parachain A that:
-	/// 1) Withdraws some native assets
-	/// 2) Exchanges these assets for relay chain derivative tokens, with maximal set to true.
-	/// 3) Deposit all the assets that are in the Holding in the account of Alice.
-	///
-	/// NOTE: The implementation of the AssetExchanger is simple
-	/// and in this case swaps all the assets in the exchange for the assets in `give`.
-	/// Depending on the implementation of AssetExchanger, the test results could differ.
-	#[test]
-	fn exchange_asset_maximal_true() {
-		// Exchange contains 10 CENTS worth of parachain A's derivative of the relay token
-		let assets_in_exchange = vec![(Parent, 10 * CENTS).into()];
-		parachain::set_exchange_assets(assets_in_exchange);
+	/// Sets up a pool on Parachain A with 45 CENTS of its native token and 90 CENTS of its
+	/// derivative of the relay token. Without a swap fee, 5 CENTS of the native token buy
+	/// 90 * 5 / (45 + 5) = 9 CENTS of the derivative.
+	fn set_up_pool() {
+		ParaA::execute_with(|| parachain::Dex::set_pool(Here, Parent, 45 * CENTS, 90 * CENTS));
+	}
 
-		let message = Xcm(vec![
+	/// XCM withdrawing 10 CENTS of the native token of Parachain A, exchanging `give` of them
+	/// for `want` of its derivative of the relay token and depositing the holding in ALICE.
+	fn exchange_message(give: u128, want: u128, maximal: bool) -> Xcm<()> {
+		Xcm(vec![
 			UnpaidExecution { weight_limit: WeightLimit::Unlimited, check_origin: None },
 			WithdrawAsset((Here, 10 * CENTS).into()),
-			// Maximal field set to true.
 			ExchangeAsset {
-				give: Definite((Here, 5 * CENTS).into()),
-				want: (Parent, 5 * CENTS).into(),
-				maximal: true,
+				give: Definite((Here, give).into()),
+				want: (Parent, want).into(),
+				maximal,
 			},
 			DepositAsset {
 				assets: AllCounted(2).into(),
@@ -74,15 +68,30 @@ mod tests {
 				}
 				.into(),
 			},
-		]);
+		])
+	}
+
+	/// Scenario:
+	/// The relay chain sends an XCM to Parachain A that:
+	/// 1) Withdraws some native assets
+	/// 2) Exchanges these assets for relay chain derivative tokens, with maximal set to true.
+	/// 3) Deposit all the assets that are in the Holding in the account of Alice.
+	///
+	/// With maximal set to true, all assets in `give` are swapped and `want` is the minimum to
+	/// receive. The exchange is a constant-product pool, so 5 CENTS buy 9 CENTS.
+	#[test]
+	fn exchange_asset_maximal_true() {
+		MockNet::reset();
+		set_up_pool();
 
 		Relay::execute_with(|| {
-			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message.clone()));
+			let message = exchange_message(5 * CENTS, 8 * CENTS, true);
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message));
 		});
 
 		ParaA::execute_with(|| {
-			assert_eq!(parachain::exchange_assets(), vec![(Here, 5 * CENTS).into()].into());
-			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE + 10 * CENTS);
+			assert_eq!(parachain::Dex::reserves(Here, Parent), Some((50 * CENTS, 81 * CENTS)));
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE + 9 * CENTS);
 			assert_eq!(ParachainBalances::free_balance(ALICE), INITIAL_BALANCE + 5 * CENTS);
 		})
 	}
@@ -93,45 +102,74 @@ mod tests {
 	/// 2) Exchanges these assets for relay chain derivative tokens, with maximal set to false.
 	/// 3) Deposit all the assets that are in the Holding in the account of Alice.
 	///
-	/// NOTE: The implementation of the AssetExchanger is simple
-	/// and in this case swaps all the assets in the exchange for the assets in `give`.
-	/// Depending on the implementation of AssetExchanger, the test results could differ.
+	/// With maximal set to false, exactly `want` is bought and the rest of `give` is returned to
+	/// the Holding. 9 CENTS cost 45 * 9 / (90 - 9) = 5 CENTS, so 5 of the 10 CENTS are returned.
 	#[test]
 	fn exchange_asset_maximal_false() {
-		// Exchange contains 10 CENTS worth of parachain A's derivative of the relay token
-		let assets_in_exchange = vec![(Parent, 10 * CENTS).into()];
-		parachain::set_exchange_assets(assets_in_exchange);
-
-		let message = Xcm(vec![
-			UnpaidExecution { weight_limit: WeightLimit::Unlimited, check_origin: None },
-			WithdrawAsset((Here, 10 * CENTS).into()),
-			// Maximal field set to false.
-			ExchangeAsset {
-				give: Definite((Here, 5 * CENTS).into()),
-				want: (Parent, 5 * CENTS).into(),
-				maximal: false,
-			},
-			DepositAsset {
-				assets: AllCounted(2).into(),
-				beneficiary: AccountId32 {
-					network: Some(parachain::RelayNetwork::get()),
-					id: ALICE.into(),
-				}
-				.into(),
-			},
-		]);
+		MockNet::reset();
+		set_up_pool();
 
 		Relay::execute_with(|| {
-			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message.clone()));
+			let message = exchange_message(10 * CENTS, 9 * CENTS, false);
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message));
 		});
 
 		ParaA::execute_with(|| {
-			assert_eq!(
-				parachain::exchange_assets(),
-				vec![(Parent, 5 * CENTS).into(), (Here, 5 * CENTS).into()].into()
-			);
-			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE + 5 * CENTS);
+			assert_eq!(parachain::Dex::reserves(Here, Parent), Some((50 * CENTS, 81 * CENTS)));
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE + 9 * CENTS);
 			assert_eq!(ParachainBalances::free_balance(ALICE), INITIAL_BALANCE + 5 * CENTS);
 		})
 	}
+
+	/// Scenario:
+	/// Same as `exchange_asset_maximal_true`, but Parachain A wants at least 10 CENTS for the
+	/// 5 CENTS it gives. The pool only gives 9 CENTS, so the exchange is rejected and the
+	/// execution stops with a `NoDeal` error: the pool is unchanged and ALICE receives nothing.
+	#[test]
+	fn exchange_asset_maximal_true_slippage() {
+		MockNet::reset();
+		set_up_pool();
+
+		Relay::execute_with(|| {
+			let message = exchange_message(5 * CENTS, 10 * CENTS, true);
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::Dex::reserves(Here, Parent), Some((45 * CENTS, 90 * CENTS)));
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE);
+			assert_eq!(ParachainBalances::free_balance(ALICE), INITIAL_BALANCE);
+		});
+		assert_para_event!(ParaA, Dex::SwapRejected { .. });
+		assert_para_event!(
+			ParaA,
+			MsgQueue::ExecutedDownward(_, Outcome::Incomplete(_, XcmError::NoDeal))
+		);
+	}
+
+	/// Scenario:
+	/// Same as `exchange_asset_maximal_false`, but Parachain A only gives 4 CENTS for the 9 CENTS
+	/// it wants, which cost 5 CENTS. The exchange is rejected and the execution stops with a
+	/// `NoDeal` error: the pool is unchanged and ALICE receives nothing.
+	#[test]
+	fn exchange_asset_maximal_false_slippage() {
+		MockNet::reset();
+		set_up_pool();
+
+		Relay::execute_with(|| {
+			let message = exchange_message(4 * CENTS, 9 * CENTS, false);
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(1), message));
+		});
+
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::Dex::reserves(Here, Parent), Some((45 * CENTS, 90 * CENTS)));
+			assert_eq!(ParachainAssets::balance(0, &ALICE), INITIAL_BALANCE);
+			assert_eq!(ParachainBalances::free_balance(ALICE), INITIAL_BALANCE);
+		});
+		assert_para_event!(ParaA, Dex::SwapRejected { .. });
+		assert_para_event!(
+			ParaA,
+			MsgQueue::ExecutedDownward(_, Outcome::Incomplete(_, XcmError::NoDeal))
+		);
+	}
 }
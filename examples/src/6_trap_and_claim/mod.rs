@@ -74,4 +74,74 @@ mod tests {
 			)
 		});
 	}
+
+	/// Scenario:
+	/// The message of `trap_and_claim_assets`, with an error handler reporting the error to
+	/// Parachain A, is dry-run on the relay chain instead of being sent.
+	/// The registers after every instruction show the withdrawn assets in the holding register,
+	/// then the error of `Trap`, and the error handler sends the report. Nothing is executed or
+	/// sent for real.
+	#[test]
+	#[cfg(feature = "dry-run")]
+	fn dry_run_trapped_assets() {
+		MockNet::reset();
+
+		let report = QueryResponseInfo {
+			destination: Parachain(1).into(),
+			query_id: QUERY_ID,
+			max_weight: Weight::zero(),
+		};
+		let message = Xcm(vec![
+			UnpaidExecution { weight_limit: WeightLimit::Unlimited, check_origin: None },
+			WithdrawAsset((Here, 10 * CENTS).into()),
+			SetErrorHandler(Xcm(vec![ReportError(report.clone())])),
+			Trap(0),
+			DepositAsset {
+				assets: All.into(),
+				beneficiary: AccountId32 { network: None, id: ALICE.into() }.into(),
+			},
+		]);
+		let sovereign_account = parachain_sovereign_account_id(1);
+
+		let run = Relay::execute_with(|| dry_run::<relay_chain::XcmConfig>(Parachain(1), message));
+
+		assert_eq!(run.barrier, Ok(()));
+		let executed: Vec<(Program, usize)> =
+			run.steps.iter().map(|step| (step.program, step.index)).collect();
+		assert_eq!(
+			executed,
+			vec![
+				(Program::Message, 0),
+				(Program::Message, 1),
+				(Program::Message, 2),
+				(Program::Message, 3),
+				(Program::ErrorHandler, 0),
+			]
+		);
+		assert_eq!(run.steps[1].registers.holding, (Here, 10 * CENTS).into());
+		assert_eq!(run.steps[1].registers.origin, Some(Parachain(1).into()));
+		assert_eq!(run.steps[2].registers.error_handler, Xcm(vec![ReportError(report)]));
+		assert_eq!(run.steps[3].result, Err(XcmError::Trap(0)));
+		assert_eq!(run.steps[3].registers.error, Some((3, XcmError::Trap(0))));
+		assert_eq!(run.steps[4].registers.holding, (Here, 10 * CENTS).into());
+		assert_eq!(run.sent.len(), 1);
+		let (destination, response) = &run.sent[0];
+		assert_eq!(*destination, Parachain(1).into());
+		assert!(matches!(
+			response.0[..],
+			[QueryResponse {
+				query_id: QUERY_ID,
+				response: Response::ExecutionResult(Some((3, XcmError::Trap(0)))),
+				..
+			}]
+		));
+		assert!(matches!(run.outcome, Outcome::Incomplete(_, XcmError::Trap(0))));
+
+		Relay::execute_with(|| {
+			assert_eq!(RelaychainBalances::free_balance(sovereign_account), INITIAL_BALANCE);
+		});
+		ParaA::execute_with(|| {
+			assert_eq!(parachain::MsgQueue::received_dmp(), vec![]);
+		});
+	}
 }
@@ -272,4 +272,57 @@ mod tests {
 			);
 		});
 	}
+
+	/// Scenario:
+	/// Parachain expects an error outside of an error handler, where the error register is always
+	/// empty, like in the `expect_error` scenario without `SetErrorHandler`.
+	/// The linter reports the `ExpectError`, and the relay chain fails to execute it with
+	/// `ExpectationFalse`. Within the error handler, it has no diagnostics, and neither has
+	/// `ExpectError(None)` outside of it, which always holds there.
+	#[test]
+	fn lint_expect_error_outside_handler() {
+		MockNet::reset();
+
+		let message_fee = relay_chain::estimate_message_fee(3);
+		let expect_error = ExpectError(Some((1, XcmError::VersionIncompatible)));
+		let message: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Here, message_fee).into()),
+			BuyExecution { fees: (Here, message_fee).into(), weight_limit: WeightLimit::Unlimited },
+			expect_error.clone(),
+		]);
+
+		let diagnostics = lint(&message);
+		assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+		assert_eq!(
+			(diagnostics[0].path.clone(), diagnostics[0].lint),
+			(vec![2], Lint::ExpectErrorOutsideHandler)
+		);
+		assert_eq!(
+			diagnostics[0].to_string(),
+			"warning[ExpectErrorOutsideHandler] at #2: the error register is only set in error \
+			handlers and appendices"
+		);
+
+		let mark = event_mark::<Relay>();
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message));
+		});
+
+		expect_relay_processed(mark, false);
+
+		let handled: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Here, message_fee).into()),
+			BuyExecution { fees: (Here, message_fee).into(), weight_limit: WeightLimit::Unlimited },
+			SetErrorHandler(Xcm(vec![expect_error])),
+			Trap(1),
+		]);
+		assert_eq!(lint(&handled), vec![]);
+
+		let no_error: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Here, message_fee).into()),
+			BuyExecution { fees: (Here, message_fee).into(), weight_limit: WeightLimit::Unlimited },
+			ExpectError(None),
+		]);
+		assert_eq!(lint(&no_error), vec![]);
+	}
 }
@@ -8,18 +8,29 @@ mod tests {
 	use xcm_simulator::TestExt;
 
 	const AMOUNT: u128 = 50 * CENTS;
-	/// Arbitrary query id
-	const QUERY_ID: u64 = 1234;
+	/// Block number after which the queries of the examples time out.
+	const TIMEOUT: u64 = 100;
+
+	/// Registers a query on Parachain A, expecting a response from the relay chain.
+	///
+	/// The relay chain responds with Parachain A as the querier, which is `Here` from the point of
+	/// view of Parachain A.
+	fn new_query() -> QueryId {
+		ParaA::execute_with(|| ParachainPalletXcm::new_query(Parent, TIMEOUT, Here))
+	}
 
 	/// Scenario:
 	/// A parachain wants to be notified that a transfer worked correctly.
-	/// It sends a `ReportHolding` after the deposit to get notified on success.
+	/// It registers a query with `pallet_xcm` and sends a `ReportHolding` after the deposit to get
+	/// notified on success.
 	///
-	/// Asserts that the balances are updated correctly and the expected XCM is sent.
+	/// Asserts that the balances are updated correctly and that the query is ready with the
+	/// expected response.
 	#[test]
 	fn query_holding() {
 		MockNet::reset();
 
+		let query_id = new_query();
 		let fee_in_relay = relay_chain::estimate_message_fee(4);
 
 		// Send a message which succeeds to the relay chain.
@@ -35,7 +46,7 @@ mod tests {
 				ReportHolding {
 					response_info: QueryResponseInfo {
 						destination: Parachain(1).into(),
-						query_id: QUERY_ID,
+						query_id,
 						max_weight: Weight::from_all(0),
 					},
 					assets: All.into(),
@@ -59,18 +70,13 @@ mod tests {
 			);
 		});
 
-		// Check that QueryResponse message was received
+		// Check that the query is ready with the response
 		ParaA::execute_with(|| {
 			assert_eq!(
-				parachain::MsgQueue::received_dmp(),
-				vec![Xcm(vec![QueryResponse {
-					query_id: QUERY_ID,
-					response: Response::Assets(
-						(Parent, AMOUNT - (AMOUNT - 5 * CENTS) - fee_in_relay).into()
-					),
-					max_weight: Weight::from_all(0),
-					querier: Some(Here.into()),
-				}])],
+				query_response::<parachain::Runtime>(query_id),
+				Some(Response::Assets(
+					(Parent, AMOUNT - (AMOUNT - 5 * CENTS) - fee_in_relay).into()
+				)),
 			);
 		});
 	}
@@ -85,6 +91,8 @@ mod tests {
 	fn query_pallet() {
 		MockNet::reset();
 
+		let query_id = new_query();
+
 		let fee_in_relay = relay_chain::estimate_message_fee(3);
 
 		ParaA::execute_with(|| {
@@ -98,7 +106,7 @@ mod tests {
 					module_name: "pallet_balances".into(),
 					response_info: QueryResponseInfo {
 						destination: Parachain(1).into(),
-						query_id: QUERY_ID,
+						query_id,
 						max_weight: Weight::from_all(0),
 					},
 				},
@@ -108,17 +116,11 @@ mod tests {
 		});
 
 		ParaA::execute_with(|| {
+			let balances_info =
+				PalletInfo::new(1, "Balances".into(), "pallet_balances".into(), 4, 0, 0).unwrap();
 			assert_eq!(
-				parachain::MsgQueue::received_dmp(),
-				vec![Xcm(vec![QueryResponse {
-					query_id: QUERY_ID,
-					response: Response::PalletsInfo(BoundedVec::truncate_from(vec![
-						PalletInfo::new(1, "Balances".into(), "pallet_balances".into(), 4, 0, 0)
-							.unwrap()
-					])),
-					max_weight: Weight::from_all(0),
-					querier: Some(Here.into()),
-				}])],
+				query_response::<parachain::Runtime>(query_id),
+				Some(Response::PalletsInfo(BoundedVec::truncate_from(vec![balances_info]))),
 			);
 		})
 	}
@@ -133,6 +135,8 @@ mod tests {
 	fn report_error() {
 		MockNet::reset();
 
+		let query_id = new_query();
+
 		let fee_in_relay =
 			relay_chain::estimate_message_fee(4) + relay_chain::estimate_message_fee(1);
 
@@ -145,7 +149,7 @@ mod tests {
 			// Set the Error Handler to report back status of Error register.
 			SetErrorHandler(Xcm(vec![ReportError(QueryResponseInfo {
 				destination: Parachain(1).into(),
-				query_id: QUERY_ID,
+				query_id,
 				max_weight: Weight::from_all(0),
 			})])),
 			Trap(1u64), // Error is thrown on index 3
@@ -159,13 +163,8 @@ mod tests {
 
 		ParaA::execute_with(|| {
 			assert_eq!(
-				parachain::MsgQueue::received_dmp(),
-				vec![Xcm(vec![QueryResponse {
-					query_id: QUERY_ID,
-					response: Response::ExecutionResult(Some((index_of_error, XcmError::Trap(1)))),
-					max_weight: Weight::from_all(0),
-					querier: Some(Here.into()),
-				}])],
+				query_response::<parachain::Runtime>(query_id),
+				Some(Response::ExecutionResult(Some((index_of_error, XcmError::Trap(1))))),
 			);
 		});
 	}
@@ -177,6 +176,8 @@ mod tests {
 	fn report_transact_status() {
 		MockNet::reset();
 
+		let query_id = new_query();
+
 		// Runtime call dispatched by the Transact instruction
 		let call = relay_chain::RuntimeCall::System(
 			frame_system::Call::<relay_chain::Runtime>::remark_with_event {
@@ -201,7 +202,7 @@ mod tests {
 			},
 			ReportTransactStatus(QueryResponseInfo {
 				destination: Parachain(1).into(),
-				query_id: QUERY_ID,
+				query_id,
 				max_weight: Weight::from_all(0),
 			}),
 		]);
@@ -212,14 +213,59 @@ mod tests {
 
 		ParaA::execute_with(|| {
 			assert_eq!(
-				parachain::MsgQueue::received_dmp(),
-				vec![Xcm(vec![QueryResponse {
-					query_id: QUERY_ID,
-					response: Response::DispatchResult(MaybeErrorCode::Success),
+				query_response::<parachain::Runtime>(query_id),
+				Some(Response::DispatchResult(MaybeErrorCode::Success)),
+			);
+		});
+	}
+
+	/// Scenario:
+	/// Parachain A wants to be notified of the response to a `QueryPallet` instead of checking
+	/// the status of the query. It registers the query with a call of its `Notifier` pallet,
+	/// which `pallet_xcm` dispatches with the response when the response arrives.
+	///
+	/// Asserts that the notifier received the response and that the query is removed.
+	#[test]
+	fn notify_query_response() {
+		MockNet::reset();
+
+		let query_id = ParaA::execute_with(|| {
+			let notify = parachain::Notifier::notify_call();
+			ParachainPalletXcm::new_notify_query(Parent, notify, TIMEOUT, Here)
+		});
+		let fee_in_relay = relay_chain::estimate_message_fee(3);
+
+		let message = Xcm(vec![
+			WithdrawAsset((Here, fee_in_relay).into()),
+			BuyExecution {
+				fees: (Here, fee_in_relay).into(),
+				weight_limit: WeightLimit::Unlimited,
+			},
+			QueryPallet {
+				module_name: "pallet_balances".into(),
+				response_info: QueryResponseInfo {
+					destination: Parachain(1).into(),
+					query_id,
 					max_weight: Weight::from_all(0),
-					querier: Some(Here.into()),
-				}])],
+				},
+			},
+		]);
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, message.clone()));
+		});
+
+		ParaA::execute_with(|| {
+			let balances_info =
+				PalletInfo::new(1, "Balances".into(), "pallet_balances".into(), 4, 0, 0).unwrap();
+			assert_eq!(
+				parachain::Notifier::notified(query_id),
+				Some((
+					Parent.into(),
+					Response::PalletsInfo(BoundedVec::truncate_from(vec![balances_info]))
+				)),
 			);
+			assert_eq!(ParachainPalletXcm::query(query_id), None);
 		});
 	}
 }
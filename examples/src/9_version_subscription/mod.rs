@@ -2,7 +2,8 @@
 mod tests {
 	use crate::simple_test_net::*;
 	use frame_support::{assert_ok, pallet_prelude::Weight};
-	use xcm::latest::prelude::*;
+	use polkadot_parachain::primitives::Id as ParaId;
+	use xcm::{latest::prelude::*, VersionedMultiLocation};
 	use xcm_simulator::TestExt;
 
 	/// Scenario:
@@ -42,4 +43,99 @@ mod tests {
 			assert_ok!(ParachainPalletXcm::send_xcm(Here, Parent, unsub_message));
 		});
 	}
+
+	/// The default network, in which parachain A wraps its messages in XCM v2 for destinations
+	/// whose version it does not know, and parachain B only speaks XCM v2.
+	fn mixed_versions_net() -> TestNetBuilder {
+		let default = default_mock_net();
+		TestNetBuilder::new()
+			.relay(GenesisSpec::relay_default())
+			.parachain_with_genesis(
+				1,
+				ParaKind::Parachain,
+				default.para_genesis(1).safe_xcm_version(2),
+			)
+			.parachain_with_genesis(2, ParaKind::Parachain, default.para_genesis(2).xcm_version(2))
+			.parachain(3, ParaKind::Parachain)
+			.parachain(ASSET_HUB_PARA_ID, ParaKind::AssetHub)
+			.parachain(BRIDGE_HUB_PARA_ID, ParaKind::BridgeHub)
+			.parachain(EVM_PARA_ID, ParaKind::Evm)
+	}
+
+	/// Scenario:
+	/// Parachain A subscribes to the XCM version of parachain B, which only speaks XCM v2.
+	/// The subscription is sent in the safe version of parachain A, v2, and parachain B answers
+	/// with its version. From then on, parachain A downgrades the messages it sends to parachain
+	/// B to v2, and refuses to send the messages v2 cannot express.
+	#[test]
+	fn downgrade_to_supported_version() {
+		MockNet::reset_with(mixed_versions_net());
+
+		ParaA::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::force_subscribe_version_notify(
+				parachain::RuntimeOrigin::root(),
+				Box::new(VersionedMultiLocation::from(MultiLocation::new(1, X1(Parachain(2))))),
+			));
+		});
+
+		assert_para_event!(ParaA, PolkadotXcm::SupportedVersionChanged(_, 2));
+		ParaA::execute_with(|| {
+			assert_eq!(supported_version::<parachain::Runtime>((Parent, Parachain(2))), Some(2));
+
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				(Parent, Parachain(2)),
+				Xcm(vec![Trap(1)])
+			));
+			// `UnpaidExecution` was introduced in v3
+			let message =
+				Xcm(vec![UnpaidExecution { weight_limit: Unlimited, check_origin: None }]);
+			assert_eq!(
+				ParachainPalletXcm::send_xcm(Here, (Parent, Parachain(2)), message),
+				Err(SendError::DestinationUnsupported)
+			);
+		});
+
+		ParaB::execute_with(|| {
+			let received = parachain::MsgQueue::received_xcmp(ParaId::from(1));
+			let (message, _, _) = received.last().expect("parachain B received the message");
+			assert_eq!(*message, Xcm(vec![Trap(1)]));
+		});
+
+		// The downgraded messages are traced with their outcome, the barrier of parachain B
+		// stopping the one without `BuyExecution`
+		let trace = trace();
+		assert!(trace.0.iter().all(|hop| hop.outcome.is_some()), "{trace:?}");
+		let hop = trace.0.iter().find(|hop| hop.message == Xcm(vec![Trap(1)])).unwrap();
+		assert_eq!((hop.from, hop.to), (Chain::Para(1), Chain::Para(2)));
+		assert_eq!(hop.outcome, Some(Outcome::Error(XcmError::Barrier)));
+	}
+
+	/// Scenario:
+	/// The relay chain and parachain C send parachain B messages in XCM v3, the latest version,
+	/// as they do not know the version of parachain B.
+	/// Parachain B only speaks XCM v2, so its message queue rejects them.
+	#[test]
+	fn reject_unsupported_version() {
+		MockNet::reset_with(mixed_versions_net());
+
+		Relay::execute_with(|| {
+			assert_ok!(RelaychainPalletXcm::send_xcm(Here, Parachain(2), Xcm(vec![Trap(1)])));
+		});
+		assert_para_event!(ParaB, MsgQueue::UnsupportedVersion(_));
+
+		ParaC::execute_with(|| {
+			assert_ok!(ParachainPalletXcm::send_xcm(
+				Here,
+				(Parent, Parachain(2)),
+				Xcm(vec![Trap(2)])
+			));
+		});
+		assert_para_event!(ParaB, MsgQueue::BadVersion(_));
+
+		ParaB::execute_with(|| {
+			assert!(parachain::MsgQueue::received_dmp().is_empty());
+			assert!(parachain::MsgQueue::received_xcmp(ParaId::from(3)).is_empty());
+		});
+	}
 }
@@ -0,0 +1,106 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decodes and pretty-prints hex-encoded `VersionedXcm`, `VersionedMultiLocation` and
+//! `VersionedMultiAssets` values, like the ones shown by chain explorers.
+
+use std::{error::Error, fmt::Debug, io::Read, process::ExitCode};
+
+use codec::Decode;
+use xcm_examples::{
+	asset_hub, bridge_hub, evm_parachain,
+	inspect::{decode_call, from_hex, Decoded, Kind},
+	parachain, relay_chain,
+};
+
+const USAGE: &str = "\
+usage: xcm-inspect <xcm|location|assets> [HEX|-] [--to-version N] [--calls CHAIN]
+
+Decodes HEX, or the hex read from stdin when it is `-` or missing, and prints the value and its
+encoding.
+
+options:
+  --to-version N  converts the value to the XCM version N before encoding it
+  --calls CHAIN   decodes the calls of the `Transact` instructions as calls of the runtime mock
+                  of CHAIN: relay, parachain, asset-hub, bridge-hub or evm-parachain";
+
+fn main() -> ExitCode {
+	match run(std::env::args().skip(1)) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(error) => {
+			eprintln!("error: {error}\n\n{USAGE}");
+			ExitCode::FAILURE
+		},
+	}
+}
+
+fn run(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn Error>> {
+	let kind = match args.next() {
+		Some(help) if help == "-h" || help == "--help" => {
+			println!("{USAGE}");
+			return Ok(())
+		},
+		Some(kind) => kind.parse::<Kind>()?,
+		None => return Err("missing the kind of the value".into()),
+	};
+	let (mut input, mut version, mut chain) = (None, None, None);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--to-version" => version = Some(args.next().ok_or("missing the version")?.parse()?),
+			"--calls" => chain = Some(args.next().ok_or("missing the chain")?),
+			_ if input.is_none() => input = Some(arg),
+			_ => return Err(format!("unexpected argument {arg}").into()),
+		}
+	}
+
+	let hex = match input {
+		Some(hex) if hex != "-" => hex,
+		_ => {
+			let mut hex = String::new();
+			std::io::stdin().read_to_string(&mut hex)?;
+			hex
+		},
+	};
+	let mut decoded = Decoded::decode(kind, &from_hex(&hex)?)?;
+	println!("{decoded}");
+	if let Some(version) = version {
+		decoded = decoded.into_version(version)?;
+		println!("\nas version {version}:\n{decoded}");
+	}
+	println!("\nencoded: {}", decoded.encode_hex());
+
+	if let Some(chain) = chain {
+		for (index, call) in decoded.transact_calls().iter().enumerate() {
+			let call = match chain.as_str() {
+				"relay" => format_call::<relay_chain::RuntimeCall>(call),
+				"parachain" => format_call::<parachain::RuntimeCall>(call),
+				"asset-hub" => format_call::<asset_hub::RuntimeCall>(call),
+				"bridge-hub" => format_call::<bridge_hub::RuntimeCall>(call),
+				"evm-parachain" => format_call::<evm_parachain::RuntimeCall>(call),
+				_ => return Err(format!("unknown chain {chain}").into()),
+			};
+			println!("\ncall #{index}: {call}");
+		}
+	}
+	Ok(())
+}
+
+fn format_call<Call: Decode + Debug>(encoded: &[u8]) -> String {
+	match decode_call::<Call>(encoded) {
+		Ok(call) => format!("{call:#?}"),
+		Err(error) => format!("{error}"),
+	}
+}
@@ -1,3 +1,7 @@
+#[path = "12_bridges/mod.rs"]
+mod bridges;
+#[path = "11_channels/mod.rs"]
+mod channels;
 #[path = "7_expects/mod.rs"]
 mod expects;
 #[path = "2_fees/mod.rs"]
@@ -6,12 +10,16 @@ mod fees;
 mod first_look;
 #[path = "5_holding_modifiers/mod.rs"]
 mod holding_modifiers;
+#[path = "13_locations/mod.rs"]
+mod locations;
 #[path = "10_locks/mod.rs"]
 mod locks;
 #[path = "4_origins/mod.rs"]
 mod origins;
 #[path = "8_queries/mod.rs"]
 mod queries;
+#[path = "14_scenarios/mod.rs"]
+mod scenarios;
 mod simple_test_net;
 #[path = "3_transact/mod.rs"]
 mod transact;
@@ -21,3 +29,8 @@ mod transfers;
 mod trap_and_claim;
 #[path = "9_version_subscription/mod.rs"]
 mod version_subscription;
+
+// For the `xcm-inspect` binary
+pub use simple_test_net::{asset_hub, bridge_hub, evm_parachain, inspect, parachain, relay_chain};
+// For the doctests of the builder
+pub use simple_test_net::program;
@@ -0,0 +1,168 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Registry of the accounts derived from locations by the chains of the simulator.
+//!
+//! The `SovereignAccountOf` of every runtime mock is wrapped in `RecordDerivations`, which
+//! records the location each account was derived from. Most derivations hash the location, so
+//! the registry is the only way back from such an account to its location.
+//!
+//! Derivations are recorded per chain, since locations are relative to the chain that derived
+//! the account: the same account stands for different locations on different chains. A chain
+//! records its derivations within its externalities, and at genesis, see `deriving_on`.
+//! Accounts derived outside of both are not recorded.
+
+use core::{
+	borrow::Borrow,
+	cell::{Cell, RefCell},
+	fmt::Display,
+	marker::PhantomData,
+};
+
+use codec::{Decode, Encode};
+use sp_runtime::traits::Get;
+use xcm::latest::prelude::*;
+use xcm_executor::traits::Convert;
+
+use super::{Chain, ADMIN, ALICE, ALITH, BOB};
+
+thread_local! {
+	/// The chain, encoded account and location of every account derived so far.
+	static DERIVATIONS: RefCell<Vec<(Chain, Vec<u8>, MultiLocation)>> = RefCell::new(Vec::new());
+	/// The chain deriving accounts outside of its externalities, see `deriving_on`.
+	static DERIVING_ON: Cell<Option<Chain>> = Cell::new(None);
+	/// Whether derivations are recorded, see `without_recording`.
+	static RECORDING: Cell<bool> = Cell::new(true);
+}
+
+/// `Converter` of the chain `ThisChain`, recording the location every account it derives comes
+/// from. Accounts `Converter` cannot reverse are looked up in the registry of the chain.
+pub struct RecordDerivations<Converter, ThisChain>(PhantomData<(Converter, ThisChain)>);
+impl<AccountId, Converter, ThisChain> Convert<MultiLocation, AccountId>
+	for RecordDerivations<Converter, ThisChain>
+where
+	AccountId: Encode + Decode + Clone,
+	Converter: Convert<MultiLocation, AccountId>,
+	ThisChain: Get<Chain>,
+{
+	fn convert_ref(location: impl Borrow<MultiLocation>) -> Result<AccountId, ()> {
+		let location = location.borrow();
+		let account = Converter::convert_ref(location)?;
+		let recording = RECORDING.with(Cell::get);
+		if let Some(chain) = deriving_chain::<ThisChain>().filter(|_| recording) {
+			let encoded = account.encode();
+			DERIVATIONS.with(|derivations| {
+				let mut derivations = derivations.borrow_mut();
+				if !derivations.iter().any(|(on, of, _)| *on == chain && *of == encoded) {
+					derivations.push((chain, encoded, *location));
+				}
+			});
+		}
+		Ok(account)
+	}
+
+	fn reverse_ref(account: impl Borrow<AccountId>) -> Result<MultiLocation, ()> {
+		let account = account.borrow();
+		Converter::reverse_ref(account).or_else(|()| {
+			let chain = deriving_chain::<ThisChain>().ok_or(())?;
+			location_of(chain, account).ok_or(())
+		})
+	}
+}
+
+/// The chain deriving accounts: the one set by `deriving_on`, or `ThisChain` within
+/// externalities.
+fn deriving_chain<ThisChain: Get<Chain>>() -> Option<Chain> {
+	DERIVING_ON
+		.with(Cell::get)
+		.or_else(|| sp_externalities::with_externalities(|_| ()).map(|()| ThisChain::get()))
+}
+
+/// Runs `f`, recording the accounts it derives on `chain`. For the genesis of the chains, which
+/// derives accounts before the externalities of the chain exist.
+pub(super) fn deriving_on<R>(chain: Chain, f: impl FnOnce() -> R) -> R {
+	let previous = DERIVING_ON.with(|deriving_on| deriving_on.replace(Some(chain)));
+	let result = f();
+	DERIVING_ON.with(|deriving_on| deriving_on.set(previous));
+	result
+}
+
+/// Runs `f` without recording the accounts it derives, for derivations that cannot tell which
+/// chain they are for.
+pub(super) fn without_recording<R>(f: impl FnOnce() -> R) -> R {
+	let previous = RECORDING.with(|recording| recording.replace(false));
+	let result = f();
+	RECORDING.with(|recording| recording.set(previous));
+	result
+}
+
+/// The location `account` was derived from on `chain`, if `chain` derived it.
+pub fn location_of<AccountId: Encode>(chain: Chain, account: &AccountId) -> Option<MultiLocation> {
+	let encoded = account.encode();
+	DERIVATIONS.with(|derivations| {
+		derivations
+			.borrow()
+			.iter()
+			.find(|(on, of, _)| *on == chain && *of == encoded)
+			.map(|(_, _, location)| *location)
+	})
+}
+
+/// The accounts derived so far, with the chain that derived each, encoded, and the location it
+/// was derived from.
+pub fn derived_accounts() -> Vec<(Chain, Vec<u8>, MultiLocation)> {
+	DERIVATIONS.with(|derivations| derivations.borrow().clone())
+}
+
+/// `account` and the location it was derived from on `chain`, like
+/// `5Gx… = Parachain(1)/AccountId32(ALICE)`, or only `account` if `chain` did not derive it.
+pub fn describe_account<AccountId: Encode + Display>(chain: Chain, account: &AccountId) -> String {
+	match location_of(chain, account) {
+		Some(location) => format!("{} = {}", account, describe_location(&location)),
+		None => account.to_string(),
+	}
+}
+
+/// `location` as a path of junctions, with the well-known accounts of the simulator named.
+pub fn describe_location(location: &MultiLocation) -> String {
+	let parents = core::iter::repeat("Parent".to_string()).take(location.parents as usize);
+	let junctions = location.interior.iter().map(|junction| match junction {
+		Parachain(para_id) => format!("Parachain({})", para_id),
+		AccountId32 { id, .. } => format!("AccountId32({})", describe_key(id)),
+		AccountKey20 { key, .. } => format!("AccountKey20({})", describe_key(key)),
+		GlobalConsensus(network) => format!("GlobalConsensus({:?})", network),
+		junction => format!("{:?}", junction),
+	});
+	let path: Vec<String> = parents.chain(junctions).collect();
+	if path.is_empty() {
+		"Here".to_string()
+	} else {
+		path.join("/")
+	}
+}
+
+fn describe_key(key: &[u8]) -> String {
+	let named: [(&[u8], &str); 4] = [
+		(ADMIN.as_ref(), "ADMIN"),
+		(ALICE.as_ref(), "ALICE"),
+		(BOB.as_ref(), "BOB"),
+		(ALITH.as_bytes(), "ALITH"),
+	];
+	match named.iter().find(|(of, _)| *of == key) {
+		Some((_, name)) => name.to_string(),
+		None => format!("0x{}", hex::encode(key)),
+	}
+}
@@ -16,14 +16,20 @@
 
 //! Asset hub parachain runtime mock.
 
-use super::{mock_msg_queue::pallet as mock_msg_queue, Balance, ForeignChainAliasAccount, UNITS};
-use codec::{Decode, Encode};
+use super::{
+	accounts::RecordDerivations,
+	bridge::{AliasesFromBridgeHub, RemoteConsensusAccount, ThisNetwork},
+	mock_msg_queue::pallet as mock_msg_queue,
+	mock_notifier::pallet as mock_notifier,
+	trader::trader_fee,
+	Balance, ForeignChainAliasAccount, BRIDGE_HUB_PARA_ID,
+};
 use core::marker::PhantomData;
 use frame_support::{
 	construct_runtime, parameter_types,
 	traits::{
-		AsEnsureOriginWithArg, ContainsPair, EnsureOrigin, EnsureOriginWithArg, Everything,
-		EverythingBut, Nothing,
+		AsEnsureOriginWithArg, Contains, ContainsPair, EnsureOrigin, EnsureOriginWithArg,
+		Everything, EverythingBut, PalletInfoAccess,
 	},
 	weights::{
 		constants::{WEIGHT_PROOF_SIZE_PER_MB, WEIGHT_REF_TIME_PER_SECOND},
@@ -32,39 +38,41 @@ use frame_support::{
 };
 use frame_system::{EnsureRoot, EnsureSigned};
 use pallet_xcm::XcmPassthrough;
-use polkadot_parachain::primitives::{
-	DmpMessageHandler, Id as ParaId, Sibling, XcmpMessageFormat, XcmpMessageHandler,
-};
-use polkadot_primitives::BlockNumber as RelayBlockNumber;
+use polkadot_parachain::primitives::Sibling;
 use sp_core::{ConstU128, ConstU32, H256};
 use sp_runtime::{
 	testing::Header,
-	traits::{Get, Hash, IdentityLookup},
+	traits::{Get, IdentityLookup},
 	AccountId32,
 };
 use sp_std::prelude::*;
-use xcm::{latest::prelude::*, VersionedXcm};
+use xcm::latest::prelude::*;
 use xcm_builder::{
-	AccountId32Aliases, AllowTopLevelPaidExecutionFrom, AsPrefixedGeneralIndex, Case,
+	AccountId32Aliases, AllowExplicitUnpaidExecutionFrom, AllowKnownQueryResponses,
+	AllowSubscriptionsFrom, AllowTopLevelPaidExecutionFrom, AsPrefixedGeneralIndex,
 	ConvertedConcreteId, CurrencyAdapter as XcmCurrencyAdapter, EnsureXcmOrigin,
 	FixedRateOfFungible, FixedWeightBounds, FungiblesAdapter, IsConcrete, NativeAsset, NoChecking,
 	NonFungiblesAdapter, ParentAsSuperuser, ParentIsPreset, SiblingParachainConvertsVia,
-	SignedAccountId32AsNative, SignedToAccountId32, SovereignSignedViaLocation,
+	SignedAccountId32AsNative, SignedToAccountId32, SovereignSignedViaLocation, WithComputedOrigin,
 };
 use xcm_executor::{
-	traits::{Convert, JustTry},
+	traits::{Convert, JustTry, WeightBounds},
 	Config, XcmExecutor,
 };
 
 pub type AccountId = AccountId32;
 pub type AssetIdForAssets = u128;
 
-pub type SovereignAccountOf = (
-	ForeignChainAliasAccount<AccountId>,
-	SiblingParachainConvertsVia<Sibling, AccountId>,
-	AccountId32Aliases<RelayNetwork, AccountId>,
-	ParentIsPreset<AccountId>,
-);
+pub type SovereignAccountOf = RecordDerivations<
+	(
+		ForeignChainAliasAccount<AccountId>,
+		SiblingParachainConvertsVia<Sibling, AccountId>,
+		AccountId32Aliases<RelayNetwork, AccountId>,
+		ParentIsPreset<AccountId>,
+		RemoteConsensusAccount<AccountId>,
+	),
+	super::AsParachain<MsgQueue>,
+>;
 
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -113,6 +121,10 @@ impl pallet_balances::Config for Runtime {
 	type WeightInfo = ();
 	type MaxReserves = MaxReserves;
 	type ReserveIdentifier = [u8; 8];
+	type HoldIdentifier = ();
+	type FreezeIdentifier = ();
+	type MaxHolds = ConstU32<0>;
+	type MaxFreezes = ConstU32<0>;
 }
 
 parameter_types! {
@@ -195,11 +207,17 @@ parameter_types! {
 	pub const ReservedDmpWeight: Weight = Weight::from_parts(WEIGHT_REF_TIME_PER_SECOND.saturating_div(4), 0);
 }
 
+pub type RelayNetwork = ThisNetwork;
+
 parameter_types! {
 	pub const KsmLocation: MultiLocation = MultiLocation::parent();
-	pub const TokenLocation: MultiLocation = Here.into_location();
-	pub const RelayNetwork: NetworkId = ByGenesis([0; 32]);
-	pub UniversalLocation: InteriorMultiLocation = Parachain(MsgQueue::parachain_id().into()).into();
+	/// Like on live networks, the native token of the asset hub is the relay chain token.
+	pub const TokenLocation: MultiLocation = MultiLocation::parent();
+	pub UniversalLocation: InteriorMultiLocation =
+		X2(GlobalConsensus(RelayNetwork::get()), Parachain(MsgQueue::parachain_id().into()));
+	pub BridgeHubLocation: MultiLocation = (Parent, Parachain(BRIDGE_HUB_PARA_ID)).into();
+	pub TrustBackedAssetsPalletLocation: MultiLocation =
+		PalletInstance(<Assets as PalletInfoAccess>::index() as u8).into();
 }
 
 pub type XcmOriginToCallOrigin = (
@@ -237,31 +255,34 @@ pub fn estimate_fee_for_weight(weight: Weight) -> u128 {
 		units_per_mb * (weight.proof_size() as u128) / (WEIGHT_PROOF_SIZE_PER_MB as u128)
 }
 
-pub type LocalBalancesTransactor =
-	XcmCurrencyAdapter<Balances, IsConcrete<TokenLocation>, SovereignAccountOf, AccountId, ()>;
+/// Fee charged by the `Trader` for executing `message`, in the relay token, as `BuyExecution`
+/// would charge it.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_fee_for(message: &Xcm<RuntimeCall>) -> u128 {
+	let weight = estimate_weight_for(message);
+	trader_fee::<<XcmConfig as Config>::Trader>(TokensPerSecondPerMegabyte::get().0, weight)
+		.expect("The `Trader` takes fees in the relay token")
+}
 
-pub struct FromMultiLocationToAsset<MultiLocation, AssetId>(
-	core::marker::PhantomData<(MultiLocation, AssetId)>,
-);
-impl Convert<MultiLocation, AssetIdForAssets>
-	for FromMultiLocationToAsset<MultiLocation, AssetIdForAssets>
-{
-	fn convert(value: MultiLocation) -> Result<AssetIdForAssets, MultiLocation> {
-		match value {
-			MultiLocation { parents: 1, interior: Here } => Ok(0 as AssetIdForAssets),
-			MultiLocation { parents: 1, interior: X1(Parachain(para_id)) } =>
-				Ok(para_id as AssetIdForAssets),
-			_ => Err(value),
-		}
-	}
+/// Weight of `message` according to the `Weigher`.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_weight_for(message: &Xcm<RuntimeCall>) -> Weight {
+	<XcmConfig as Config>::Weigher::weight(&mut message.clone())
+		.expect("message has at most `MaxInstructions` instructions")
 }
 
+pub type LocalBalancesTransactor =
+	XcmCurrencyAdapter<Balances, IsConcrete<TokenLocation>, SovereignAccountOf, AccountId, ()>;
+
+/// Assets created in `pallet_assets`, identified by `PalletInstance(<index>)/GeneralIndex(<id>)`.
 pub type AssetsTransactor = FungiblesAdapter<
 	Assets,
 	ConvertedConcreteId<
 		AssetIdForAssets,
 		Balance,
-		FromMultiLocationToAsset<MultiLocation, AssetIdForAssets>,
+		AsPrefixedGeneralIndex<TrustBackedAssetsPalletLocation, AssetIdForAssets, JustTry>,
 		JustTry,
 	>,
 	SovereignAccountOf,
@@ -282,8 +303,24 @@ pub type ForeignUniquesTransactor = NonFungiblesAdapter<
 /// Means for transacting assets on this chain
 pub type AssetTransactors = (LocalBalancesTransactor, AssetsTransactor, ForeignUniquesTransactor);
 
-pub type XcmRouter = super::ParachainXcmRouter<MsgQueue>;
-pub type Barrier = AllowTopLevelPaidExecutionFrom<Everything>;
+pub struct ParentRelay;
+impl Contains<MultiLocation> for ParentRelay {
+	fn contains(location: &MultiLocation) -> bool {
+		location.contains_parents_only(1)
+	}
+}
+
+pub type XcmRouter = super::ParachainRouter<MsgQueue, PolkadotXcm>;
+pub type Barrier = WithComputedOrigin<
+	(
+		AllowExplicitUnpaidExecutionFrom<ParentRelay>,
+		AllowTopLevelPaidExecutionFrom<Everything>,
+		AllowSubscriptionsFrom<Everything>,
+		AllowKnownQueryResponses<PolkadotXcm>,
+	),
+	UniversalLocation,
+	ConstU32<8>,
+>;
 
 parameter_types! {
 	pub NftCollectionOne: MultiAssetFilter
@@ -309,24 +346,31 @@ impl Config for XcmConfig {
 	type Barrier = Barrier;
 	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
 	type Trader = FixedRateOfFungible<TokensPerSecondPerMegabyte, ()>;
-	type ResponseHandler = ();
-	type AssetTrap = ();
+	type ResponseHandler = PolkadotXcm;
+	type AssetTrap = PolkadotXcm;
 	type AssetLocker = PolkadotXcm;
 	type AssetExchanger = ();
-	type AssetClaims = ();
+	type AssetClaims = PolkadotXcm;
 	type SubscriptionService = PolkadotXcm;
 	type PalletInstancesInfo = AllPalletsWithSystem;
 	type FeeManager = ();
 	type MaxAssetsIntoHolding = MaxAssetsIntoHolding;
 	type MessageExporter = ();
-	type UniversalAliases = Nothing;
+	type UniversalAliases = AliasesFromBridgeHub<BridgeHubLocation>;
 	type CallDispatcher = RuntimeCall;
 	type SafeCallFilter = Everything;
 }
 
 impl mock_msg_queue::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
-	type XcmExecutor = XcmExecutor<XcmConfig>;
+	type XcmExecutor = super::TraceExecutor<XcmExecutor<XcmConfig>, super::AsParachain<MsgQueue>>;
+	type ExecuteOverweightOrigin = EnsureRoot<AccountId>;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
+}
+
+impl mock_notifier::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type ResponseOrigin = pallet_xcm::EnsureResponse<Everything>;
 }
 
 pub type LocalOriginToLocation = SignedToAccountId32<RuntimeOrigin, AccountId, RelayNetwork>;
@@ -353,22 +397,25 @@ impl pallet_xcm::Config for Runtime {
 	type ExecuteXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, LocalOriginToLocation>;
 	type XcmExecuteFilter = Everything;
 	type XcmExecutor = XcmExecutor<XcmConfig>;
-	type XcmTeleportFilter = Nothing;
+	type XcmTeleportFilter = Everything;
 	type XcmReserveTransferFilter = Everything;
 	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
 	type UniversalLocation = UniversalLocation;
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
-	type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
 	type Currency = Balances;
 	type CurrencyMatcher = IsConcrete<TokenLocation>;
 	type TrustedLockers = TrustedLockerCase<TrustedLockPairs>;
 	type SovereignAccountOf = SovereignAccountOf;
 	type MaxLockers = ConstU32<8>;
+	type MaxRemoteLockConsumers = ConstU32<0>;
+	type RemoteLockConsumerIdentifier = ();
 	type WeightInfo = pallet_xcm::TestWeightInfo;
 	#[cfg(feature = "runtime-benchmarks")]
 	type ReachableDest = ReachableDest;
+	type AdminOrigin = EnsureRoot<AccountId>;
 }
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
@@ -382,9 +429,10 @@ construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Storage, Config, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		MsgQueue: mock_msg_queue::{Pallet, Storage, Event<T>},
+		MsgQueue: mock_msg_queue::{Pallet, Call, Storage, Event<T>},
 		PolkadotXcm: pallet_xcm::{Pallet, Call, Event<T>, Origin},
 		Assets: pallet_assets,
 		ForeignUniques: pallet_uniques,
+		Notifier: mock_notifier::{Pallet, Call, Storage, Event<T>},
 	}
 );
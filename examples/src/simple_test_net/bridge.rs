@@ -0,0 +1,159 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bridge between the consensus system of `MockNet` and the one of `BridgedMockNet`.
+//!
+//! Both networks are built from the same runtime mocks, every chain stores the network it is
+//! part of at genesis. The bridge hub of a network exports messages with `ExportMessage`, which
+//! hauls them into a queue shared by both bridge hubs. `relay_bridge_messages` dispatches the
+//! queued messages on the bridge hub of the other network, which routes them to their
+//! destination.
+
+use core::{borrow::Borrow, cell::RefCell, marker::PhantomData};
+
+use codec::Encode;
+use frame_support::{storage::unhashed, traits::Contains};
+use sp_core::blake2_256;
+use sp_runtime::traits::Get;
+use sp_std::collections::vec_deque::VecDeque;
+use xcm::latest::prelude::*;
+use xcm_builder::{BridgeBlobDispatcher, DispatchBlob, HaulBlob, HaulBlobError};
+use xcm_executor::traits::Convert;
+use xcm_simulator::TestExt;
+
+use super::{
+	bridge_hub, bridged_net::BridgedBridgeHub, BridgeHub, BRIDGED_RELAY_NETWORK, RELAY_NETWORK,
+};
+
+const THIS_NETWORK_KEY: &[u8] = b":mock:this_network:";
+
+/// The network of the chain whose externalities are set.
+///
+/// Outside of the externalities of any chain, e.g. when building a message in a test, this is
+/// the network of `MockNet`.
+pub struct ThisNetwork;
+impl ThisNetwork {
+	pub fn get() -> NetworkId {
+		if sp_externalities::with_externalities(|_| ()).is_none() {
+			return RELAY_NETWORK
+		}
+		unhashed::get(THIS_NETWORK_KEY).unwrap_or(RELAY_NETWORK)
+	}
+
+	/// Sets the network of the chain. Must be called within the externalities of the chain.
+	pub fn set(network: NetworkId) {
+		unhashed::put(THIS_NETWORK_KEY, &network);
+	}
+}
+impl<I: From<NetworkId>> Get<I> for ThisNetwork {
+	fn get() -> I {
+		I::from(ThisNetwork::get())
+	}
+}
+
+/// The network at the other end of the bridge.
+pub struct OtherNetwork;
+impl Get<NetworkId> for OtherNetwork {
+	fn get() -> NetworkId {
+		if ThisNetwork::get() == RELAY_NETWORK {
+			BRIDGED_RELAY_NETWORK
+		} else {
+			RELAY_NETWORK
+		}
+	}
+}
+
+/// Prefix for generating the sovereign account of a location in another consensus system.
+pub const REMOTE_CONSENSUS_PREFIX: [u8; 15] = *b"RemoteConsensus";
+
+/// Converts a location in another consensus system, like the origin of a message exported over
+/// the bridge, into a sovereign account.
+///
+/// The account only depends on the universal location, so it is the same on every chain.
+pub struct RemoteConsensusAccount<AccountId>(PhantomData<AccountId>);
+impl<AccountId: From<[u8; 32]> + Clone> Convert<MultiLocation, AccountId>
+	for RemoteConsensusAccount<AccountId>
+{
+	fn convert_ref(location: impl Borrow<MultiLocation>) -> Result<AccountId, ()> {
+		let interior = location.borrow().interior;
+		match interior.first() {
+			Some(GlobalConsensus(_)) =>
+				Ok((REMOTE_CONSENSUS_PREFIX, interior).using_encoded(blake2_256).into()),
+			_ => Err(()),
+		}
+	}
+
+	fn reverse_ref(_: impl Borrow<AccountId>) -> Result<MultiLocation, ()> {
+		Err(())
+	}
+}
+
+/// Allows the bridge hub at `BridgeHubLocation` to alias the origin of any other consensus
+/// system with `UniversalOrigin`.
+pub struct AliasesFromBridgeHub<BridgeHubLocation>(PhantomData<BridgeHubLocation>);
+impl<BridgeHubLocation: Get<MultiLocation>> Contains<(MultiLocation, Junction)>
+	for AliasesFromBridgeHub<BridgeHubLocation>
+{
+	fn contains((origin, global): &(MultiLocation, Junction)) -> bool {
+		*origin == BridgeHubLocation::get() &&
+			matches!(global, GlobalConsensus(network) if *network != ThisNetwork::get())
+	}
+}
+
+thread_local! {
+	/// Blobs hauled over the bridge and the network they are hauled to.
+	static BRIDGE_QUEUE: RefCell<VecDeque<(NetworkId, Vec<u8>)>> = RefCell::new(VecDeque::new());
+}
+
+/// Hauls blobs to the bridge hub of the other network, see `relay_bridge_messages`.
+pub struct BridgeQueue;
+impl HaulBlob for BridgeQueue {
+	fn haul_blob(blob: Vec<u8>) -> Result<(), HaulBlobError> {
+		BRIDGE_QUEUE.with(|queue| queue.borrow_mut().push_back((OtherNetwork::get(), blob)));
+		Ok(())
+	}
+}
+
+/// Number of messages exported over the bridge that are not relayed yet.
+pub fn pending_bridge_messages() -> usize {
+	BRIDGE_QUEUE.with(|queue| queue.borrow().len())
+}
+
+/// Drops the messages exported over the bridge that are not relayed yet.
+pub fn clear_bridge_messages() {
+	BRIDGE_QUEUE.with(|queue| queue.borrow_mut().clear());
+}
+
+/// Relays all messages exported over the bridge, in both directions, until none are left.
+///
+/// Each message is dispatched on the bridge hub of the network it is exported to, and the
+/// messages this sends are processed by that network like any other. Panics if a message
+/// cannot be dispatched.
+pub fn relay_bridge_messages() {
+	type Dispatcher = BridgeBlobDispatcher<bridge_hub::XcmRouter, bridge_hub::UniversalLocation>;
+
+	while let Some((network, blob)) = BRIDGE_QUEUE.with(|queue| queue.borrow_mut().pop_front()) {
+		let dispatch = || {
+			Dispatcher::dispatch_blob(blob)
+				.unwrap_or_else(|error| panic!("Bridge message not dispatched: {error:?}"))
+		};
+		if network == RELAY_NETWORK {
+			BridgeHub::execute_with(dispatch);
+		} else {
+			BridgedBridgeHub::execute_with(dispatch);
+		}
+	}
+}
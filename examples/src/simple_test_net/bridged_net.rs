@@ -0,0 +1,104 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A second consensus system, `BRIDGED_RELAY_NETWORK`, connected to `MockNet` by the bridge hubs
+//! of both networks.
+//!
+//! The chains are built from the same runtime mocks as the ones of `MockNet`, whose routers only
+//! accept the para ids of `MockNet`. The para ids of `BridgedMockNet` must therefore be a subset
+//! of them.
+
+use core::cell::RefCell;
+
+use xcm_simulator::{decl_test_network, decl_test_parachain, decl_test_relay_chain};
+
+use super::{
+	bridge_hub, parachain, relay_chain, FaultyHandler, GenesisSpec, ParaKind, TestNetBuilder,
+	BRIDGED_RELAY_NETWORK, BRIDGE_HUB_PARA_ID,
+};
+
+decl_test_parachain! {
+	pub struct BridgedParaA {
+		Runtime = parachain::Runtime,
+		XcmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		new_ext = bridged_mock_net().para_ext(1),
+	}
+}
+
+decl_test_parachain! {
+	pub struct BridgedBridgeHub {
+		Runtime = bridge_hub::Runtime,
+		XcmpMessageHandler = FaultyHandler<bridge_hub::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<bridge_hub::MsgQueue>,
+		new_ext = bridged_mock_net().para_ext(BRIDGE_HUB_PARA_ID),
+	}
+}
+
+decl_test_relay_chain! {
+	pub struct BridgedRelay {
+		Runtime = relay_chain::Runtime,
+		RuntimeCall = relay_chain::RuntimeCall,
+		RuntimeEvent = relay_chain::RuntimeEvent,
+		XcmConfig = relay_chain::XcmConfig,
+		MessageQueue = relay_chain::MessageQueue,
+		System = relay_chain::System,
+		new_ext = bridged_mock_net().relay_ext(),
+	}
+}
+
+decl_test_network! {
+	pub struct BridgedMockNet {
+		relay_chain = BridgedRelay,
+		parachains = vec![
+			(1, BridgedParaA),
+			(BRIDGE_HUB_PARA_ID, BridgedBridgeHub),
+		],
+	}
+}
+
+thread_local! {
+	/// Overrides the network `BridgedMockNet` is reset to while `BridgedMockNet::reset_with`
+	/// resets it.
+	static BRIDGED_MOCK_NET: RefCell<Option<TestNetBuilder>> = RefCell::new(None);
+}
+
+/// The network `BridgedMockNet` is reset to by default.
+pub fn default_bridged_mock_net() -> TestNetBuilder {
+	TestNetBuilder::new()
+		.network(BRIDGED_RELAY_NETWORK)
+		.relay(GenesisSpec::relay_default())
+		.parachain(1, ParaKind::Parachain)
+		.parachain(BRIDGE_HUB_PARA_ID, ParaKind::BridgeHub)
+}
+
+/// The network `BridgedMockNet` is being reset to, see `mock_net`.
+pub fn bridged_mock_net() -> TestNetBuilder {
+	BRIDGED_MOCK_NET
+		.with(|net| net.borrow().clone())
+		.unwrap_or_else(default_bridged_mock_net)
+}
+
+impl BridgedMockNet {
+	/// Resets the network with the genesis described by `net`, see `MockNet::reset_with`.
+	///
+	/// `net` must be declared with `.network(BRIDGED_RELAY_NETWORK)`.
+	pub fn reset_with(net: TestNetBuilder) {
+		BRIDGED_MOCK_NET.with(|mock_net| *mock_net.borrow_mut() = Some(net));
+		Self::reset();
+		BRIDGED_MOCK_NET.with(|mock_net| *mock_net.borrow_mut() = None);
+	}
+}
@@ -0,0 +1,297 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Blocks of the chains of the simulator.
+//!
+//! All chains of a network start at block 1 and produce their blocks in lockstep with the relay
+//! chain, with `MockNet::advance_blocks`. Every block runs the `on_finalize` hooks of the
+//! previous block and the `on_initialize` hooks of the new one on every chain.
+//!
+//! By default the simulator delivers the messages a chain sends as soon as its `execute_with`
+//! returns. In a network built with `TestNetBuilder::block_stepping`, `StepRouter` schedules
+//! them instead: a message sent in relay block `N` is delivered at the start of block `N + 1`,
+//! with `N` as the relay block it was sent at.
+
+use core::{cell::RefCell, marker::PhantomData};
+
+use codec::Encode;
+use frame_support::{
+	storage::unhashed,
+	traits::{EnqueueMessage, OnFinalize, OnInitialize, ServiceQueues},
+	weights::Weight,
+};
+use polkadot_parachain::primitives::{
+	DmpMessageHandler, Id as ParaId, XcmpMessageFormat, XcmpMessageHandler,
+};
+use polkadot_primitives::BlockNumber as RelayBlockNumber;
+use sp_runtime::traits::{Get, One};
+use xcm::{latest::prelude::*, VersionedXcm};
+use xcm_simulator::{AggregateMessageOrigin, TestExt, UmpQueueId};
+
+use super::{
+	asset_hub,
+	bridge::ThisNetwork,
+	bridge_hub,
+	bridged_net::{BridgedBridgeHub, BridgedMockNet, BridgedParaA, BridgedRelay},
+	evm_parachain, faults,
+	mock_msg_queue::pallet as mock_msg_queue,
+	parachain, relay_chain, AssetHub, BridgeHub, Chain, EvmPara, FaultyHandler, MockChain, MockNet,
+	ParaA, ParaB, ParaC, Relay, ASSET_HUB_PARA_ID, BRIDGED_RELAY_NETWORK, BRIDGE_HUB_PARA_ID,
+	EVM_PARA_ID, RELAY_NETWORK,
+};
+
+const BLOCK_STEPPING_KEY: &[u8] = b":mock:block_stepping:";
+
+/// Whether the network of the chain whose externalities are set delivers messages block by
+/// block, see `TestNetBuilder::block_stepping`.
+pub struct BlockStepping;
+impl BlockStepping {
+	pub fn get() -> bool {
+		unhashed::get(BLOCK_STEPPING_KEY).unwrap_or(false)
+	}
+
+	/// Sets whether the network of the chain is stepped. Must be called within the externalities
+	/// of the chain.
+	pub fn set(enabled: bool) {
+		unhashed::put(BLOCK_STEPPING_KEY, &enabled);
+	}
+}
+
+/// A message scheduled by `StepRouter`, as encoded by the simulator.
+struct Scheduled {
+	network: NetworkId,
+	/// The relay block the message is delivered at.
+	at: RelayBlockNumber,
+	from: Chain,
+	to: Chain,
+	sent_at: RelayBlockNumber,
+	message: Vec<u8>,
+}
+
+thread_local! {
+	/// The current relay block of each network.
+	static RELAY_BLOCKS: RefCell<Vec<(NetworkId, RelayBlockNumber)>> = RefCell::new(Vec::new());
+	static SCHEDULE: RefCell<Vec<Scheduled>> = RefCell::new(Vec::new());
+}
+
+/// The current relay block of `network`.
+pub fn relay_block_number(network: NetworkId) -> RelayBlockNumber {
+	RELAY_BLOCKS.with(|blocks| {
+		blocks
+			.borrow()
+			.iter()
+			.find(|(of, _)| *of == network)
+			.map_or(1, |(_, number)| *number)
+	})
+}
+
+fn set_relay_block_number(network: NetworkId, number: RelayBlockNumber) {
+	RELAY_BLOCKS.with(|blocks| {
+		let mut blocks = blocks.borrow_mut();
+		blocks.retain(|(of, _)| *of != network);
+		blocks.push((network, number));
+	});
+}
+
+/// Restarts the clock of `network` at block 1 and forgets its scheduled messages, when the
+/// network is reset.
+pub(super) fn reset_clock(network: NetworkId) {
+	set_relay_block_number(network, 1);
+	SCHEDULE.with(|schedule| schedule.borrow_mut().retain(|message| message.network != network));
+}
+
+/// The number of messages `StepRouter` scheduled for a later block.
+pub fn scheduled_messages() -> usize {
+	SCHEDULE.with(|schedule| schedule.borrow().len())
+}
+
+/// Router of the chain `ThisChain`, scheduling the messages `Router` accepts for the next relay
+/// block when the network is stepped block by block. Otherwise `Router` delivers them.
+pub struct StepRouter<Router, ThisChain>(PhantomData<(Router, ThisChain)>);
+impl<Router: SendXcm, ThisChain: Get<Chain>> SendXcm for StepRouter<Router, ThisChain> {
+	/// The ticket of `Router`, and the message to schedule instead in a stepped network.
+	type Ticket = (Router::Ticket, Option<(Chain, Xcm<()>)>);
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let scheduled = if BlockStepping::get() {
+			destination.zip(message.clone()).and_then(|(destination, message)| {
+				Some((ThisChain::get().resolve(&destination)?, message))
+			})
+		} else {
+			None
+		};
+		let (ticket, price) = Router::validate(destination, message)?;
+		Ok(((ticket, scheduled), price))
+	}
+
+	fn deliver((ticket, scheduled): Self::Ticket) -> Result<XcmHash, SendError> {
+		let (to, message) = match scheduled {
+			Some(scheduled) => scheduled,
+			None => return Router::deliver(ticket),
+		};
+		let network = ThisNetwork::get();
+		let sent_at = relay_block_number(network);
+		let message = VersionedXcm::from(message).encode();
+		let id = sp_io::hashing::blake2_256(&message);
+		let scheduled =
+			Scheduled { network, at: sent_at + 1, from: ThisChain::get(), to, sent_at, message };
+		SCHEDULE.with(|schedule| schedule.borrow_mut().push(scheduled));
+		Ok(id)
+	}
+}
+
+/// Whether `produce_block` finalizes the current block or initializes the next one.
+#[derive(Clone, Copy)]
+enum Phase {
+	Finalize,
+	Initialize,
+}
+
+fn produce_block<Chain: MockChain>(phase: Phase) {
+	type System<Chain> = frame_system::Pallet<<Chain as MockChain>::Runtime>;
+	Chain::execute_with(|| {
+		let number = System::<Chain>::block_number();
+		match phase {
+			Phase::Finalize => {
+				Chain::AllPallets::on_finalize(number);
+				System::<Chain>::set_block_number(number + One::one());
+			},
+			Phase::Initialize => {
+				Chain::AllPallets::on_initialize(number);
+			},
+		}
+	});
+}
+
+/// Produces the next block of `network`, whose chains are produced by `chains`.
+///
+/// The messages due at the new block are delivered between the `on_finalize` hooks of the
+/// previous block and the `on_initialize` hooks of the new one.
+fn next_block(network: NetworkId, chains: &[fn(Phase)]) {
+	chains.iter().for_each(|chain| chain(Phase::Finalize));
+	let number = relay_block_number(network) + 1;
+	set_relay_block_number(network, number);
+
+	faults::advance_delayed(network, 1);
+	let due = SCHEDULE.with(|schedule| {
+		let mut schedule = schedule.borrow_mut();
+		let (due, later): (Vec<_>, Vec<_>) = schedule
+			.drain(..)
+			.partition(|message| message.network == network && message.at <= number);
+		*schedule = later;
+		due
+	});
+	due.into_iter().for_each(receive);
+
+	chains.iter().for_each(|chain| chain(Phase::Initialize));
+}
+
+/// Delivers `message` to its destination, like the simulator does.
+fn receive(message: Scheduled) {
+	let Scheduled { network, from, to, sent_at, message, .. } = message;
+	let bridged = network != RELAY_NETWORK;
+	match (bridged, to) {
+		(false, Chain::Relay) => Relay::execute_with(|| receive_upward(from, &message)),
+		(true, Chain::Relay) => BridgedRelay::execute_with(|| receive_upward(from, &message)),
+		(false, Chain::Para(1)) =>
+			ParaA::execute_with(|| receive_on::<parachain::Runtime>(from, sent_at, message)),
+		(false, Chain::Para(2)) =>
+			ParaB::execute_with(|| receive_on::<parachain::Runtime>(from, sent_at, message)),
+		(false, Chain::Para(3)) =>
+			ParaC::execute_with(|| receive_on::<parachain::Runtime>(from, sent_at, message)),
+		(false, Chain::Para(ASSET_HUB_PARA_ID)) =>
+			AssetHub::execute_with(|| receive_on::<asset_hub::Runtime>(from, sent_at, message)),
+		(false, Chain::Para(BRIDGE_HUB_PARA_ID)) =>
+			BridgeHub::execute_with(|| receive_on::<bridge_hub::Runtime>(from, sent_at, message)),
+		(false, Chain::Para(EVM_PARA_ID)) =>
+			EvmPara::execute_with(|| receive_on::<evm_parachain::Runtime>(from, sent_at, message)),
+		(true, Chain::Para(1)) =>
+			BridgedParaA::execute_with(|| receive_on::<parachain::Runtime>(from, sent_at, message)),
+		(true, Chain::Para(BRIDGE_HUB_PARA_ID)) => BridgedBridgeHub::execute_with(|| {
+			receive_on::<bridge_hub::Runtime>(from, sent_at, message)
+		}),
+		(_, Chain::Para(para_id)) => panic!("No parachain with para_id = {para_id} to deliver to"),
+	}
+}
+
+/// Enqueues the upward message and services the message queue of the relay chain.
+fn receive_upward(from: Chain, message: &[u8]) {
+	use relay_chain::MessageQueue;
+
+	let para_id = match from {
+		Chain::Para(para_id) => para_id,
+		Chain::Relay => unreachable!("the relay chain does not send upward messages; qed"),
+	};
+	let origin = AggregateMessageOrigin::Ump(UmpQueueId::Para(para_id.into()));
+	let message = message.try_into().expect("Upward message too long");
+	<MessageQueue as EnqueueMessage<AggregateMessageOrigin>>::enqueue_message(message, origin);
+	<MessageQueue as ServiceQueues>::service_queues(Weight::MAX);
+}
+
+/// Hands a downward or horizontal message to the message queue of a parachain, with the weight
+/// its blocks give to messages.
+fn receive_on<Runtime: mock_msg_queue::Config>(
+	from: Chain,
+	sent_at: RelayBlockNumber,
+	message: Vec<u8>,
+) {
+	type MsgQueue<Runtime> = mock_msg_queue::Pallet<Runtime>;
+	let budget = MsgQueue::<Runtime>::block_weight_budget();
+	match from {
+		Chain::Relay => {
+			let messages = core::iter::once((sent_at, message));
+			FaultyHandler::<MsgQueue<Runtime>>::handle_dmp_messages(messages, budget);
+		},
+		Chain::Para(sender) => {
+			let mut data = XcmpMessageFormat::ConcatenatedVersionedXcm.encode();
+			data.extend(message);
+			let messages = core::iter::once((ParaId::from(sender), sent_at, &data[..]));
+			FaultyHandler::<MsgQueue<Runtime>>::handle_xcmp_messages(messages, budget);
+		},
+	}
+}
+
+impl MockNet {
+	/// Produces `blocks` blocks on every chain of the network, delivering the messages due at
+	/// each of them.
+	pub fn advance_blocks(blocks: u32) {
+		let chains: &[fn(Phase)] = &[
+			produce_block::<Relay>,
+			produce_block::<ParaA>,
+			produce_block::<ParaB>,
+			produce_block::<ParaC>,
+			produce_block::<AssetHub>,
+			produce_block::<BridgeHub>,
+			produce_block::<EvmPara>,
+		];
+		(0..blocks).for_each(|_| next_block(RELAY_NETWORK, chains));
+	}
+}
+
+impl BridgedMockNet {
+	/// Produces `blocks` blocks on every chain of the network, see `MockNet::advance_blocks`.
+	pub fn advance_blocks(blocks: u32) {
+		let chains: &[fn(Phase)] = &[
+			produce_block::<BridgedRelay>,
+			produce_block::<BridgedParaA>,
+			produce_block::<BridgedBridgeHub>,
+		];
+		(0..blocks).for_each(|_| next_block(BRIDGED_RELAY_NETWORK, chains));
+	}
+}
@@ -0,0 +1,288 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Dry runs of XCM programs, instruction by instruction.
+//!
+//! `dry_run::<parachain::XcmConfig>(origin, message)` executes `message` with the XCM executor
+//! of a chain, within its externalities, and reverts all changes to its storage afterwards. It
+//! reports the registers of the XCVM after every instruction, see
+//! `src/reference/xcvm-registers.md`, and the messages the program would have sent, which are
+//! captured by `DryRunRouter` instead of being delivered:
+//!
+//! ```ignore
+//! ParaA::execute_with(|| {
+//!     let run = dry_run::<parachain::XcmConfig>(Parent, message);
+//!     println!("{run}");
+//! });
+//! ```
+//!
+//! The executor is stepped with its benchmarking interface, so it only runs the instructions of
+//! the message, then the error handler or the appendix, like `XcmExecutor::execute`. The
+//! instructions are executed whether the barrier lets the message through or not.
+//!
+//! That interface is only built with the `runtime-benchmarks` feature of `xcm-executor`, which
+//! the `dry-run` feature of this crate enables, so `dry_run` is only available with it.
+//! `DryRunRouter` is always part of the routers of the runtime mocks.
+
+use core::{cell::RefCell, fmt, marker::PhantomData};
+
+use codec::Encode;
+use frame_support::{traits::ProcessMessageError, weights::Weight};
+use xcm::latest::prelude::*;
+#[cfg(feature = "dry-run")]
+use xcm_executor::{
+	traits::{ShouldExecute, WeightBounds},
+	XcmExecutor,
+};
+
+thread_local! {
+	/// The messages sent during the current dry run, `None` outside of dry runs.
+	static CAPTURED: RefCell<Option<Vec<(MultiLocation, Xcm<()>)>>> = RefCell::new(None);
+}
+
+/// The program an instruction is part of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Program {
+	/// The message itself.
+	Message,
+	/// The error handler, executed after an instruction of the previous program failed.
+	ErrorHandler,
+	/// The appendix, executed after the previous program.
+	Appendix,
+}
+
+/// The registers of the XCVM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Registers {
+	pub holding: MultiAssets,
+	pub origin: Option<MultiLocation>,
+	/// The index of the instruction that failed and its error.
+	pub error: Option<(u32, XcmError)>,
+	pub error_handler: Xcm<()>,
+	pub appendix: Xcm<()>,
+	pub transact_status: MaybeErrorCode,
+	pub topic: Option<[u8; 32]>,
+	/// The weight bought but not used so far.
+	pub surplus: Weight,
+	/// The part of the surplus refunded with `RefundSurplus`.
+	pub refunded: Weight,
+}
+
+/// An instruction executed during a dry run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+	pub program: Program,
+	/// The index of the instruction in its program.
+	pub index: usize,
+	pub instruction: Instruction<()>,
+	pub result: Result<(), XcmError>,
+	/// The registers after the instruction.
+	pub registers: Registers,
+}
+
+/// The report of `dry_run`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRun {
+	/// Whether the barrier lets the message through, `Err(Unsupported)` when the weight of the
+	/// message cannot be computed.
+	pub barrier: Result<(), ProcessMessageError>,
+	/// The weight of the message, as computed by the `Weigher`.
+	pub weight: Weight,
+	pub steps: Vec<Step>,
+	pub outcome: Outcome,
+	/// The messages the program would have sent, with their destination.
+	pub sent: Vec<(MultiLocation, Xcm<()>)>,
+}
+
+impl DryRun {
+	/// The registers after the last instruction executed.
+	pub fn registers(&self) -> Option<&Registers> {
+		self.steps.last().map(|step| &step.registers)
+	}
+}
+
+impl fmt::Display for DryRun {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "barrier: {:?}, weight: {:?}", self.barrier, self.weight)?;
+		for step in &self.steps {
+			let registers = &step.registers;
+			let (program, index) = (step.program, step.index);
+			writeln!(f, "{program:?}#{index} {:?} => {:?}", step.instruction, step.result)?;
+			writeln!(f, "    holding: {:?}", registers.holding)?;
+			writeln!(f, "    origin: {:?}", registers.origin)?;
+			writeln!(f, "    error: {:?}", registers.error)?;
+			writeln!(f, "    error handler: {:?}", registers.error_handler)?;
+			writeln!(f, "    appendix: {:?}", registers.appendix)?;
+			writeln!(f, "    transact status: {:?}", registers.transact_status)?;
+			writeln!(f, "    topic: {:?}", registers.topic)?;
+			let (surplus, refunded) = (registers.surplus, registers.refunded);
+			writeln!(f, "    surplus: {surplus:?}, refunded: {refunded:?}")?;
+		}
+		for (destination, message) in &self.sent {
+			writeln!(f, "sent to {destination:?}: {message:?}")?;
+		}
+		write!(f, "outcome: {:?}", self.outcome)
+	}
+}
+
+/// Executes `message` from `origin` with the executor configured by `Config`, and reverts the
+/// changes to storage. Must be called within the externalities of the chain of `Config`.
+#[cfg(feature = "dry-run")]
+pub fn dry_run<Config: xcm_executor::Config>(
+	origin: impl Into<MultiLocation>,
+	message: Xcm<Config::RuntimeCall>,
+) -> DryRun {
+	let origin = origin.into();
+	sp_io::storage::start_transaction();
+	CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+	let run = execute::<Config>(origin, message);
+	let sent = CAPTURED.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+	sp_io::storage::rollback_transaction();
+	DryRun { sent, ..run }
+}
+
+#[cfg(feature = "dry-run")]
+fn execute<Config: xcm_executor::Config>(
+	origin: MultiLocation,
+	mut message: Xcm<Config::RuntimeCall>,
+) -> DryRun {
+	let weight = match Config::Weigher::weight(&mut message) {
+		Ok(weight) => weight,
+		Err(()) => {
+			let outcome = Outcome::Error(XcmError::WeightNotComputable);
+			let barrier = Err(ProcessMessageError::Unsupported);
+			return DryRun { barrier, weight: Weight::zero(), steps: vec![], outcome, sent: vec![] }
+		},
+	};
+	let barrier =
+		Config::Barrier::should_execute(&origin, &mut message.0, weight, &mut Weight::zero());
+
+	let id = message_id(&Xcm::<()>::from(message.clone()));
+	let mut vm = XcmExecutor::<Config>::new(origin, id);
+	let mut steps = Vec::new();
+	let mut next = Some((Program::Message, message));
+	while let Some((kind, Xcm(instructions))) = next.take().filter(|(_, xcm)| !xcm.0.is_empty()) {
+		let mut failed = false;
+		for (index, instruction) in instructions.into_iter().enumerate() {
+			if failed {
+				// Like `XcmExecutor::process`, which counts the weight of skipped instructions as
+				// surplus
+				let skipped = Config::Weigher::instr_weight(&instruction).unwrap_or_default();
+				vm.set_total_surplus(vm.total_surplus().saturating_add(skipped));
+				continue
+			}
+			let executed = Instruction::<()>::from(instruction.clone());
+			let result = vm.bench_process(Xcm(vec![instruction])).map_err(|e| e.xcm_error);
+			if let Err(error) = &result {
+				vm.set_error(Some((index as u32, error.clone())));
+				failed = true;
+			}
+			let registers = registers(&vm);
+			steps.push(Step { program: kind, index, instruction: executed, result, registers });
+		}
+		next = Some(if failed {
+			match take_error_handler(&mut vm) {
+				handler if !handler.0.is_empty() => (Program::ErrorHandler, handler),
+				_ => (Program::Appendix, take_appendix(&mut vm)),
+			}
+		} else {
+			drop_error_handler(&mut vm);
+			(Program::Appendix, take_appendix(&mut vm))
+		});
+	}
+
+	let outcome = vm.bench_post_process(weight);
+	DryRun { barrier, weight, steps, outcome, sent: vec![] }
+}
+
+#[cfg(feature = "dry-run")]
+fn registers<Config: xcm_executor::Config>(vm: &XcmExecutor<Config>) -> Registers {
+	let holding: Vec<MultiAsset> = vm.holding().clone().into();
+	Registers {
+		holding: holding.into(),
+		origin: *vm.origin(),
+		error: vm.error().clone(),
+		error_handler: Xcm::<()>::from(vm.error_handler().clone()),
+		appendix: Xcm::<()>::from(vm.appendix().clone()),
+		transact_status: vm.transact_status().clone(),
+		topic: *vm.topic(),
+		surplus: *vm.total_surplus(),
+		refunded: *vm.total_refunded(),
+	}
+}
+
+#[cfg(feature = "dry-run")]
+fn take_error_handler<Config: xcm_executor::Config>(
+	vm: &mut XcmExecutor<Config>,
+) -> Xcm<Config::RuntimeCall> {
+	let handler = vm.error_handler().clone();
+	vm.set_error_handler(Xcm(vec![]));
+	vm.set_error_handler_weight(Weight::zero());
+	handler
+}
+
+#[cfg(feature = "dry-run")]
+fn drop_error_handler<Config: xcm_executor::Config>(vm: &mut XcmExecutor<Config>) {
+	let surplus = vm.total_surplus().saturating_add(*vm.error_handler_weight());
+	vm.set_total_surplus(surplus);
+	vm.set_error_handler(Xcm(vec![]));
+	vm.set_error_handler_weight(Weight::zero());
+}
+
+#[cfg(feature = "dry-run")]
+fn take_appendix<Config: xcm_executor::Config>(
+	vm: &mut XcmExecutor<Config>,
+) -> Xcm<Config::RuntimeCall> {
+	let appendix = vm.appendix().clone();
+	vm.set_appendix(Xcm(vec![]));
+	vm.set_appendix_weight(Weight::zero());
+	appendix
+}
+
+fn message_id(message: &Xcm<()>) -> XcmHash {
+	VersionedXcm::from(message.clone()).using_encoded(sp_io::hashing::blake2_256)
+}
+
+/// Router capturing the messages `Router` validates during a dry run instead of delivering
+/// them. Outside of dry runs, it delivers them with `Router`.
+pub struct DryRunRouter<Router>(PhantomData<Router>);
+impl<Router: SendXcm> SendXcm for DryRunRouter<Router> {
+	type Ticket = (Router::Ticket, Option<(MultiLocation, Xcm<()>)>);
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let sent = destination.zip(message.clone());
+		let (ticket, price) = Router::validate(destination, message)?;
+		Ok(((ticket, sent), price))
+	}
+
+	fn deliver((ticket, sent): Self::Ticket) -> Result<XcmHash, SendError> {
+		let captured = CAPTURED.with(|captured| match (captured.borrow_mut().as_mut(), sent) {
+			(Some(captured), Some((destination, message))) => {
+				let id = message_id(&message);
+				captured.push((destination, message));
+				Some(id)
+			},
+			_ => None,
+		});
+		match captured {
+			Some(id) => Ok(id),
+			None => Router::deliver(ticket),
+		}
+	}
+}
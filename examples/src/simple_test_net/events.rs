@@ -0,0 +1,236 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Assertions on the events of the chains of the network.
+//!
+//! The assertions enter the externalities of the chain themselves, so they are used outside of
+//! `execute_with`:
+//!
+//! ```ignore
+//! let mark = event_mark::<ParaA>();
+//! ParaB::execute_with(|| { /* send a message to parachain A */ });
+//! assert_para_event!(ParaA, since mark, MsgQueue::Fail(_, XcmError::Barrier));
+//!
+//! let mark = event_mark::<Relay>();
+//! ParaA::execute_with(|| { /* send a message to the relay chain */ });
+//! expect_relay_processed(mark, false);
+//! ```
+//!
+//! Events are matched per pallet, with the pallet named as in the `construct_runtime!` of the
+//! runtime mock, see `pallets`.
+
+use core::fmt::Debug;
+
+use frame_support::traits::{OnFinalize, OnInitialize};
+use frame_system::pallet_prelude::BlockNumberFor;
+use xcm_simulator::TestExt;
+
+use super::{
+	asset_hub, bridge_hub, evm_parachain, parachain, relay_chain, AssetHub, BridgeHub,
+	BridgedBridgeHub, BridgedParaA, BridgedRelay, EvmPara, ParaA, ParaB, ParaC, Relay,
+};
+
+/// A chain of the network and the runtime mock it is built from.
+pub trait MockChain: TestExt {
+	type Runtime: frame_system::Config;
+	/// The pallets of the runtime mock, whose hooks run on every block.
+	type AllPallets: OnInitialize<BlockNumberFor<Self::Runtime>>
+		+ OnFinalize<BlockNumberFor<Self::Runtime>>;
+}
+
+impl MockChain for Relay {
+	type Runtime = relay_chain::Runtime;
+	type AllPallets = relay_chain::AllPalletsWithSystem;
+}
+impl MockChain for ParaA {
+	type Runtime = parachain::Runtime;
+	type AllPallets = parachain::AllPalletsWithSystem;
+}
+impl MockChain for ParaB {
+	type Runtime = parachain::Runtime;
+	type AllPallets = parachain::AllPalletsWithSystem;
+}
+impl MockChain for ParaC {
+	type Runtime = parachain::Runtime;
+	type AllPallets = parachain::AllPalletsWithSystem;
+}
+impl MockChain for AssetHub {
+	type Runtime = asset_hub::Runtime;
+	type AllPallets = asset_hub::AllPalletsWithSystem;
+}
+impl MockChain for BridgeHub {
+	type Runtime = bridge_hub::Runtime;
+	type AllPallets = bridge_hub::AllPalletsWithSystem;
+}
+impl MockChain for EvmPara {
+	type Runtime = evm_parachain::Runtime;
+	type AllPallets = evm_parachain::AllPalletsWithSystem;
+}
+impl MockChain for BridgedRelay {
+	type Runtime = relay_chain::Runtime;
+	type AllPallets = relay_chain::AllPalletsWithSystem;
+}
+impl MockChain for BridgedParaA {
+	type Runtime = parachain::Runtime;
+	type AllPallets = parachain::AllPalletsWithSystem;
+}
+impl MockChain for BridgedBridgeHub {
+	type Runtime = bridge_hub::Runtime;
+	type AllPallets = bridge_hub::AllPalletsWithSystem;
+}
+
+/// The `RuntimeEvent` of the runtime mock of `Chain`.
+pub type RuntimeEventOf<Chain> =
+	<<Chain as MockChain>::Runtime as frame_system::Config>::RuntimeEvent;
+
+/// The events of the pallets the assertion macros understand, named after the pallets.
+pub mod pallets {
+	use super::super::{
+		mock_dex::pallet as mock_dex, mock_msg_queue::pallet as mock_msg_queue,
+		mock_notifier::pallet as mock_notifier,
+	};
+
+	pub type MsgQueue<T> = mock_msg_queue::Event<T>;
+	pub type PolkadotXcm<T> = pallet_xcm::Event<T>;
+	pub type XcmPallet<T> = pallet_xcm::Event<T>;
+	pub type MessageQueue<T> = pallet_message_queue::Event<T>;
+	pub type Balances<T> = pallet_balances::Event<T>;
+	pub type Assets<T> = pallet_assets::Event<T>;
+	pub type Dex<T> = mock_dex::Event<T>;
+	pub type Notifier<T> = mock_notifier::Event<T>;
+}
+
+/// The number of events a chain has deposited, marking the events deposited after it.
+pub type EventMark = usize;
+
+/// Marks the events `Chain` deposits from now on, see `events_since`.
+pub fn event_mark<Chain: MockChain>() -> EventMark {
+	Chain::execute_with(|| frame_system::Pallet::<Chain::Runtime>::events().len())
+}
+
+/// The events `Chain` deposited since `mark`.
+pub fn events_since<Chain: MockChain>(mark: EventMark) -> Vec<RuntimeEventOf<Chain>> {
+	Chain::execute_with(|| {
+		frame_system::Pallet::<Chain::Runtime>::events()
+			.into_iter()
+			.skip(mark)
+			.map(|record| record.event)
+			.collect()
+	})
+}
+
+/// Asserts that `Chain` deposited an event of the pallet event type `E` for which `matches`
+/// holds since `mark`. On failure, lists the events deposited since `mark`, marking the ones of
+/// `E` that did not match `expected`.
+pub fn assert_event<Chain, E>(mark: EventMark, expected: &str, matches: impl Fn(&E) -> bool)
+where
+	Chain: MockChain,
+	RuntimeEventOf<Chain>: TryInto<E>,
+	E: Debug,
+{
+	let events = events_since::<Chain>(mark);
+	let mut report = String::new();
+	for (index, event) in events.iter().enumerate() {
+		let line = format!("{:?}", event);
+		match event.clone().try_into() {
+			Ok(pallet_event) if matches(&pallet_event) => return,
+			Ok(_) => report.push_str(&format!("  - #{} {}\n", mark + index, line)),
+			Err(_) => report.push_str(&format!("    #{} {}\n", mark + index, line)),
+		}
+	}
+	if events.is_empty() {
+		report.push_str("    (none)\n");
+	}
+	panic!(
+		"expected an event matching `{}` on {} since event #{}\n  + {}\nevents since #{} \
+		 (`-` for the ones of the same pallet):\n{}",
+		expected,
+		core::any::type_name::<Chain>().rsplit("::").next().unwrap_or_default(),
+		mark,
+		expected,
+		mark,
+		report,
+	);
+}
+
+/// Asserts that the relay chain processed an upward message with the outcome `success` since
+/// `mark`. On failure, shows the upward messages it processed since `mark` against the expected
+/// outcome.
+pub fn expect_relay_processed(mark: EventMark, success: bool) {
+	type Event = pallets::MessageQueue<relay_chain::Runtime>;
+	let processed: Vec<(bool, Event)> = events_since::<Relay>(mark)
+		.into_iter()
+		.filter_map(|event| match event.try_into() {
+			Ok(event @ Event::Processed { success, .. }) => Some((success, event)),
+			_ => None,
+		})
+		.collect();
+	if processed.iter().any(|(processed, _)| *processed == success) {
+		return
+	}
+	let mut report = String::new();
+	for (_, event) in &processed {
+		report.push_str(&format!("  - MessageQueue::{:?}\n", event));
+	}
+	if processed.is_empty() {
+		report.push_str("  - (no upward message processed)\n");
+	}
+	panic!(
+		"expected the relay chain to process an upward message since event #{}\n  + \
+		 MessageQueue::Processed {{ success: {}, .. }}\n{}",
+		mark, success, report,
+	);
+}
+
+/// Asserts that a parachain deposited an event matching a pattern on the event of one of its
+/// pallets, see `pallets`.
+///
+/// `assert_para_event!(ParaA, MsgQueue::Fail(_, XcmError::Barrier))` looks at all events of
+/// the parachain, `assert_para_event!(ParaA, since mark, ...)` only at the ones after `mark`.
+macro_rules! assert_para_event {
+	($chain:ty, since $mark:expr, $pallet:ident :: $variant:ident $($pattern:tt)*) => {{
+		use $crate::simple_test_net::events::{assert_event, pallets, MockChain};
+		type Runtime = <$chain as MockChain>::Runtime;
+		assert_event::<$chain, pallets::$pallet<Runtime>>(
+			$mark,
+			stringify!($pallet :: $variant $($pattern)*),
+			|event| matches!(event, pallets::$pallet::<Runtime>::$variant $($pattern)*),
+		)
+	}};
+	($chain:ty, $pallet:ident :: $variant:ident $($pattern:tt)*) => {
+		$crate::simple_test_net::events::assert_para_event!(
+			$chain, since 0, $pallet :: $variant $($pattern)*
+		)
+	};
+}
+
+/// Asserts that the relay chain deposited an event matching a pattern, like
+/// `assert_para_event!`.
+macro_rules! assert_relay_event {
+	(since $mark:expr, $pallet:ident :: $variant:ident $($pattern:tt)*) => {
+		$crate::simple_test_net::events::assert_para_event!(
+			$crate::simple_test_net::Relay, since $mark, $pallet :: $variant $($pattern)*
+		)
+	};
+	($pallet:ident :: $variant:ident $($pattern:tt)*) => {
+		$crate::simple_test_net::events::assert_para_event!(
+			$crate::simple_test_net::Relay, since 0, $pallet :: $variant $($pattern)*
+		)
+	};
+}
+
+pub(crate) use assert_para_event;
+pub(crate) use assert_relay_event;
@@ -0,0 +1,292 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parachain runtime mock with Ethereum-style accounts.
+//!
+//! Accounts are 20 bytes long and are seen by other chains as `AccountKey20` junctions, like on
+//! Moonbeam. Its native token is the relay chain token, of which the relay chain is the reserve.
+
+use super::{
+	accounts::RecordDerivations, bridge::ThisNetwork, mock_msg_queue::pallet as mock_msg_queue,
+	trader::trader_fee, Balance, ForeignChainAliasAccount,
+};
+use frame_support::{
+	construct_runtime, parameter_types,
+	traits::{Contains, Everything, Nothing},
+	weights::{
+		constants::{WEIGHT_PROOF_SIZE_PER_MB, WEIGHT_REF_TIME_PER_SECOND},
+		Weight,
+	},
+};
+use frame_system::EnsureRoot;
+use pallet_xcm::XcmPassthrough;
+use polkadot_parachain::primitives::Sibling;
+use sp_core::{ConstU32, H160, H256};
+use sp_runtime::{testing::Header, traits::IdentityLookup};
+use sp_std::prelude::*;
+use xcm::latest::prelude::*;
+use xcm_builder::{
+	AccountKey20Aliases, AllowExplicitUnpaidExecutionFrom, AllowKnownQueryResponses,
+	AllowSubscriptionsFrom, AllowTopLevelPaidExecutionFrom, CurrencyAdapter as XcmCurrencyAdapter,
+	EnsureXcmOrigin, FixedRateOfFungible, FixedWeightBounds, IsConcrete, NativeAsset,
+	ParentAsSuperuser, ParentIsPreset, SiblingParachainConvertsVia, SignedAccountKey20AsNative,
+	SovereignSignedViaLocation, WithComputedOrigin,
+};
+use xcm_executor::{
+	traits::{Convert, WeightBounds},
+	Config, XcmExecutor,
+};
+
+pub type AccountId = H160;
+
+pub type SovereignAccountOf = RecordDerivations<
+	(
+		ForeignChainAliasAccount<AccountId>,
+		SiblingParachainConvertsVia<Sibling, AccountId>,
+		AccountKey20Aliases<RelayNetwork, AccountId>,
+		ParentIsPreset<AccountId>,
+	),
+	super::AsParachain<MsgQueue>,
+>;
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+
+impl frame_system::Config for Runtime {
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = BlockHashCount;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type DbWeight = ();
+	type BaseCallFilter = Everything;
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+parameter_types! {
+	pub ExistentialDeposit: Balance = 1;
+	pub const MaxLocks: u32 = 50;
+	pub const MaxReserves: u32 = 50;
+}
+
+impl pallet_balances::Config for Runtime {
+	type MaxLocks = MaxLocks;
+	type Balance = Balance;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxReserves = MaxReserves;
+	type ReserveIdentifier = [u8; 8];
+	type HoldIdentifier = ();
+	type FreezeIdentifier = ();
+	type MaxHolds = ConstU32<0>;
+	type MaxFreezes = ConstU32<0>;
+}
+
+pub type RelayNetwork = ThisNetwork;
+
+parameter_types! {
+	pub const TokenLocation: MultiLocation = MultiLocation::parent();
+	pub UniversalLocation: InteriorMultiLocation =
+		X2(GlobalConsensus(RelayNetwork::get()), Parachain(MsgQueue::parachain_id().into()));
+}
+
+pub type XcmOriginToCallOrigin = (
+	SovereignSignedViaLocation<SovereignAccountOf, RuntimeOrigin>,
+	ParentAsSuperuser<RuntimeOrigin>,
+	SignedAccountKey20AsNative<RelayNetwork, RuntimeOrigin>,
+	XcmPassthrough<RuntimeOrigin>,
+);
+
+parameter_types! {
+	pub const XcmInstructionWeight: Weight = Weight::from_parts(1_000, 1_000);
+	pub TokensPerSecondPerMegabyte: (AssetId, u128, u128) = (Concrete(Parent.into()), 1_000_000_000_000, 1024 * 1024);
+	pub const MaxInstructions: u32 = 100;
+	pub const MaxAssetsIntoHolding: u32 = 64;
+}
+
+pub fn estimate_message_fee(number_of_instructions: u64) -> u128 {
+	let weight = estimate_message_weight(number_of_instructions);
+
+	estimate_fee_for_weight(weight)
+}
+
+pub fn estimate_message_weight(number_of_instructions: u64) -> Weight {
+	XcmInstructionWeight::get().saturating_mul(number_of_instructions)
+}
+
+pub fn estimate_fee_for_weight(weight: Weight) -> u128 {
+	let (_, units_per_second, units_per_mb) = TokensPerSecondPerMegabyte::get();
+
+	units_per_second * (weight.ref_time() as u128) / (WEIGHT_REF_TIME_PER_SECOND as u128) +
+		units_per_mb * (weight.proof_size() as u128) / (WEIGHT_PROOF_SIZE_PER_MB as u128)
+}
+
+/// Fee charged by the `Trader` for executing `message`, in the relay token, as `BuyExecution`
+/// would charge it.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_fee_for(message: &Xcm<RuntimeCall>) -> u128 {
+	let weight = estimate_weight_for(message);
+	trader_fee::<<XcmConfig as Config>::Trader>(TokensPerSecondPerMegabyte::get().0, weight)
+		.expect("The `Trader` takes fees in the relay token")
+}
+
+/// Weight of `message` according to the `Weigher`.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_weight_for(message: &Xcm<RuntimeCall>) -> Weight {
+	<XcmConfig as Config>::Weigher::weight(&mut message.clone())
+		.expect("message has at most `MaxInstructions` instructions")
+}
+
+pub type LocalBalancesTransactor =
+	XcmCurrencyAdapter<Balances, IsConcrete<TokenLocation>, SovereignAccountOf, AccountId, ()>;
+
+pub struct ParentRelay;
+impl Contains<MultiLocation> for ParentRelay {
+	fn contains(location: &MultiLocation) -> bool {
+		location.contains_parents_only(1)
+	}
+}
+
+pub type XcmRouter = super::ParachainRouter<MsgQueue, PolkadotXcm>;
+pub type Barrier = WithComputedOrigin<
+	(
+		AllowExplicitUnpaidExecutionFrom<ParentRelay>,
+		AllowTopLevelPaidExecutionFrom<Everything>,
+		AllowKnownQueryResponses<PolkadotXcm>,
+		AllowSubscriptionsFrom<Everything>,
+	),
+	UniversalLocation,
+	ConstU32<8>,
+>;
+
+pub struct XcmConfig;
+impl Config for XcmConfig {
+	type RuntimeCall = RuntimeCall;
+	type XcmSender = XcmRouter;
+	type AssetTransactor = LocalBalancesTransactor;
+	type OriginConverter = XcmOriginToCallOrigin;
+	type IsReserve = NativeAsset;
+	type IsTeleporter = ();
+	type UniversalLocation = UniversalLocation;
+	type Barrier = Barrier;
+	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
+	type Trader = FixedRateOfFungible<TokensPerSecondPerMegabyte, ()>;
+	type ResponseHandler = PolkadotXcm;
+	type AssetTrap = PolkadotXcm;
+	type AssetLocker = ();
+	type AssetExchanger = ();
+	type AssetClaims = PolkadotXcm;
+	type SubscriptionService = PolkadotXcm;
+	type PalletInstancesInfo = AllPalletsWithSystem;
+	type FeeManager = ();
+	type MaxAssetsIntoHolding = MaxAssetsIntoHolding;
+	type MessageExporter = ();
+	type UniversalAliases = Nothing;
+	type CallDispatcher = RuntimeCall;
+	type SafeCallFilter = Everything;
+}
+
+impl mock_msg_queue::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type XcmExecutor = super::TraceExecutor<XcmExecutor<XcmConfig>, super::AsParachain<MsgQueue>>;
+	type ExecuteOverweightOrigin = EnsureRoot<AccountId>;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
+}
+
+/// Converts a signed origin into the `AccountKey20` location of its account, like
+/// `SignedToAccountId32` does for 32 byte accounts.
+pub struct SignedToAccountKey20;
+impl Convert<RuntimeOrigin, MultiLocation> for SignedToAccountKey20 {
+	fn convert(origin: RuntimeOrigin) -> Result<MultiLocation, RuntimeOrigin> {
+		match frame_system::ensure_signed(origin.clone()) {
+			Ok(who) => Ok(AccountKey20 { network: RelayNetwork::get(), key: who.into() }.into()),
+			Err(_) => Err(origin),
+		}
+	}
+}
+
+pub type LocalOriginToLocation = SignedToAccountKey20;
+
+#[cfg(feature = "runtime-benchmarks")]
+parameter_types! {
+	pub ReachableDest: Option<MultiLocation> = Some(Parent.into());
+}
+
+impl pallet_xcm::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type SendXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, LocalOriginToLocation>;
+	type XcmRouter = XcmRouter;
+	type ExecuteXcmOrigin = EnsureXcmOrigin<RuntimeOrigin, LocalOriginToLocation>;
+	type XcmExecuteFilter = Everything;
+	type XcmExecutor = XcmExecutor<XcmConfig>;
+	type XcmTeleportFilter = Nothing;
+	type XcmReserveTransferFilter = Everything;
+	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
+	type UniversalLocation = UniversalLocation;
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
+	type Currency = Balances;
+	type CurrencyMatcher = IsConcrete<TokenLocation>;
+	type TrustedLockers = Nothing;
+	type SovereignAccountOf = SovereignAccountOf;
+	type MaxLockers = ConstU32<8>;
+	type MaxRemoteLockConsumers = ConstU32<0>;
+	type RemoteLockConsumerIdentifier = ();
+	type WeightInfo = pallet_xcm::TestWeightInfo;
+	#[cfg(feature = "runtime-benchmarks")]
+	type ReachableDest = ReachableDest;
+	type AdminOrigin = EnsureRoot<AccountId>;
+}
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
+type Block = frame_system::mocking::MockBlock<Runtime>;
+
+construct_runtime!(
+	pub enum Runtime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Storage, Config, Event<T>},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		MsgQueue: mock_msg_queue::{Pallet, Call, Storage, Event<T>},
+		PolkadotXcm: pallet_xcm::{Pallet, Call, Event<T>, Origin},
+	}
+);
@@ -0,0 +1,380 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Faults of the transport between the chains of the simulator.
+//!
+//! Tests inject faults on the messages of a route with `inject_fault`. `FaultRouter` drops,
+//! duplicates and holds back the messages a chain sends, and `FaultyHandler` and the message
+//! processor of the relay chain mock corrupt the bytes of the messages a chain receives.
+//! Faults apply to the thread of the test that injected them, until `clear_faults`.
+
+use core::{cell::RefCell, marker::PhantomData};
+
+use codec::Encode;
+use frame_support::weights::Weight;
+use polkadot_parachain::primitives::{DmpMessageHandler, Id as ParaId, XcmpMessageHandler};
+use polkadot_primitives::BlockNumber as RelayBlockNumber;
+use sp_runtime::traits::Get;
+use xcm::{latest::prelude::*, v2, VersionedXcm};
+use xcm_simulator::TestExt;
+
+use super::{bridge::ThisNetwork, bridged_net::BridgedRelay, Chain, Relay, RELAY_NETWORK};
+
+/// A fault of the transport, injected on a message with `inject_fault`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+	/// The message is never delivered.
+	Drop,
+	/// The message is delivered after the given number of blocks, see `MockNet::advance_blocks`.
+	Delay(u32),
+	/// The message is delivered twice.
+	Duplicate,
+	/// The message is delivered after the next message of the same route.
+	Reorder,
+	/// The message is delivered with corrupted bytes.
+	Corrupt(Corruption),
+}
+
+/// How the bytes of a message are corrupted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corruption {
+	/// The message does not decode as a versioned XCM.
+	InvalidFormat,
+	/// The message decodes as a versioned XCM that cannot be converted to the latest version.
+	BadVersion,
+}
+
+/// The chains a message is sent between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Route {
+	pub network: NetworkId,
+	pub from: Chain,
+	pub to: Chain,
+}
+
+struct Rule {
+	route: Route,
+	/// The number of messages of the route to let through before the fault applies.
+	skip: u32,
+	fault: Fault,
+}
+
+/// When a held back message is delivered.
+enum Release {
+	/// After the given number of blocks.
+	AfterBlocks(u32),
+	/// After the next message of the route.
+	AfterNextMessage,
+}
+
+struct Held {
+	route: Route,
+	release: Release,
+	deliver: Box<dyn FnOnce() -> Result<XcmHash, SendError>>,
+}
+
+thread_local! {
+	static RULES: RefCell<Vec<Rule>> = RefCell::new(Vec::new());
+	static HELD: RefCell<Vec<Held>> = RefCell::new(Vec::new());
+	/// The messages to corrupt on delivery, by route and message id.
+	static CORRUPTED: RefCell<Vec<(Route, XcmHash, Corruption)>> = RefCell::new(Vec::new());
+}
+
+/// Injects `fault` on the `nth` message, counting from 1, that `from` sends to `to` in `MockNet`
+/// from now on.
+pub fn inject_fault(from: Chain, to: Chain, nth: u32, fault: Fault) {
+	inject_fault_on(RELAY_NETWORK, from, to, nth, fault)
+}
+
+/// Injects `fault` on the `nth` message that `from` sends to `to` in the network `network`, see
+/// `inject_fault`.
+pub fn inject_fault_on(network: NetworkId, from: Chain, to: Chain, nth: u32, fault: Fault) {
+	assert!(nth > 0, "Messages are counted from 1");
+	let rule = Rule { route: Route { network, from, to }, skip: nth - 1, fault };
+	RULES.with(|rules| rules.borrow_mut().push(rule));
+}
+
+/// Forgets the injected faults, the held back messages and the messages to corrupt.
+pub fn clear_faults() {
+	RULES.with(|rules| rules.borrow_mut().clear());
+	HELD.with(|held| held.borrow_mut().clear());
+	CORRUPTED.with(|corrupted| corrupted.borrow_mut().clear());
+}
+
+/// Forgets the injected faults, the held back messages and the messages to corrupt of `network`,
+/// which is being reset.
+pub(super) fn reset_faults(network: NetworkId) {
+	RULES.with(|rules| rules.borrow_mut().retain(|rule| rule.route.network != network));
+	HELD.with(|held| held.borrow_mut().retain(|held| held.route.network != network));
+	CORRUPTED
+		.with(|corrupted| corrupted.borrow_mut().retain(|(route, ..)| route.network != network));
+}
+
+/// The number of messages held back by `Fault::Delay` or `Fault::Reorder`.
+pub fn held_messages() -> usize {
+	HELD.with(|held| held.borrow().len())
+}
+
+/// Advances the messages of `network` delayed with `Fault::Delay` by `blocks` blocks, and
+/// delivers the ones whose delay elapsed, in the order they were sent.
+pub(super) fn advance_delayed(network: NetworkId, blocks: u32) {
+	let released = HELD.with(|held| {
+		let mut held = held.borrow_mut();
+		let mut released = Vec::new();
+		let mut index = 0;
+		while index < held.len() {
+			if let (true, Release::AfterBlocks(remaining)) =
+				(held[index].route.network == network, &mut held[index].release)
+			{
+				*remaining = remaining.saturating_sub(blocks);
+				if *remaining == 0 {
+					released.push(held.remove(index));
+					continue
+				}
+			}
+			index += 1;
+		}
+		released
+	});
+	for message in released {
+		// Delivered from the relay chain, so that the network processes the message right after.
+		let deliver = || {
+			(message.deliver)()
+				.unwrap_or_else(|error| panic!("Held message not delivered: {error:?}"))
+		};
+		if network == RELAY_NETWORK {
+			Relay::execute_with(deliver);
+		} else {
+			BridgedRelay::execute_with(deliver);
+		}
+	}
+}
+
+/// The faults that apply to the message `route` is sending, counting the message for the
+/// others.
+fn take_faults(route: Route) -> Vec<Fault> {
+	RULES.with(|rules| {
+		let mut faults = Vec::new();
+		rules.borrow_mut().retain_mut(|rule| {
+			if rule.route != route {
+				return true
+			}
+			if rule.skip > 0 {
+				rule.skip -= 1;
+				return true
+			}
+			faults.push(rule.fault);
+			false
+		});
+		faults
+	})
+}
+
+/// Delivers the messages of `route` held back until its next message.
+fn release_reordered(route: Route) -> Result<(), SendError> {
+	let released: Vec<Held> = HELD.with(|held| {
+		let mut held = held.borrow_mut();
+		let (released, kept) = held.drain(..).partition(|message| {
+			message.route == route && matches!(message.release, Release::AfterNextMessage)
+		});
+		*held = kept;
+		released
+	});
+	for message in released {
+		(message.deliver)()?;
+	}
+	Ok(())
+}
+
+/// The id the destination executes `message` with, see `super::trace`.
+pub(super) fn message_id(message: &Xcm<()>) -> XcmHash {
+	VersionedXcm::from(message.clone()).using_encoded(sp_io::hashing::blake2_256)
+}
+
+/// Router of the chain `ThisChain`, applying the injected faults to the messages `Router`
+/// delivers.
+pub struct FaultRouter<Router, ThisChain>(PhantomData<(Router, ThisChain)>);
+impl<Router, ThisChain> SendXcm for FaultRouter<Router, ThisChain>
+where
+	Router: SendXcm + 'static,
+	Router::Ticket: 'static,
+	ThisChain: Get<Chain>,
+{
+	/// The ticket of `Router`, a second one to deliver a duplicate with, and the route and id of
+	/// the message.
+	type Ticket = (Router::Ticket, Option<Router::Ticket>, Option<(Route, XcmHash)>);
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let from = ThisChain::get();
+		let sent = destination.zip(message.clone()).and_then(|(destination, message)| {
+			let to = from.resolve(&destination)?;
+			let route = Route { network: ThisNetwork::get(), from, to };
+			Some((route, destination, message))
+		});
+		let (ticket, price) = Router::validate(destination, message)?;
+		let (route, destination, message) = match sent {
+			Some(sent) => sent,
+			None => return Ok(((ticket, None, None), price)),
+		};
+		// Only validated twice when a duplicate may be needed, `Router` may have side effects.
+		let duplicate = RULES.with(|rules| {
+			rules
+				.borrow()
+				.iter()
+				.any(|rule| rule.route == route && rule.fault == Fault::Duplicate)
+		});
+		let spare = if duplicate {
+			Router::validate(&mut Some(destination), &mut Some(message.clone()))
+				.ok()
+				.map(|(spare, _)| spare)
+		} else {
+			None
+		};
+		Ok(((ticket, spare, Some((route, message_id(&message)))), price))
+	}
+
+	fn deliver((ticket, spare, sent): Self::Ticket) -> Result<XcmHash, SendError> {
+		let (route, id) = match sent {
+			Some(sent) => sent,
+			None => return Router::deliver(ticket),
+		};
+		let faults = take_faults(route);
+
+		if faults.contains(&Fault::Drop) {
+			return Ok(id)
+		}
+		for fault in &faults {
+			if let Fault::Corrupt(corruption) = fault {
+				CORRUPTED.with(|corrupted| corrupted.borrow_mut().push((route, id, *corruption)));
+			}
+		}
+		let release = faults.iter().find_map(|fault| match fault {
+			Fault::Delay(blocks) => Some(Release::AfterBlocks(*blocks)),
+			Fault::Reorder => Some(Release::AfterNextMessage),
+			_ => None,
+		});
+		let mut tickets = vec![ticket];
+		if faults.contains(&Fault::Duplicate) {
+			tickets.extend(spare);
+		}
+		let deliver = move || {
+			tickets.into_iter().try_for_each(|ticket| Router::deliver(ticket).map(|_| ()))?;
+			Ok(id)
+		};
+
+		match release {
+			Some(release) => {
+				// Held back messages are released by a later message, not by themselves.
+				release_reordered(route)?;
+				let held = Held { route, release, deliver: Box::new(deliver) };
+				HELD.with(|messages| messages.borrow_mut().push(held));
+				Ok(id)
+			},
+			None => {
+				deliver()?;
+				release_reordered(route)?;
+				Ok(id)
+			},
+		}
+	}
+}
+
+/// The bytes to deliver instead of `message`, a versioned XCM sent on `route`, if it is to be
+/// corrupted.
+fn corrupt(route: Route, message: &[u8]) -> Option<Vec<u8>> {
+	let id = sp_io::hashing::blake2_256(message);
+	let corruption = CORRUPTED.with(|corrupted| {
+		let mut corrupted = corrupted.borrow_mut();
+		let index = corrupted.iter().position(|(to_corrupt, to_corrupt_id, _)| {
+			*to_corrupt == route && *to_corrupt_id == id
+		})?;
+		Some(corrupted.remove(index).2)
+	})?;
+	Some(match corruption {
+		// No version of `VersionedXcm` is encoded with this index.
+		Corruption::InvalidFormat =>
+			[&[u8::MAX][..], message.get(1..).unwrap_or_default()].concat(),
+		// Named networks do not exist in XCM v3.
+		Corruption::BadVersion => {
+			let network = v2::NetworkId::Named(
+				b"corrupted".to_vec().try_into().expect("shorter than 32 bytes; qed"),
+			);
+			let junction = v2::Junction::AccountId32 { network, id: [0; 32] };
+			let instruction = v2::Instruction::<()>::DescendOrigin(v2::Junctions::X1(junction));
+			VersionedXcm::V2(v2::Xcm(vec![instruction])).encode()
+		},
+	})
+}
+
+/// The bytes to deliver instead of `message`, a message of the latest version sent on `route`:
+/// corrupted if a fault was injected on it, or encoded in the version it was wrapped in.
+fn on_the_wire(route: Route, message: &[u8]) -> Option<Vec<u8>> {
+	corrupt(route, message).or_else(|| super::versions::as_wrapped(route, message))
+}
+
+/// The bytes of the upward message `message` of the parachain `para_id` to process on the relay
+/// chain, corrupted if a fault was injected on it, see `FaultyHandler`.
+pub fn corrupt_upward_message(para_id: u32, message: &[u8]) -> Vec<u8> {
+	let route = Route { network: ThisNetwork::get(), from: Chain::Para(para_id), to: Chain::Relay };
+	on_the_wire(route, message).unwrap_or_else(|| message.to_vec())
+}
+
+/// Message handler of a parachain, handing `Handler` the messages it receives as they were put
+/// on the wire: corrupted if a fault was injected on them, and in the XCM version they were
+/// wrapped in, see `super::versions`.
+pub struct FaultyHandler<Handler>(PhantomData<Handler>);
+
+impl<Handler: XcmpMessageHandler + Get<ParaId>> XcmpMessageHandler for FaultyHandler<Handler> {
+	fn handle_xcmp_messages<'a, I: Iterator<Item = (ParaId, RelayBlockNumber, &'a [u8])>>(
+		iter: I,
+		max_weight: Weight,
+	) -> Weight {
+		let to = Chain::Para(Handler::get().into());
+		let messages: Vec<_> = iter
+			.map(|(sender, sent_at, data)| {
+				let route =
+					Route { network: ThisNetwork::get(), from: Chain::Para(sender.into()), to };
+				// The message follows the byte of its `XcmpMessageFormat`.
+				let data = match on_the_wire(route, data.get(1..).unwrap_or_default()) {
+					Some(corrupted) => [&data[..1], &corrupted[..]].concat(),
+					None => data.to_vec(),
+				};
+				(sender, sent_at, data)
+			})
+			.collect();
+		Handler::handle_xcmp_messages(
+			messages.iter().map(|(sender, sent_at, data)| (*sender, *sent_at, &data[..])),
+			max_weight,
+		)
+	}
+}
+
+impl<Handler: DmpMessageHandler + Get<ParaId>> DmpMessageHandler for FaultyHandler<Handler> {
+	fn handle_dmp_messages(
+		iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>,
+		limit: Weight,
+	) -> Weight {
+		let to = Chain::Para(Handler::get().into());
+		let route = Route { network: ThisNetwork::get(), from: Chain::Relay, to };
+		Handler::handle_dmp_messages(
+			iter.map(|(sent_at, data)| (sent_at, on_the_wire(route, &data).unwrap_or(data))),
+			limit,
+		)
+	}
+}
@@ -0,0 +1,119 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fee estimation of a XCM program on every chain it is executed on.
+
+use frame_support::weights::Weight;
+use xcm::latest::prelude::*;
+
+use super::{
+	asset_hub, bridge_hub, evm_parachain, mock_net, parachain, relay_chain, Balance, Chain,
+	ParaKind,
+};
+
+/// Weight and fee of the part of a XCM program executed on one chain.
+///
+/// Fees are in the relay chain token, which every chain of the network charges in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HopFee {
+	pub chain: Chain,
+	pub weight: Weight,
+	pub fee: Balance,
+}
+
+/// Estimates the fees of `message` when executed on `chain`, followed by the fees of the messages
+/// it sends with `InitiateReserveWithdraw`, `DepositReserveAsset`, `TransferReserveAsset` and
+/// `InitiateTeleport`, in the order they are executed in.
+///
+/// The messages sent include the two instructions the executor prepends to the inner program,
+/// e.g. `WithdrawAsset` and `ClearOrigin` for `InitiateReserveWithdraw`. Instructions added by
+/// the sender before `message` reaches `chain`, like the `DescendOrigin` of
+/// `pallet_xcm::send_xcm`, are not. Messages exported over the bridge with `ExportMessage` are
+/// not followed. Panics if a message is sent outside of the network.
+pub fn estimate_fees<Call>(chain: Chain, message: &Xcm<Call>) -> Vec<HopFee> {
+	let mut hops = Vec::new();
+	estimate_hop(chain, Xcm::<()>::from(message.clone()), &mut hops);
+	hops
+}
+
+/// Sum of the fees of `estimate_fees`.
+pub fn estimate_total_fee<Call>(chain: Chain, message: &Xcm<Call>) -> Balance {
+	estimate_fees(chain, message).iter().map(|hop| hop.fee).sum()
+}
+
+fn estimate_hop(chain: Chain, message: Xcm<()>, hops: &mut Vec<HopFee>) {
+	let (weight, fee) = match chain {
+		Chain::Relay => {
+			let message = Xcm::from(message.clone());
+			(relay_chain::estimate_weight_for(&message), relay_chain::estimate_fee_for(&message))
+		},
+		Chain::Para(para_id) => match mock_net().kind_of(para_id) {
+			Some(ParaKind::Parachain) => {
+				let message = Xcm::from(message.clone());
+				(parachain::estimate_weight_for(&message), parachain::estimate_fee_for(&message))
+			},
+			Some(ParaKind::AssetHub) => {
+				let message = Xcm::from(message.clone());
+				(asset_hub::estimate_weight_for(&message), asset_hub::estimate_fee_for(&message))
+			},
+			Some(ParaKind::BridgeHub) => {
+				let message = Xcm::from(message.clone());
+				(bridge_hub::estimate_weight_for(&message), bridge_hub::estimate_fee_for(&message))
+			},
+			Some(ParaKind::Evm) => {
+				let message = Xcm::from(message.clone());
+				(
+					evm_parachain::estimate_weight_for(&message),
+					evm_parachain::estimate_fee_for(&message),
+				)
+			},
+			None => panic!("No parachain exists with para_id = {para_id}"),
+		},
+	};
+	hops.push(HopFee { chain, weight, fee });
+
+	let mut sent = Vec::new();
+	sent_messages(chain, &message, &mut sent);
+	for (destination, message) in sent {
+		estimate_hop(destination, message, hops);
+	}
+}
+
+/// Collects the messages sent by `message` when executed on `chain`, including those of error
+/// handlers and appendices.
+fn sent_messages(chain: Chain, message: &Xcm<()>, sent: &mut Vec<(Chain, Xcm<()>)>) {
+	for instruction in message.0.iter() {
+		let (destination, prefix, xcm) = match instruction {
+			InitiateReserveWithdraw { reserve, xcm, .. } =>
+				(reserve, WithdrawAsset(MultiAssets::new()), xcm),
+			DepositReserveAsset { dest, xcm, .. } | TransferReserveAsset { dest, xcm, .. } =>
+				(dest, ReserveAssetDeposited(MultiAssets::new()), xcm),
+			InitiateTeleport { dest, xcm, .. } =>
+				(dest, ReceiveTeleportedAsset(MultiAssets::new()), xcm),
+			SetErrorHandler(inner) | SetAppendix(inner) => {
+				sent_messages(chain, inner, sent);
+				continue
+			},
+			_ => continue,
+		};
+		let destination = chain
+			.resolve(destination)
+			.unwrap_or_else(|| panic!("{destination:?} is not a chain of the network"));
+		let mut inner = vec![prefix, ClearOrigin];
+		inner.extend(xcm.0.iter().cloned());
+		sent.push((destination, Xcm(inner)));
+	}
+}
@@ -0,0 +1,369 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Genesis state of a single chain of the test network.
+
+use frame_support::traits::GenesisBuild;
+use sp_core::H160;
+use sp_runtime::{traits::StaticLookup, AccountId32, Storage};
+use xcm::latest::prelude::*;
+
+use super::{
+	parachain::{self, AssetIdForAssets},
+	trader::FeeRate,
+	Balance, ADMIN, ALICE, ALITH, BOB, INITIAL_BALANCE,
+};
+
+/// Metadata of an asset class in `pallet_assets`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetMetadata {
+	pub name: Vec<u8>,
+	pub symbol: Vec<u8>,
+	pub decimals: u8,
+}
+
+/// An asset class created in `pallet_assets`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetClass {
+	pub id: AssetIdForAssets,
+	pub owner: AccountId32,
+	pub is_sufficient: bool,
+	pub min_balance: Balance,
+	pub metadata: Option<AssetMetadata>,
+}
+
+impl AssetClass {
+	/// An insufficient asset class owned by `ADMIN`, without metadata.
+	pub fn new(id: AssetIdForAssets) -> Self {
+		Self { id, owner: ADMIN, is_sufficient: false, min_balance: 1, metadata: None }
+	}
+
+	pub fn with_metadata(mut self, name: &str, symbol: &str, decimals: u8) -> Self {
+		self.metadata = Some(AssetMetadata { name: name.into(), symbol: symbol.into(), decimals });
+		self
+	}
+}
+
+/// A collection of the uniques pallet of the chain, with its minted items.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniquesCollection {
+	pub id: u32,
+	pub owner: AccountId32,
+	/// Minted items and their owners.
+	pub items: Vec<(u32, AccountId32)>,
+}
+
+/// Sovereign accounts to fund at genesis, resolved with the `SovereignAccountOf` of the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sovereign {
+	/// The sovereign account of a location, as seen from the chain.
+	Location(MultiLocation),
+	/// The sovereign accounts of all other parachains of the network.
+	Parachains,
+	/// The sovereign accounts of an account on all other parachains of the network.
+	ParachainAccounts(AccountId32),
+}
+
+/// Funds the sovereign account(s) of `of` with `amount` of the native token, or of the
+/// `pallet_assets` asset class `asset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SovereignFunding {
+	pub of: Sovereign,
+	pub asset: Option<AssetIdForAssets>,
+	pub amount: Balance,
+}
+
+/// Genesis state of a chain.
+///
+/// Chains without `pallet_assets` ignore the asset classes and asset balances, chains without
+/// `pallet_uniques` the collections. Only the `parachain` runtime mock has fee rates. Chains
+/// with 20 byte accounts fund `evm_balances` instead of `balances`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenesisSpec {
+	/// Accounts and their balance of the native token.
+	pub balances: Vec<(AccountId32, Balance)>,
+	/// 20 byte accounts and their balance of the native token, on chains with such accounts.
+	pub evm_balances: Vec<(H160, Balance)>,
+	pub assets: Vec<AssetClass>,
+	pub asset_balances: Vec<(AssetIdForAssets, AccountId32, Balance)>,
+	pub collections: Vec<UniquesCollection>,
+	pub sovereign_funding: Vec<SovereignFunding>,
+	/// Assets fees can be paid in besides the relay token, and their fee rate.
+	pub fee_rates: Vec<(MultiLocation, FeeRate)>,
+	/// The XCM version the chain speaks, the latest if `None`.
+	pub xcm_version: Option<XcmVersion>,
+	/// The XCM version the chain wraps its messages in for destinations whose version it does
+	/// not know yet. If `None`, the chain sends no message to them until it learns their version.
+	pub safe_xcm_version: Option<XcmVersion>,
+}
+
+impl GenesisSpec {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn balance(mut self, who: AccountId32, amount: Balance) -> Self {
+		self.balances.push((who, amount));
+		self
+	}
+
+	pub fn evm_balance(mut self, who: H160, amount: Balance) -> Self {
+		self.evm_balances.push((who, amount));
+		self
+	}
+
+	pub fn asset(mut self, class: AssetClass) -> Self {
+		self.assets.push(class);
+		self
+	}
+
+	pub fn asset_balance(
+		mut self,
+		id: AssetIdForAssets,
+		who: AccountId32,
+		amount: Balance,
+	) -> Self {
+		self.asset_balances.push((id, who, amount));
+		self
+	}
+
+	pub fn collection(mut self, collection: UniquesCollection) -> Self {
+		self.collections.push(collection);
+		self
+	}
+
+	pub fn fund_sovereign(
+		mut self,
+		of: Sovereign,
+		asset: Option<AssetIdForAssets>,
+		amount: Balance,
+	) -> Self {
+		self.sovereign_funding.push(SovereignFunding { of, asset, amount });
+		self
+	}
+
+	pub fn fee_rate(mut self, asset: impl Into<MultiLocation>, rate: FeeRate) -> Self {
+		self.fee_rates.push((asset.into(), rate));
+		self
+	}
+
+	/// Makes the chain speak XCM `version` and reject the messages of later versions.
+	pub fn xcm_version(mut self, version: XcmVersion) -> Self {
+		self.xcm_version = Some(version);
+		self
+	}
+
+	/// Makes the chain wrap its messages in XCM `version` for the destinations whose version it
+	/// does not know yet, while `pallet_xcm` negotiates their version. The default genesis of
+	/// every runtime mock uses the latest version.
+	pub fn safe_xcm_version(mut self, version: XcmVersion) -> Self {
+		self.safe_xcm_version = Some(version);
+		self
+	}
+
+	/// The default genesis of the relay chain.
+	pub fn relay_default() -> Self {
+		Self::new()
+			.safe_xcm_version(xcm::latest::VERSION)
+			.balance(ALICE, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::ParachainAccounts(ALICE), None, INITIAL_BALANCE)
+	}
+
+	/// The default genesis of a chain built from the `parachain` runtime mock.
+	///
+	/// Asset class `0` is the derivative of the relay chain token and asset class `<para_id>` the
+	/// derivative of the native token of the sibling `para_id`. Fees can be paid in all of them
+	/// and in the native token.
+	pub fn parachain_default(siblings: &[u32]) -> Self {
+		let mut spec = Self::new()
+			.safe_xcm_version(xcm::latest::VERSION)
+			.balance(ALICE, INITIAL_BALANCE)
+			.balance(BOB, INITIAL_BALANCE)
+			.balance(parachain::TreasuryAccount::get(), INITIAL_BALANCE)
+			.fee_rate(Here, parachain::NativeFeeRate::get())
+			.asset(AssetClass::new(0))
+			.asset_balance(0, ALICE, INITIAL_BALANCE)
+			// Native token and relay token derivative, for fee payment
+			.fund_sovereign(Sovereign::Location(Parent.into()), None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Location(Parent.into()), Some(0), INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, Some(0), INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::ParachainAccounts(ALICE), None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::ParachainAccounts(ALICE), Some(0), INITIAL_BALANCE);
+		for &para_id in siblings {
+			spec = spec
+				.asset(AssetClass::new(para_id as AssetIdForAssets))
+				.asset_balance(para_id as AssetIdForAssets, ALICE, INITIAL_BALANCE)
+				.fee_rate((Parent, Parachain(para_id)), parachain::SiblingFeeRate::get());
+		}
+		spec
+	}
+
+	/// The default genesis of a chain built from the `asset_hub` runtime mock.
+	///
+	/// The native token of the asset hub is the relay chain token, so there are no derivatives
+	/// to create in `pallet_assets`.
+	pub fn asset_hub_default() -> Self {
+		Self::new()
+			.safe_xcm_version(xcm::latest::VERSION)
+			.balance(ALICE, INITIAL_BALANCE)
+			.balance(BOB, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Location(Parent.into()), None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::ParachainAccounts(ALICE), None, INITIAL_BALANCE)
+	}
+
+	/// The default genesis of a chain built from the `bridge_hub` runtime mock.
+	///
+	/// Like the asset hub, the bridge hub uses the relay chain token as its native token.
+	pub fn bridge_hub_default() -> Self {
+		Self::new()
+			.safe_xcm_version(xcm::latest::VERSION)
+			.balance(ALICE, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Location(Parent.into()), None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, None, INITIAL_BALANCE)
+	}
+
+	/// The default genesis of a chain built from the `evm_parachain` runtime mock.
+	///
+	/// Its native token is the relay chain token, like on the asset hub.
+	pub fn evm_parachain_default() -> Self {
+		Self::new()
+			.safe_xcm_version(xcm::latest::VERSION)
+			.evm_balance(ALITH, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Location(Parent.into()), None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::Parachains, None, INITIAL_BALANCE)
+			.fund_sovereign(Sovereign::ParachainAccounts(ALICE), None, INITIAL_BALANCE)
+	}
+
+	/// Native and asset balances, with the sovereign accounts resolved.
+	///
+	/// `parents` is the number of parents to the relay chain from the chain, `para_ids` are the
+	/// other parachains of the network and `sovereign_of` is the `SovereignAccountOf` of the chain.
+	pub(crate) fn resolve_balances(
+		&self,
+		parents: u8,
+		para_ids: &[u32],
+		sovereign_of: impl Fn(MultiLocation) -> AccountId32,
+	) -> (Vec<(AccountId32, Balance)>, Vec<(AssetIdForAssets, AccountId32, Balance)>) {
+		let (sovereign_balances, sovereign_asset_balances) =
+			self.resolve_sovereign_balances(parents, para_ids, sovereign_of);
+		let balances = self.balances.iter().cloned().chain(sovereign_balances).collect();
+		let asset_balances =
+			self.asset_balances.iter().cloned().chain(sovereign_asset_balances).collect();
+		(balances, asset_balances)
+	}
+
+	/// Balances of the sovereign accounts to fund, for chains with accounts of any type, like
+	/// `resolve_balances`.
+	pub(crate) fn resolve_sovereign_balances<AccountId>(
+		&self,
+		parents: u8,
+		para_ids: &[u32],
+		sovereign_of: impl Fn(MultiLocation) -> AccountId,
+	) -> (Vec<(AccountId, Balance)>, Vec<(AssetIdForAssets, AccountId, Balance)>) {
+		let mut balances = Vec::new();
+		let mut asset_balances = Vec::new();
+		for SovereignFunding { of, asset, amount } in &self.sovereign_funding {
+			let locations: Vec<MultiLocation> = match of {
+				Sovereign::Location(location) => vec![*location],
+				Sovereign::Parachains => para_ids
+					.iter()
+					.map(|&para_id| MultiLocation::new(parents, X1(Parachain(para_id))))
+					.collect(),
+				Sovereign::ParachainAccounts(who) => para_ids
+					.iter()
+					.map(|&para_id| {
+						let who = Junction::AccountId32 { network: None, id: who.clone().into() };
+						MultiLocation::new(parents, X2(Parachain(para_id), who))
+					})
+					.collect(),
+			};
+			for location in locations {
+				let account = sovereign_of(location);
+				match asset {
+					None => balances.push((account, *amount)),
+					Some(id) => asset_balances.push((*id, account, *amount)),
+				}
+			}
+		}
+		(balances, asset_balances)
+	}
+}
+
+pub(crate) fn assimilate_balances<R>(storage: &mut Storage, balances: Vec<(R::AccountId, Balance)>)
+where
+	R: pallet_balances::Config<Balance = Balance>,
+{
+	pallet_balances::GenesisConfig::<R> { balances }
+		.assimilate_storage(storage)
+		.unwrap();
+}
+
+pub(crate) fn assimilate_assets<R>(
+	storage: &mut Storage,
+	assets: &[AssetClass],
+	accounts: Vec<(AssetIdForAssets, AccountId32, Balance)>,
+) where
+	R: pallet_assets::Config<AssetId = AssetIdForAssets, Balance = Balance>
+		+ frame_system::Config<AccountId = AccountId32>,
+{
+	pallet_assets::GenesisConfig::<R> {
+		assets: assets
+			.iter()
+			.map(|class| (class.id, class.owner.clone(), class.is_sufficient, class.min_balance))
+			.collect(),
+		metadata: assets
+			.iter()
+			.filter_map(|class| {
+				class.metadata.clone().map(|AssetMetadata { name, symbol, decimals }| {
+					(class.id, name, symbol, decimals)
+				})
+			})
+			.collect(),
+		accounts,
+	}
+	.assimilate_storage(storage)
+	.unwrap();
+}
+
+/// Creates the collections and mints their items. Must be called within the externalities of
+/// the chain, as the uniques pallet has no genesis config.
+pub(crate) fn create_collections<R>(collections: &[UniquesCollection])
+where
+	R: pallet_uniques::Config<CollectionId = u32, ItemId = u32>
+		+ frame_system::Config<AccountId = AccountId32>,
+{
+	for UniquesCollection { id, owner, items } in collections {
+		pallet_uniques::Pallet::<R>::force_create(
+			frame_system::RawOrigin::Root.into(),
+			*id,
+			R::Lookup::unlookup(owner.clone()),
+			true,
+		)
+		.unwrap();
+		for (item, item_owner) in items {
+			pallet_uniques::Pallet::<R>::mint(
+				frame_system::RawOrigin::Signed(owner.clone()).into(),
+				*id,
+				*item,
+				R::Lookup::unlookup(item_owner.clone()),
+			)
+			.unwrap();
+		}
+	}
+}
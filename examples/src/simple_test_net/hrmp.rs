@@ -0,0 +1,77 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! HRMP channels between the parachains of the network.
+//!
+//! The channels are opened, accepted and closed with the `hrmp` pallet of the relay chain mock.
+//! The router of every parachain mock only sends to a sibling over an open channel of the relay
+//! chain of its network.
+
+use core::marker::PhantomData;
+
+use polkadot_parachain::primitives::{HrmpChannelId, Id as ParaId};
+use polkadot_runtime_parachains::hrmp;
+use sp_runtime::traits::Get;
+use xcm::latest::prelude::*;
+use xcm_simulator::TestExt;
+
+use super::{bridge::ThisNetwork, bridged_net::BridgedRelay, relay_chain, Relay, RELAY_NETWORK};
+
+/// Whether the HRMP channel from `sender` to `recipient` is open in `MockNet`.
+pub fn hrmp_channel_exists(sender: u32, recipient: u32) -> bool {
+	Relay::execute_without_dispatch(|| channel_exists(sender, recipient))
+}
+
+/// Whether the HRMP channel from `sender` to `recipient` is open in `BridgedMockNet`.
+pub fn bridged_hrmp_channel_exists(sender: u32, recipient: u32) -> bool {
+	BridgedRelay::execute_without_dispatch(|| channel_exists(sender, recipient))
+}
+
+fn channel_exists(sender: u32, recipient: u32) -> bool {
+	let channel_id = HrmpChannelId { sender: sender.into(), recipient: recipient.into() };
+	hrmp::HrmpChannels::<relay_chain::Runtime>::contains_key(channel_id)
+}
+
+/// Router of the parachain `ParachainId`, rejecting messages to a sibling without an open HRMP
+/// channel to it on the network of the parachain.
+pub struct HrmpChannelGuard<Router, ParachainId>(PhantomData<(Router, ParachainId)>);
+impl<Router: SendXcm, ParachainId: Get<ParaId>> SendXcm for HrmpChannelGuard<Router, ParachainId> {
+	type Ticket = Router::Ticket;
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		if let Some(MultiLocation { parents: 1, interior }) = destination {
+			if let Some(Parachain(recipient)) = interior.first() {
+				let sender = ParachainId::get().into();
+				let exists = if ThisNetwork::get() == RELAY_NETWORK {
+					hrmp_channel_exists(sender, *recipient)
+				} else {
+					bridged_hrmp_channel_exists(sender, *recipient)
+				};
+				if !exists {
+					return Err(SendError::Transport("No open HRMP channel to the destination"))
+				}
+			}
+		}
+		Router::validate(destination, message)
+	}
+
+	fn deliver(ticket: Self::Ticket) -> Result<XcmHash, SendError> {
+		Router::deliver(ticket)
+	}
+}
@@ -0,0 +1,250 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of SCALE-encoded XCM values, for the `xcm-inspect` binary.
+//!
+//! ```ignore
+//! let decoded = Decoded::decode(Kind::Xcm, &from_hex("0x0310...")?)?;
+//! println!("{decoded}");
+//! println!("{}", decoded.into_version(2)?.encode_hex());
+//! ```
+
+use core::{fmt, str::FromStr};
+
+use codec::{Decode, DecodeAll, DecodeLimit, Encode};
+use xcm::{
+	latest::prelude::*, IntoVersion, VersionedMultiAssets, VersionedMultiLocation, VersionedXcm,
+	MAX_XCM_DECODE_DEPTH,
+};
+
+/// The type of an encoded value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+	/// `VersionedXcm<()>`
+	Xcm,
+	/// `VersionedMultiLocation`
+	Location,
+	/// `VersionedMultiAssets`
+	Assets,
+}
+
+impl FromStr for Kind {
+	type Err = InspectError;
+
+	fn from_str(kind: &str) -> Result<Self, Self::Err> {
+		match kind {
+			"xcm" => Ok(Kind::Xcm),
+			"location" => Ok(Kind::Location),
+			"assets" => Ok(Kind::Assets),
+			_ => Err(InspectError::UnknownKind(kind.to_string())),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum InspectError {
+	Hex(hex::FromHexError),
+	Decode(codec::Error),
+	/// The number of bytes left after the decoded value.
+	TrailingBytes(usize),
+	/// The value cannot be expressed in this version.
+	Version(XcmVersion),
+	UnknownKind(String),
+}
+
+impl fmt::Display for InspectError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InspectError::Hex(error) => write!(f, "invalid hex: {error}"),
+			InspectError::Decode(error) => write!(f, "cannot decode: {error}"),
+			InspectError::TrailingBytes(count) => write!(f, "{count} bytes left after the value"),
+			InspectError::Version(version) => write!(f, "cannot be expressed in version {version}"),
+			InspectError::UnknownKind(kind) =>
+				write!(f, "unknown kind {kind:?}, expected xcm, location or assets"),
+		}
+	}
+}
+
+impl std::error::Error for InspectError {}
+
+impl From<codec::Error> for InspectError {
+	fn from(error: codec::Error) -> Self {
+		InspectError::Decode(error)
+	}
+}
+
+/// A decoded value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Decoded {
+	Xcm(VersionedXcm<()>),
+	Location(VersionedMultiLocation),
+	Assets(VersionedMultiAssets),
+}
+
+impl Decoded {
+	/// Decodes all of `bytes` as a value of `kind`.
+	pub fn decode(kind: Kind, mut bytes: &[u8]) -> Result<Self, InspectError> {
+		let decoded = match kind {
+			Kind::Xcm => {
+				let xcm = VersionedXcm::decode_with_depth_limit(MAX_XCM_DECODE_DEPTH, &mut bytes)?;
+				Decoded::Xcm(xcm)
+			},
+			Kind::Location => Decoded::Location(VersionedMultiLocation::decode(&mut bytes)?),
+			Kind::Assets => Decoded::Assets(VersionedMultiAssets::decode(&mut bytes)?),
+		};
+		match bytes.len() {
+			0 => Ok(decoded),
+			count => Err(InspectError::TrailingBytes(count)),
+		}
+	}
+
+	/// The XCM version the value is encoded in.
+	pub fn version(&self) -> XcmVersion {
+		match self {
+			Decoded::Xcm(VersionedXcm::V2(_)) |
+			Decoded::Location(VersionedMultiLocation::V2(_)) |
+			Decoded::Assets(VersionedMultiAssets::V2(_)) => 2,
+			Decoded::Xcm(VersionedXcm::V3(_)) |
+			Decoded::Location(VersionedMultiLocation::V3(_)) |
+			Decoded::Assets(VersionedMultiAssets::V3(_)) => 3,
+		}
+	}
+
+	/// The value, converted to `version`.
+	pub fn into_version(self, version: XcmVersion) -> Result<Self, InspectError> {
+		let converted = match self {
+			Decoded::Xcm(xcm) => xcm.into_version(version).map(Decoded::Xcm),
+			Decoded::Location(location) => location.into_version(version).map(Decoded::Location),
+			Decoded::Assets(assets) => assets.into_version(version).map(Decoded::Assets),
+		};
+		converted.map_err(|()| InspectError::Version(version))
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		match self {
+			Decoded::Xcm(xcm) => xcm.encode(),
+			Decoded::Location(location) => location.encode(),
+			Decoded::Assets(assets) => assets.encode(),
+		}
+	}
+
+	pub fn encode_hex(&self) -> String {
+		to_hex(&self.encode())
+	}
+
+	/// The encoded calls of the `Transact` instructions of the message, including the ones of its
+	/// error handler, appendix and the programs it sends, in the order they appear.
+	pub fn transact_calls(&self) -> Vec<Vec<u8>> {
+		let mut calls = Vec::new();
+		if let Decoded::Xcm(xcm) = self {
+			if let Ok(xcm) = Xcm::<()>::try_from(xcm.clone()) {
+				collect_calls(&xcm, &mut calls);
+			}
+		}
+		calls
+	}
+}
+
+impl fmt::Display for Decoded {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Decoded::Xcm(xcm) => write!(f, "{xcm:#?}"),
+			Decoded::Location(location) => write!(f, "{location:#?}"),
+			Decoded::Assets(assets) => write!(f, "{assets:#?}"),
+		}
+	}
+}
+
+fn collect_calls(xcm: &Xcm<()>, calls: &mut Vec<Vec<u8>>) {
+	for instruction in &xcm.0 {
+		match instruction {
+			Transact { call, .. } => calls.push(call.clone().into_encoded()),
+			SetErrorHandler(xcm) |
+			SetAppendix(xcm) |
+			DepositReserveAsset { xcm, .. } |
+			InitiateReserveWithdraw { xcm, .. } |
+			InitiateTeleport { xcm, .. } |
+			TransferReserveAsset { xcm, .. } |
+			ExportMessage { xcm, .. } => collect_calls(xcm, calls),
+			_ => (),
+		}
+	}
+}
+
+/// Decodes `encoded` as a call of a runtime, like `parachain::RuntimeCall`.
+pub fn decode_call<Call: Decode>(encoded: &[u8]) -> Result<Call, InspectError> {
+	Ok(Call::decode_all(&mut &encoded[..])?)
+}
+
+/// The bytes of `input`, hex-encoded with or without a `0x` prefix.
+pub fn from_hex(input: &str) -> Result<Vec<u8>, InspectError> {
+	let input = input.trim();
+	hex::decode(input.strip_prefix("0x").unwrap_or(input)).map_err(InspectError::Hex)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+	format!("0x{}", hex::encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simple_test_net::{parachain, CENTS, RELAY_NETWORK};
+	use frame_support::weights::Weight;
+
+	/// A message transacting a call on parachain A, hex-encoded like chain explorers show it, is
+	/// decoded like `xcm-inspect` does. It is converted to XCM version 2 and re-encoded, and the
+	/// call of its `Transact` is decoded as a call of the parachain mock.
+	#[test]
+	fn inspect_encoded_message() {
+		let call = parachain::RuntimeCall::System(
+			frame_system::Call::<parachain::Runtime>::remark_with_event { remark: vec![1, 2, 3] },
+		);
+		let message: Xcm<()> = Xcm(vec![
+			WithdrawAsset((Parent, CENTS).into()),
+			BuyExecution { fees: (Parent, CENTS).into(), weight_limit: WeightLimit::Unlimited },
+			Transact {
+				origin_kind: OriginKind::SovereignAccount,
+				require_weight_at_most: Weight::from_parts(1_000_000_000, 64 * 1024),
+				call: call.encode().into(),
+			},
+		]);
+		let hex = to_hex(&VersionedXcm::V3(message.clone()).encode());
+
+		let decoded = Decoded::decode(Kind::Xcm, &from_hex(&hex).unwrap()).unwrap();
+		assert_eq!(decoded, Decoded::Xcm(VersionedXcm::V3(message)));
+		assert_eq!(decoded.version(), 3);
+		assert_eq!(decoded.encode_hex(), hex);
+
+		let v2 = decoded.clone().into_version(2).unwrap();
+		assert_eq!(v2.version(), 2);
+		assert_eq!(Decoded::decode(Kind::Xcm, &from_hex(&v2.encode_hex()).unwrap()).unwrap(), v2);
+
+		let calls = decoded.transact_calls();
+		assert_eq!(calls, vec![call.encode()]);
+		assert_eq!(decode_call::<parachain::RuntimeCall>(&calls[0]).unwrap(), call);
+
+		// Bytes after the value
+		let trailing = from_hex(&format!("{hex}00")).unwrap();
+		let decoded = Decoded::decode(Kind::Xcm, &trailing);
+		assert!(matches!(decoded, Err(InspectError::TrailingBytes(1))));
+
+		// Locations in other consensus systems cannot be expressed in version 2
+		let location = MultiLocation::new(2, X1(GlobalConsensus(RELAY_NETWORK)));
+		let location = Decoded::Location(VersionedMultiLocation::V3(location));
+		assert!(matches!(location.into_version(2), Err(InspectError::Version(2))));
+	}
+}
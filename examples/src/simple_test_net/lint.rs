@@ -0,0 +1,295 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Static checks of XCM programs against the barriers of the runtime mocks and the registers of
+//! the XCVM, see `src/reference/xcvm-registers.md`.
+//!
+//! The barriers of all runtime mocks let through the programs that start, after the instructions
+//! computing their origin (`WithComputedOrigin`), with:
+//! - an instruction loading the holding register, optionally followed by `ClearOrigin`, then
+//!   `BuyExecution` (`AllowTopLevelPaidExecutionFrom`);
+//! - `UnpaidExecution`, from the origins allowed to (`AllowExplicitUnpaidExecutionFrom`);
+//! - `QueryResponse`, `SubscribeVersion`, `UnsubscribeVersion`, `NoteUnlockable` or
+//!   `UnlockAsset`, on the chains with the matching barrier.
+//!
+//! The programs sent to other chains are prefixed with an instruction loading the holding
+//! register and `ClearOrigin` by the executor, so they start with `BuyExecution`.
+//!
+//! The checks do not know the origin, the chain or the state the program is executed with, so
+//! a program without diagnostics can still fail, and the programs executed locally with
+//! `pallet_xcm::execute` are not subject to the barrier.
+
+use core::fmt;
+
+use xcm::latest::prelude::*;
+
+/// What a diagnostic is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lint {
+	/// No barrier lets the program through.
+	Barrier,
+	/// `BuyExecution` does not directly follow the instruction loading the holding register.
+	BuyExecutionNotAfterLoad,
+	/// The instruction takes assets from the holding register, which is empty.
+	EmptyHolding,
+	/// The fees of `BuyExecution` are not in the holding register.
+	FeesNotInHolding,
+	/// The fees of `BuyExecution` are more than the holding register has of their asset.
+	FeesExceedHolding,
+	/// `ExpectError` of an error outside of an error handler or appendix, where the error
+	/// register is always empty. `ExpectError(None)` always holds there, so it is not reported.
+	ExpectErrorOutsideHandler,
+}
+
+/// A problem found in an instruction of a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+	/// Index of the instruction in the program, followed by its indices in the nested programs
+	/// it is part of, e.g. `[2, 0]` for the first instruction of the program of the third one.
+	pub path: Vec<usize>,
+	pub lint: Lint,
+	pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let path: Vec<String> = self.path.iter().map(|index| index.to_string()).collect();
+		write!(f, "warning[{:?}] at #{}: {}", self.lint, path.join("."), self.message)
+	}
+}
+
+/// The diagnostics of `message`, a program sent to a chain of the network, in the order of its
+/// instructions.
+pub fn lint<Call>(message: &Xcm<Call>) -> Vec<Diagnostic> {
+	let message = Xcm::<()>::from(message.clone());
+	let mut diagnostics = Vec::new();
+	check_barrier(&message.0, &mut diagnostics);
+	check_program(&message.0, &[], Holding::Empty, Context::TopLevel, &mut diagnostics);
+	diagnostics
+}
+
+/// Where a program is executed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Context {
+	/// The program of a message.
+	TopLevel,
+	/// A program sent to another chain by an instruction.
+	Sent,
+	/// An error handler or appendix, executed with the error register set.
+	Handler,
+}
+
+/// What the holding register is known to contain.
+#[derive(Clone)]
+enum Holding {
+	Empty,
+	/// Exactly these assets, until some are taken out of it.
+	Known(MultiAssets),
+	/// Assets, or maybe nothing.
+	Unknown,
+}
+
+impl Holding {
+	fn load(&mut self, assets: &MultiAssets) {
+		match self {
+			Holding::Empty => *self = Holding::Known(assets.clone()),
+			Holding::Known(held) =>
+				assets.inner().iter().for_each(|asset| held.push(asset.clone())),
+			Holding::Unknown => (),
+		}
+	}
+}
+
+fn diagnose(
+	diagnostics: &mut Vec<Diagnostic>,
+	path: &[usize],
+	index: usize,
+	lint: Lint,
+	message: String,
+) {
+	let mut path = path.to_vec();
+	path.push(index);
+	diagnostics.push(Diagnostic { path, lint, message });
+}
+
+fn is_load(instruction: &Instruction<()>) -> bool {
+	matches!(
+		instruction,
+		WithdrawAsset(_) | ReserveAssetDeposited(_) | ReceiveTeleportedAsset(_) | ClaimAsset { .. }
+	)
+}
+
+/// Checks that a barrier lets the program of a message through.
+fn check_barrier(instructions: &[Instruction<()>], diagnostics: &mut Vec<Diagnostic>) {
+	// `WithComputedOrigin`
+	let start = instructions
+		.iter()
+		.position(|instruction| !matches!(instruction, UniversalOrigin(_) | DescendOrigin(_)))
+		.unwrap_or(instructions.len());
+	let first = match instructions.get(start) {
+		Some(first) => first,
+		None => {
+			let message = "an empty program is not let through".to_string();
+			return diagnose(diagnostics, &[], start, Lint::Barrier, message)
+		},
+	};
+	match first {
+		UnpaidExecution { .. } |
+		QueryResponse { .. } |
+		SubscribeVersion { .. } |
+		UnsubscribeVersion |
+		NoteUnlockable { .. } |
+		UnlockAsset { .. } => (),
+		first if is_load(first) => check_buy_execution(instructions, start + 1, &[], diagnostics),
+		BuyExecution { .. } => {
+			let message =
+				"`BuyExecution` must follow an instruction loading the holding register, \
+				like `WithdrawAsset`"
+					.to_string();
+			diagnose(diagnostics, &[], start, Lint::BuyExecutionNotAfterLoad, message)
+		},
+		_ => {
+			let message = "the program does not pay for its execution: it starts neither with an \
+				instruction loading the holding register nor with `UnpaidExecution`"
+				.to_string();
+			diagnose(diagnostics, &[], start, Lint::Barrier, message)
+		},
+	}
+}
+
+/// Checks that `BuyExecution` is the first instruction from `start` that is not `ClearOrigin`.
+fn check_buy_execution(
+	instructions: &[Instruction<()>],
+	start: usize,
+	path: &[usize],
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	let index = instructions[start.min(instructions.len())..]
+		.iter()
+		.position(|instruction| !matches!(instruction, ClearOrigin))
+		.map(|position| start + position);
+	match index.map(|index| (index, &instructions[index])) {
+		Some((_, BuyExecution { .. })) => (),
+		Some((index, _)) => {
+			let message = "`BuyExecution` must directly follow the instruction loading the \
+				holding register, optionally after `ClearOrigin`"
+				.to_string();
+			diagnose(diagnostics, path, index, Lint::BuyExecutionNotAfterLoad, message)
+		},
+		None => {
+			let message = "the program loads the holding register but never buys its execution";
+			let lint = Lint::BuyExecutionNotAfterLoad;
+			diagnose(diagnostics, path, instructions.len(), lint, message.to_string())
+		},
+	}
+}
+
+/// Follows the holding register through `instructions`, and checks the nested programs.
+fn check_program(
+	instructions: &[Instruction<()>],
+	path: &[usize],
+	mut holding: Holding,
+	context: Context,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	for (index, instruction) in instructions.iter().enumerate() {
+		let nested_path = [path, &[index]].concat();
+		match instruction {
+			WithdrawAsset(assets) |
+			ReserveAssetDeposited(assets) |
+			ReceiveTeleportedAsset(assets) |
+			ClaimAsset { assets, .. } => holding.load(assets),
+			BuyExecution { fees, .. } => check_fees(&holding, fees, path, index, diagnostics),
+			DepositAsset { assets, .. } |
+			DepositReserveAsset { assets, .. } |
+			InitiateReserveWithdraw { assets, .. } |
+			InitiateTeleport { assets, .. } => {
+				if let Holding::Empty = holding {
+					let message = "nothing is in the holding register to take assets from";
+					diagnose(diagnostics, path, index, Lint::EmptyHolding, message.to_string());
+				}
+				holding = match assets {
+					Wild(All) | Wild(AllCounted(_)) => Holding::Empty,
+					_ => Holding::Unknown,
+				};
+			},
+			BurnAsset(_) | ExchangeAsset { .. } => {
+				if let Holding::Empty = holding {
+					let message = "nothing is in the holding register to take assets from";
+					diagnose(diagnostics, path, index, Lint::EmptyHolding, message.to_string());
+				}
+				holding = Holding::Unknown;
+			},
+			// The surplus of the fees may be refunded to an empty holding register
+			RefundSurplus if matches!(holding, Holding::Empty) => holding = Holding::Unknown,
+			ExpectError(Some(_)) if context != Context::Handler => {
+				let message = "the error register is only set in error handlers and appendices";
+				let lint = Lint::ExpectErrorOutsideHandler;
+				diagnose(diagnostics, path, index, lint, message.to_string());
+			},
+			SetErrorHandler(xcm) | SetAppendix(xcm) => {
+				let (holding, context) = (Holding::Unknown, Context::Handler);
+				check_program(&xcm.0, &nested_path, holding, context, diagnostics)
+			},
+			_ => (),
+		}
+
+		let sent = match instruction {
+			DepositReserveAsset { xcm, .. } |
+			InitiateReserveWithdraw { xcm, .. } |
+			InitiateTeleport { xcm, .. } |
+			TransferReserveAsset { xcm, .. } => Some(xcm),
+			_ => None,
+		};
+		if let Some(xcm) = sent {
+			// The executor loads the holding register of the program it sends
+			check_buy_execution(&xcm.0, 0, &nested_path, diagnostics);
+			check_program(&xcm.0, &nested_path, Holding::Unknown, Context::Sent, diagnostics);
+		}
+	}
+}
+
+fn check_fees(
+	holding: &Holding,
+	fees: &MultiAsset,
+	path: &[usize],
+	index: usize,
+	diagnostics: &mut Vec<Diagnostic>,
+) {
+	let held = match holding {
+		Holding::Empty => {
+			let message = "nothing is in the holding register to pay the fees with";
+			return diagnose(diagnostics, path, index, Lint::EmptyHolding, message.to_string())
+		},
+		Holding::Known(held) => held,
+		Holding::Unknown => return,
+	};
+	match held.inner().iter().find(|asset| asset.id == fees.id) {
+		None => {
+			let message = format!("the fees, {:?}, are not in the holding register", fees.id);
+			diagnose(diagnostics, path, index, Lint::FeesNotInHolding, message)
+		},
+		Some(MultiAsset { fun: Fungible(amount), .. }) => match fees.fun {
+			Fungible(fee) if fee > *amount => {
+				let message =
+					format!("the fees, {fee}, are more than the holding register has, {amount}");
+				diagnose(diagnostics, path, index, Lint::FeesExceedHolding, message)
+			},
+			_ => (),
+		},
+		Some(_) => (),
+	}
+}
@@ -0,0 +1,127 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Locations and assets as seen from the chains of the networks.
+//!
+//! A location relative to one chain is reanchored to another one with `MultiLocation::reanchor`,
+//! from the `UniversalLocation` of both chains:
+//!
+//! ```ignore
+//! // The relay chain token, `Here` on the relay chain, is `Parent` on parachain A
+//! assert_eq!(asset_on(Chain::Para(1), Chain::Relay, (Here, 10)), Ok((Parent, 10).into()));
+//! ```
+//!
+//! The universal locations are read within the externalities of the chains, so the helpers
+//! taking a `Chain` must not be called within `execute_with`.
+
+use xcm::latest::prelude::*;
+use xcm_simulator::TestExt;
+
+use super::{
+	asset_hub, bridge_hub, evm_parachain, parachain, relay_chain, AssetHub, BridgeHub,
+	BridgedBridgeHub, BridgedParaA, BridgedRelay, Chain, EvmPara, ParaA, ParaB, ParaC, Relay,
+	ASSET_HUB_PARA_ID, BRIDGE_HUB_PARA_ID, EVM_PARA_ID,
+};
+
+/// The `UniversalLocation` of `chain` of `MockNet`.
+pub fn universal_location(chain: Chain) -> InteriorMultiLocation {
+	match chain {
+		Chain::Relay => Relay::execute_with(relay_chain::UniversalLocation::get),
+		Chain::Para(1) => ParaA::execute_with(parachain::UniversalLocation::get),
+		Chain::Para(2) => ParaB::execute_with(parachain::UniversalLocation::get),
+		Chain::Para(3) => ParaC::execute_with(parachain::UniversalLocation::get),
+		Chain::Para(ASSET_HUB_PARA_ID) => AssetHub::execute_with(asset_hub::UniversalLocation::get),
+		Chain::Para(BRIDGE_HUB_PARA_ID) =>
+			BridgeHub::execute_with(bridge_hub::UniversalLocation::get),
+		Chain::Para(EVM_PARA_ID) => EvmPara::execute_with(evm_parachain::UniversalLocation::get),
+		Chain::Para(para_id) => panic!("No parachain exists with para_id = {para_id}"),
+	}
+}
+
+/// The `UniversalLocation` of `chain` of `BridgedMockNet`.
+pub fn bridged_universal_location(chain: Chain) -> InteriorMultiLocation {
+	match chain {
+		Chain::Relay => BridgedRelay::execute_with(relay_chain::UniversalLocation::get),
+		Chain::Para(1) => BridgedParaA::execute_with(parachain::UniversalLocation::get),
+		Chain::Para(BRIDGE_HUB_PARA_ID) =>
+			BridgedBridgeHub::execute_with(bridge_hub::UniversalLocation::get),
+		Chain::Para(para_id) => panic!("No bridged parachain exists with para_id = {para_id}"),
+	}
+}
+
+/// `location`, relative to the chain whose universal location is `from`, relative to the chain
+/// whose universal location is `to`.
+pub fn reanchored(
+	location: impl Into<MultiLocation>,
+	from: InteriorMultiLocation,
+	to: InteriorMultiLocation,
+) -> Result<MultiLocation, ()> {
+	let mut location = location.into();
+	location.reanchor(&target(&from, to), from)?;
+	Ok(location)
+}
+
+/// `asset`, relative to the chain whose universal location is `from`, relative to the chain
+/// whose universal location is `to`.
+pub fn reanchored_asset(
+	asset: impl Into<MultiAsset>,
+	from: InteriorMultiLocation,
+	to: InteriorMultiLocation,
+) -> Result<MultiAsset, ()> {
+	let mut asset = asset.into();
+	asset.reanchor(&target(&from, to), from)?;
+	Ok(asset)
+}
+
+/// `assets`, relative to the chain whose universal location is `from`, relative to the chain
+/// whose universal location is `to`.
+pub fn reanchored_assets(
+	assets: impl Into<MultiAssets>,
+	from: InteriorMultiLocation,
+	to: InteriorMultiLocation,
+) -> Result<MultiAssets, ()> {
+	let mut assets = assets.into();
+	assets.reanchor(&target(&from, to), from)?;
+	Ok(assets)
+}
+
+/// `location`, relative to the chain `from` of `MockNet`, relative to `chain`.
+pub fn location_on(
+	chain: Chain,
+	from: Chain,
+	location: impl Into<MultiLocation>,
+) -> Result<MultiLocation, ()> {
+	if chain == from {
+		return Ok(location.into())
+	}
+	reanchored(location, universal_location(from), universal_location(chain))
+}
+
+/// `asset`, relative to the chain `from` of `MockNet`, relative to `chain`.
+pub fn asset_on(chain: Chain, from: Chain, asset: impl Into<MultiAsset>) -> Result<MultiAsset, ()> {
+	if chain == from {
+		return Ok(asset.into())
+	}
+	reanchored_asset(asset, universal_location(from), universal_location(chain))
+}
+
+/// The location of the chain with the universal location `to`, relative to the one with the
+/// universal location `from`.
+fn target(from: &InteriorMultiLocation, to: InteriorMultiLocation) -> MultiLocation {
+	let mut target = MultiLocation::new(from.len() as u8, to);
+	target.simplify(from);
+	target
+}
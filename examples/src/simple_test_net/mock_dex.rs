@@ -0,0 +1,191 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Constant-product exchange mock, the `AssetExchanger` of the parachain runtime mock.
+//!
+//! Every pool holds the reserves of a pair of concrete fungible assets and quotes prices with
+//! `x * y = k`, without a swap fee. The reserves are only accounted for in storage: the assets
+//! given to the exchange leave the holding register and the assets bought from it are minted
+//! into the holding register.
+
+use xcm::latest::prelude::*;
+use xcm_executor::{traits::AssetExchange, Assets as HoldingAssets};
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	#[pallet::storage]
+	/// The reserves of the pool of each pair of assets, keyed by the pair in ascending order.
+	pub(super) type Pools<T: Config> =
+		StorageMap<_, Blake2_128Concat, (MultiLocation, MultiLocation), (u128, u128), OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The reserves of the pool of `asset_a` and `asset_b` were set.
+		PoolSet { asset_a: MultiLocation, asset_b: MultiLocation, reserve_a: u128, reserve_b: u128 },
+		/// `amount_in` of `asset_in` was swapped for `amount_out` of `asset_out`.
+		Swapped {
+			origin: Option<MultiLocation>,
+			asset_in: MultiLocation,
+			amount_in: u128,
+			asset_out: MultiLocation,
+			amount_out: u128,
+		},
+		/// A swap of `asset_in` for `asset_out` was rejected, as the pool would have given less
+		/// than wanted or taken more than given.
+		SwapRejected {
+			origin: Option<MultiLocation>,
+			asset_in: MultiLocation,
+			asset_out: MultiLocation,
+		},
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Sets the reserves of the pool of `asset_a` and `asset_b`, creating it if needed.
+		pub fn set_pool(
+			asset_a: impl Into<MultiLocation>,
+			asset_b: impl Into<MultiLocation>,
+			reserve_a: u128,
+			reserve_b: u128,
+		) {
+			let (asset_a, asset_b) = (asset_a.into(), asset_b.into());
+			Self::put_reserves(asset_a, asset_b, (reserve_a, reserve_b));
+			Self::deposit_event(Event::PoolSet { asset_a, asset_b, reserve_a, reserve_b });
+		}
+
+		/// The reserves of `asset_a` and `asset_b` in their pool.
+		pub fn reserves(
+			asset_a: impl Into<MultiLocation>,
+			asset_b: impl Into<MultiLocation>,
+		) -> Option<(u128, u128)> {
+			let (asset_a, asset_b) = (asset_a.into(), asset_b.into());
+			if asset_a < asset_b {
+				Pools::<T>::get((asset_a, asset_b))
+			} else {
+				Pools::<T>::get((asset_b, asset_a))
+					.map(|(reserve_b, reserve_a)| (reserve_a, reserve_b))
+			}
+		}
+
+		/// The amount of `asset_out` the pool gives for `amount_in` of `asset_in`.
+		pub fn quote_exact_in(
+			asset_in: impl Into<MultiLocation>,
+			asset_out: impl Into<MultiLocation>,
+			amount_in: u128,
+		) -> Option<u128> {
+			let (reserve_in, reserve_out) = Self::reserves(asset_in, asset_out)?;
+			reserve_out
+				.checked_mul(amount_in)?
+				.checked_div(reserve_in.checked_add(amount_in)?)
+		}
+
+		/// The amount of `asset_in` the pool takes for `amount_out` of `asset_out`, `None` if the
+		/// pool does not hold more than `amount_out`.
+		pub fn quote_exact_out(
+			asset_in: impl Into<MultiLocation>,
+			asset_out: impl Into<MultiLocation>,
+			amount_out: u128,
+		) -> Option<u128> {
+			let (reserve_in, reserve_out) = Self::reserves(asset_in, asset_out)?;
+			let remaining =
+				reserve_out.checked_sub(amount_out).filter(|remaining| *remaining > 0)?;
+			// Rounded up, so that the product of the reserves never decreases.
+			let numerator = reserve_in.checked_mul(amount_out)?;
+			numerator.checked_add(remaining - 1).map(|numerator| numerator / remaining)
+		}
+
+		fn put_reserves(asset_a: MultiLocation, asset_b: MultiLocation, reserves: (u128, u128)) {
+			if asset_a < asset_b {
+				Pools::<T>::insert((asset_a, asset_b), reserves);
+			} else {
+				Pools::<T>::insert((asset_b, asset_a), (reserves.1, reserves.0));
+			}
+		}
+	}
+
+	/// Swaps a single fungible asset for another one, through their pool.
+	///
+	/// With `maximal`, all of `give` is swapped and `want` is the minimum to receive. Otherwise
+	/// exactly `want` is bought and the rest of `give` is returned. The swap is rejected, and
+	/// `give` returned, if it would receive less than `want` or need more than `give`.
+	impl<T: Config> AssetExchange for Pallet<T> {
+		fn exchange_asset(
+			origin: Option<&MultiLocation>,
+			give: HoldingAssets,
+			want: &MultiAssets,
+			maximal: bool,
+		) -> Result<HoldingAssets, HoldingAssets> {
+			let (asset_in, available) = match (give.fungible.iter().next(), give.len()) {
+				(Some((Concrete(asset), amount)), 1) => (*asset, *amount),
+				_ => return Err(give),
+			};
+			let (asset_out, wanted) = match want.inner() {
+				[MultiAsset { id: Concrete(asset), fun: Fungible(amount) }] => (*asset, *amount),
+				_ => return Err(give),
+			};
+			let (reserve_in, reserve_out) = match Self::reserves(asset_in, asset_out) {
+				Some(reserves) => reserves,
+				None => return Err(give),
+			};
+
+			let swap = if maximal {
+				Self::quote_exact_in(asset_in, asset_out, available)
+					.filter(|amount_out| *amount_out >= wanted)
+					.map(|amount_out| (available, amount_out))
+			} else {
+				Self::quote_exact_out(asset_in, asset_out, wanted)
+					.filter(|amount_in| *amount_in <= available)
+					.map(|amount_in| (amount_in, wanted))
+			};
+			let origin = origin.copied();
+			let (amount_in, amount_out) = match swap {
+				Some(swap) => swap,
+				None => {
+					Self::deposit_event(Event::SwapRejected { origin, asset_in, asset_out });
+					return Err(give)
+				},
+			};
+
+			let reserves = (reserve_in + amount_in, reserve_out - amount_out);
+			Self::put_reserves(asset_in, asset_out, reserves);
+			Self::deposit_event(Event::Swapped {
+				origin,
+				asset_in,
+				amount_in,
+				asset_out,
+				amount_out,
+			});
+
+			let mut received = HoldingAssets::from(MultiAsset::from((asset_out, amount_out)));
+			if available > amount_in {
+				received.subsume((asset_in, available - amount_in).into());
+			}
+			Ok(received)
+		}
+	}
+}
@@ -26,21 +26,79 @@ use polkadot_primitives::BlockNumber as RelayBlockNumber;
 use sp_runtime::traits::{Get, Hash};
 
 use sp_std::prelude::*;
-use xcm::{latest::prelude::*, VersionedXcm};
+use xcm::{latest::prelude::*, IdentifyVersion, VersionedXcm};
+
+/// Index of a message in the overweight queue.
+pub type OverweightIndex = u64;
+
+/// The sender of a message in the overweight queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode, scale_info::TypeInfo)]
+pub enum OverweightOrigin {
+    /// Downward message from the relay chain.
+    Parent,
+    /// Horizontal message from a sibling parachain.
+    Sibling(ParaId),
+}
 
 #[frame_support::pallet]
 pub mod pallet {
     use super::*;
     use frame_support::pallet_prelude::*;
+    use frame_system::pallet_prelude::*;
 
     #[pallet::config]
     pub trait Config: frame_system::Config {
         type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
         type XcmExecutor: ExecuteXcm<Self::RuntimeCall>;
+        /// Origin allowed to execute messages of the overweight queue.
+        type ExecuteOverweightOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+        /// The latest XCM version the chain understands. Messages of later versions are
+        /// rejected.
+        type AdvertisedXcmVersion: Get<XcmVersion>;
     }
 
     #[pallet::call]
-    impl<T: Config> Pallet<T> {}
+    impl<T: Config> Pallet<T> {
+        /// Executes the message `index` of the overweight queue with at most `weight_limit`.
+        ///
+        /// The message is removed from the queue unless `weight_limit` is too low to execute it.
+        #[pallet::call_index(0)]
+        #[pallet::weight(weight_limit.saturating_add(T::DbWeight::get().reads_writes(1, 1)))]
+        pub fn service_overweight(
+            origin: OriginFor<T>,
+            index: OverweightIndex,
+            weight_limit: Weight,
+        ) -> DispatchResultWithPostInfo {
+            T::ExecuteOverweightOrigin::ensure_origin(origin)?;
+
+            let (sender, sent_at, data) =
+                Overweight::<T>::get(index).ok_or(Error::<T>::UnknownOverweightIndex)?;
+            let xcm = VersionedXcm::<T::RuntimeCall>::decode(&mut &data[..])
+                .map_err(|_| Error::<T>::BadXcm)?;
+
+            // Executed like when it was received, so it is recorded and reported the same way.
+            let result = match sender {
+                OverweightOrigin::Parent => {
+                    let id = sp_io::hashing::blake2_256(&data[..]);
+                    let xcm = Xcm::try_from(xcm).map_err(|()| Error::<T>::BadXcm)?;
+                    Self::handle_dmp_message(id, xcm, weight_limit)
+                }
+                OverweightOrigin::Sibling(para_id) =>
+                    Self::handle_xcmp_message(para_id, sent_at, xcm, weight_limit),
+            };
+            let used = match result {
+                Ok(used) => used,
+                Err(XcmError::WeightLimitReached(_)) =>
+                    return Err(Error::<T>::WeightOverLimit.into()),
+                // The execution failed before using any weight.
+                Err(_) => Weight::zero(),
+            };
+
+            Overweight::<T>::remove(index);
+            Self::deposit_event(Event::OverweightServiced(index, used));
+            Ok(Some(used.saturating_add(T::DbWeight::get().reads_writes(1, 1))).into())
+        }
+    }
 
     #[pallet::pallet]
     #[pallet::without_storage_info]
@@ -55,6 +113,89 @@ pub mod pallet {
     /// A queue of received DMP messages
     pub(super) type ReceivedDmp<T: Config> = StorageValue<_, Vec<Xcm<T::RuntimeCall>>, ValueQuery>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn received_xcmp)]
+    /// The received XCMP messages of each sibling, with the relay block they were sent at and the
+    /// outcome of their execution
+    pub(super) type ReceivedXcmp<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        ParaId,
+        Vec<(Xcm<T::RuntimeCall>, RelayBlockNumber, Outcome)>,
+        ValueQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn deferred_dmp)]
+    /// Downward messages that did not fit in the remaining weight of the block they were received
+    /// in, with the relay block they were sent at. They are executed before the messages of the
+    /// next block.
+    pub(super) type DeferredDmp<T: Config> =
+        StorageValue<_, Vec<(RelayBlockNumber, Vec<u8>)>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn deferred_xcmp)]
+    /// XCMP messages that did not fit in the remaining weight of the block they were received in,
+    /// with their sender and the relay block they were sent at, see `DeferredDmp`.
+    pub(super) type DeferredXcmp<T: Config> =
+        StorageValue<_, Vec<(ParaId, RelayBlockNumber, Vec<u8>)>, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn overweight)]
+    /// Messages that need more weight than a block gives to all messages, with their sender and
+    /// the relay block they were sent at. They are only executed by `service_overweight`.
+    pub(super) type Overweight<T: Config> = StorageMap<
+        _,
+        Twox64Concat,
+        OverweightIndex,
+        (OverweightOrigin, RelayBlockNumber, Vec<u8>),
+        OptionQuery,
+    >;
+
+    #[pallet::storage]
+    #[pallet::getter(fn overweight_count)]
+    /// The number of messages ever enqueued in `Overweight`, used as the index of the next one.
+    pub(super) type OverweightCount<T: Config> = StorageValue<_, OverweightIndex, ValueQuery>;
+
+    #[pallet::type_value]
+    pub fn UnlimitedBudget() -> Weight {
+        Weight::MAX
+    }
+
+    #[pallet::storage]
+    #[pallet::getter(fn block_weight_budget)]
+    /// The weight a block gives to all messages, whatever weight the caller of the handlers
+    /// allows. Unlimited unless set with `set_block_weight_budget`.
+    pub(super) type BlockWeightBudget<T: Config> =
+        StorageValue<_, Weight, ValueQuery, UnlimitedBudget>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn block_weight_used)]
+    /// The weight the messages used in the current block, out of `BlockWeightBudget`.
+    pub(super) type BlockWeightUsed<T: Config> = StorageValue<_, Weight, ValueQuery>;
+
+    #[pallet::hooks]
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// Executes the deferred messages with the weight the messages received at the start of
+        /// the block left.
+        fn on_initialize(_: BlockNumberFor<T>) -> Weight {
+            let mut weight_used = Weight::zero();
+            if !DeferredDmp::<T>::get().is_empty() {
+                let used = Self::handle_dmp_messages(core::iter::empty(), Weight::MAX);
+                weight_used.saturating_accrue(used);
+            }
+            if !DeferredXcmp::<T>::get().is_empty() {
+                let used = Self::handle_xcmp_messages(core::iter::empty(), Weight::MAX);
+                weight_used.saturating_accrue(used);
+            }
+            weight_used
+        }
+
+        fn on_finalize(_: BlockNumberFor<T>) {
+            BlockWeightUsed::<T>::kill();
+        }
+    }
+
     impl<T: Config> Get<ParaId> for Pallet<T> {
         fn get() -> ParaId {
             Self::parachain_id()
@@ -83,6 +224,24 @@ pub mod pallet {
         UnsupportedVersion(MessageId),
         /// Downward message executed with the given outcome.
         ExecutedDownward(MessageId, Outcome),
+
+        // Overweight queue
+        /// A message needs more weight than a block gives to all messages and was put in the
+        /// overweight queue with the given index. It requires the given weight to be executed.
+        OverweightEnqueued(OverweightOrigin, RelayBlockNumber, OverweightIndex, Weight),
+        /// The message of the overweight queue with the given index was executed, using the given
+        /// weight.
+        OverweightServiced(OverweightIndex, Weight),
+    }
+
+    #[pallet::error]
+    pub enum Error<T> {
+        /// No message in the overweight queue has the given index.
+        UnknownOverweightIndex,
+        /// The message of the overweight queue is not a supported XCM.
+        BadXcm,
+        /// The weight limit is too low to execute the message.
+        WeightOverLimit,
     }
 
     impl<T: Config> Pallet<T> {
@@ -90,18 +249,79 @@ pub mod pallet {
             ParachainId::<T>::put(para_id);
         }
 
+        /// Sets the weight a block gives to all messages, see `BlockWeightBudget`.
+        pub fn set_block_weight_budget(budget: Weight) {
+            BlockWeightBudget::<T>::put(budget);
+        }
+
+        /// The weight a block gives to all messages when the handlers are given `max_weight`.
+        fn block_limit(max_weight: Weight) -> Weight {
+            max_weight.min(Self::block_weight_budget())
+        }
+
+        fn enqueue_overweight(
+            sender: OverweightOrigin,
+            sent_at: RelayBlockNumber,
+            data: Vec<u8>,
+            required: Weight,
+        ) {
+            let index = OverweightCount::<T>::mutate(|count| {
+                let index = *count;
+                *count += 1;
+                index
+            });
+            Overweight::<T>::insert(index, (sender, sent_at, data));
+            Self::deposit_event(Event::OverweightEnqueued(sender, sent_at, index, required));
+        }
+
+        /// Executes the downward message with at most `max_weight`. Returns
+        /// `Err(XcmError::WeightLimitReached(required))` without depositing an event if that is
+        /// not enough.
+        fn handle_dmp_message(
+            id: MessageId,
+            xcm: Xcm<T::RuntimeCall>,
+            max_weight: Weight,
+        ) -> Result<Weight, XcmError> {
+            let outcome = T::XcmExecutor::execute_xcm(Parent, xcm.clone(), id, max_weight);
+            if let Outcome::Error(XcmError::WeightLimitReached(required)) = outcome {
+                return Err(XcmError::WeightLimitReached(required))
+            }
+            let used = outcome.weight_used();
+            <ReceivedDmp<T>>::append(xcm);
+            Self::deposit_event(Event::ExecutedDownward(id, outcome));
+            Ok(used)
+        }
+
+        /// Executes the message with at most `max_weight`. Returns
+        /// `Err(XcmError::WeightLimitReached(required))` without depositing an event if that is
+        /// not enough.
         fn handle_xcmp_message(
             sender: ParaId,
-            _sent_at: RelayBlockNumber,
+            sent_at: RelayBlockNumber,
             xcm: VersionedXcm<T::RuntimeCall>,
             max_weight: Weight,
         ) -> Result<Weight, XcmError> {
             let hash = Encode::using_encoded(&xcm, T::Hashing::hash);
             let message_hash = Encode::using_encoded(&xcm, sp_io::hashing::blake2_256);
-            let (result, event) = match Xcm::<T::RuntimeCall>::try_from(xcm) {
+            let converted = if xcm.identify_version() <= T::AdvertisedXcmVersion::get() {
+                Xcm::<T::RuntimeCall>::try_from(xcm)
+            } else {
+                Err(())
+            };
+            let (result, event) = match converted {
                 Ok(xcm) => {
                     let location = (Parent, Parachain(sender.into()));
-                    match T::XcmExecutor::execute_xcm(location, xcm, message_hash, max_weight) {
+                    let outcome = T::XcmExecutor::execute_xcm(
+                        location,
+                        xcm.clone(),
+                        message_hash,
+                        max_weight,
+                    );
+                    if let Outcome::Error(XcmError::WeightLimitReached(required)) = outcome {
+                        return Err(XcmError::WeightLimitReached(required))
+                    }
+                    <ReceivedXcmp<T>>::append(sender, (xcm, sent_at, outcome.clone()));
+                    match outcome {
                         Outcome::Error(e) => (Err(e.clone()), Event::Fail(Some(hash), e)),
                         Outcome::Complete(w) => (Ok(w), Event::Success(Some(hash))),
                         // As far as the caller is concerned, this was dispatched without error, so
@@ -124,6 +344,40 @@ pub mod pallet {
             iter: I,
             max_weight: Weight,
         ) -> Weight {
+            let deferred = DeferredXcmp::<T>::take();
+            let limit = Self::block_limit(max_weight);
+            let mut weight_used = Weight::zero();
+            // Set once a message does not fit in the remaining weight. All later messages are
+            // deferred too, so that they are not executed out of order.
+            let mut deferring = false;
+            let mut handle = |sender: ParaId, sent_at, xcm: VersionedXcm<T::RuntimeCall>| {
+                let data = xcm.encode();
+                if deferring {
+                    DeferredXcmp::<T>::append((sender, sent_at, data));
+                    return
+                }
+                let remaining =
+                    limit.saturating_sub(Self::block_weight_used()).saturating_sub(weight_used);
+                match Self::handle_xcmp_message(sender, sent_at, xcm, remaining) {
+                    Ok(used) => weight_used.saturating_accrue(used),
+                    // Heavier than a whole block, it would never fit.
+                    Err(XcmError::WeightLimitReached(required)) if required.any_gt(limit) => {
+                        let origin = OverweightOrigin::Sibling(sender);
+                        Self::enqueue_overweight(origin, sent_at, data, required);
+                    }
+                    Err(XcmError::WeightLimitReached(_)) => {
+                        deferring = true;
+                        DeferredXcmp::<T>::append((sender, sent_at, data));
+                    }
+                    Err(_) => {}
+                }
+            };
+
+            for (sender, sent_at, data) in deferred {
+                if let Ok(xcm) = VersionedXcm::<T::RuntimeCall>::decode(&mut &data[..]) {
+                    handle(sender, sent_at, xcm);
+                }
+            }
             for (sender, sent_at, data) in iter {
                 let mut data_ref = data;
                 let _ = XcmpMessageFormat::decode(&mut data_ref)
@@ -134,13 +388,17 @@ pub mod pallet {
                     if let Ok(xcm) =
                         VersionedXcm::<T::RuntimeCall>::decode(&mut remaining_fragments)
                     {
-                        let _ = Self::handle_xcmp_message(sender, sent_at, xcm, max_weight);
+                        handle(sender, sent_at, xcm);
                     } else {
-                        debug_assert!(false, "Invalid incoming XCMP message data");
+                        // The rest of the blob cannot be split into messages.
+                        let hash = T::Hashing::hash(remaining_fragments);
+                        Self::deposit_event(Event::BadFormat(Some(hash)));
+                        break
                     }
                 }
             }
-            max_weight
+            BlockWeightUsed::<T>::mutate(|used| used.saturating_accrue(weight_used));
+            weight_used
         }
     }
 
@@ -149,24 +407,52 @@ pub mod pallet {
             iter: impl Iterator<Item = (RelayBlockNumber, Vec<u8>)>,
             limit: Weight,
         ) -> Weight {
-            for (_i, (_sent_at, data)) in iter.enumerate() {
+            let deferred = DeferredDmp::<T>::take();
+            let limit = Self::block_limit(limit);
+            let mut weight_used = Weight::zero();
+            // See `handle_xcmp_messages`.
+            let mut deferring = false;
+            for (sent_at, data) in deferred.into_iter().chain(iter) {
+                if deferring {
+                    DeferredDmp::<T>::append((sent_at, data));
+                    continue
+                }
                 let id = sp_io::hashing::blake2_256(&data[..]);
                 let maybe_versioned = VersionedXcm::<T::RuntimeCall>::decode(&mut &data[..]);
                 match maybe_versioned {
                     Err(_) => {
                         Self::deposit_event(Event::InvalidFormat(id));
                     }
+                    Ok(versioned)
+                        if versioned.identify_version() > T::AdvertisedXcmVersion::get() =>
+                    {
+                        Self::deposit_event(Event::UnsupportedVersion(id));
+                    }
                     Ok(versioned) => match Xcm::try_from(versioned) {
                         Err(()) => Self::deposit_event(Event::UnsupportedVersion(id)),
                         Ok(x) => {
-                            let outcome = T::XcmExecutor::execute_xcm(Parent, x.clone(), id, limit);
-                            <ReceivedDmp<T>>::append(x);
-                            Self::deposit_event(Event::ExecutedDownward(id, outcome));
+                            let remaining = limit
+                                .saturating_sub(Self::block_weight_used())
+                                .saturating_sub(weight_used);
+                            match Self::handle_dmp_message(id, x, remaining) {
+                                Ok(used) => weight_used.saturating_accrue(used),
+                                Err(XcmError::WeightLimitReached(required))
+                                    if required.any_gt(limit) =>
+                                {
+                                    let origin = OverweightOrigin::Parent;
+                                    Self::enqueue_overweight(origin, sent_at, data, required);
+                                }
+                                Err(_) => {
+                                    deferring = true;
+                                    DeferredDmp::<T>::append((sent_at, data));
+                                }
+                            }
                         }
                     },
                 }
             }
-            limit
+            BlockWeightUsed::<T>::mutate(|used| used.saturating_accrue(weight_used));
+            weight_used
         }
     }
 }
@@ -0,0 +1,78 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Callback of the queries registered with `pallet_xcm::Pallet::new_notify_query`.
+//!
+//! `pallet_xcm` dispatches `notify` with the query id and the response appended to the call
+//! registered with the query, from the `pallet_xcm::Origin::Response` origin of the responder.
+
+use xcm::latest::prelude::*;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+		/// Origin of the responses to the queries, resolving to the responder.
+		type ResponseOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = MultiLocation>;
+	}
+
+	#[pallet::pallet]
+	#[pallet::without_storage_info]
+	pub struct Pallet<T>(_);
+
+	#[pallet::storage]
+	#[pallet::getter(fn notified)]
+	/// The response notified for each query, with its responder.
+	pub(super) type Notified<T: Config> =
+		StorageMap<_, Twox64Concat, QueryId, (MultiLocation, Response), OptionQuery>;
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// `responder` responded `response` to the query `query_id`.
+		ResponseNotified { query_id: QueryId, responder: MultiLocation, response: Response },
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Records the response to the query `query_id`.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::DbWeight::get().writes(1))]
+		pub fn notify(
+			origin: OriginFor<T>,
+			query_id: QueryId,
+			response: Response,
+		) -> DispatchResult {
+			let responder = T::ResponseOrigin::ensure_origin(origin)?;
+			Notified::<T>::insert(query_id, (responder, response.clone()));
+			Self::deposit_event(Event::ResponseNotified { query_id, responder, response });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The call to register with `new_notify_query`. `pallet_xcm` replaces its arguments
+		/// with the query id and the response.
+		pub fn notify_call() -> Call<T> {
+			Call::notify { query_id: 0, response: Response::Null }
+		}
+	}
+}
@@ -14,27 +14,93 @@
 // You should have received a copy of the GNU General Public License
 // along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
 #![allow(dead_code)]
+pub mod accounts;
+pub mod asset_hub;
+pub mod bridge;
+pub mod bridge_hub;
+pub mod bridged_net;
+pub mod clock;
+pub mod dry_run;
+pub mod events;
+pub mod evm_parachain;
+pub mod faults;
+pub mod fees;
+pub mod genesis;
+pub mod hrmp;
+pub mod inspect;
+pub mod lint;
+pub mod locations;
+pub mod mock_dex;
 pub mod mock_msg_queue;
+pub mod mock_notifier;
 pub mod parachain;
+pub mod program;
 pub mod relay_chain;
+pub mod scenario;
+pub mod sovereign;
+pub mod topology;
+pub mod trace;
+pub mod trader;
+pub mod versions;
 
-use core::{borrow::Borrow, marker::PhantomData};
+use core::{borrow::Borrow, cell::RefCell, marker::PhantomData};
 
-use frame_support::{
-	ensure,
-	pallet_prelude::Weight,
-	sp_tracing,
-	traits::{GenesisBuild, ProcessMessageError},
-};
+use codec::Decode;
+use frame_support::{ensure, pallet_prelude::Weight, traits::ProcessMessageError};
 use sp_core::blake2_256;
+use sp_runtime::traits::TrailingZeroInput;
 use xcm::prelude::*;
 use xcm_executor::traits::{Convert, ShouldExecute};
 use xcm_simulator::{decl_test_network, decl_test_parachain, decl_test_relay_chain, TestExt};
 
+pub use accounts::{
+	derived_accounts, describe_account, describe_location, location_of, RecordDerivations,
+};
+pub use bridge::{
+	clear_bridge_messages, pending_bridge_messages, relay_bridge_messages, ThisNetwork,
+};
+pub use bridged_net::{
+	bridged_mock_net, default_bridged_mock_net, BridgedBridgeHub, BridgedMockNet, BridgedParaA,
+	BridgedRelay,
+};
+pub use clock::{relay_block_number, scheduled_messages, BlockStepping, StepRouter};
+#[cfg(feature = "dry-run")]
+pub use dry_run::dry_run;
+pub use dry_run::{DryRun, DryRunRouter, Program, Registers, Step};
+pub(crate) use events::{assert_para_event, assert_relay_event};
+pub use events::{
+	event_mark, events_since, expect_relay_processed, EventMark, MockChain, RuntimeEventOf,
+};
+pub use faults::{
+	clear_faults, corrupt_upward_message, held_messages, inject_fault, inject_fault_on, Corruption,
+	Fault, FaultRouter, FaultyHandler,
+};
+pub use fees::{estimate_fees, estimate_total_fee, HopFee};
+pub use genesis::{AssetClass, GenesisSpec, Sovereign, UniquesCollection};
+pub use hrmp::{bridged_hrmp_channel_exists, hrmp_channel_exists, HrmpChannelGuard};
+pub use lint::{lint, Diagnostic, Lint};
+pub use locations::{
+	asset_on, bridged_universal_location, location_on, reanchored, reanchored_asset,
+	reanchored_assets, universal_location,
+};
+pub use program::{InnerBuilder, XcmBuilder};
+pub use scenario::{run_scenario, Scenario, ScenarioError};
+pub use sovereign::{
+	sovereign_account_on, sovereign_account_seen_from, sovereign_account_with, AnyAccountId,
+	NamedConverters, SovereignAccount, SovereignAccountError,
+};
+pub use topology::{Chain, ParaKind, TestNetBuilder};
+pub use trace::{
+	clear_trace, trace, AsParachain, AsRelay, Hop, HopKind, Trace, TraceExecutor, TraceRouter,
+};
+pub use versions::{supported_version, AdvertisedXcmVersion, VersionedRouter};
+
 // Accounts
 pub const ADMIN: sp_runtime::AccountId32 = sp_runtime::AccountId32::new([0u8; 32]);
 pub const ALICE: sp_runtime::AccountId32 = sp_runtime::AccountId32::new([1u8; 32]);
 pub const BOB: sp_runtime::AccountId32 = sp_runtime::AccountId32::new([2u8; 32]);
+/// Account of the EVM parachain.
+pub const ALITH: sp_core::H160 = sp_core::H160([1u8; 20]);
 
 // Balances
 pub type Balance = u128;
@@ -42,11 +108,20 @@ pub const UNITS: Balance = 10_000_000_000;
 pub const CENTS: Balance = UNITS / 100; // 100_000_000
 pub const INITIAL_BALANCE: u128 = 10 * UNITS;
 
+// Networks
+pub const RELAY_NETWORK: NetworkId = ByGenesis([0; 32]);
+pub const BRIDGED_RELAY_NETWORK: NetworkId = ByGenesis([1; 32]);
+
+// Para ids
+pub const ASSET_HUB_PARA_ID: u32 = 1000;
+pub const BRIDGE_HUB_PARA_ID: u32 = 1002;
+pub const EVM_PARA_ID: u32 = 2004;
+
 decl_test_parachain! {
 	pub struct ParaA {
 		Runtime = parachain::Runtime,
-		XcmpMessageHandler = parachain::MsgQueue,
-		DmpMessageHandler = parachain::MsgQueue,
+		XcmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
 		new_ext = para_ext(1),
 	}
 }
@@ -54,8 +129,8 @@ decl_test_parachain! {
 decl_test_parachain! {
 	pub struct ParaB {
 		Runtime = parachain::Runtime,
-		XcmpMessageHandler = parachain::MsgQueue,
-		DmpMessageHandler = parachain::MsgQueue,
+		XcmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
 		new_ext = para_ext(2),
 	}
 }
@@ -63,9 +138,36 @@ decl_test_parachain! {
 decl_test_parachain! {
 	pub struct ParaC {
 		Runtime = parachain::Runtime,
-		XcmpMessageHandler = parachain::MsgQueue,
-		DmpMessageHandler = parachain::MsgQueue,
-		new_ext = para_ext(2),
+		XcmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<parachain::MsgQueue>,
+		new_ext = para_ext(3),
+	}
+}
+
+decl_test_parachain! {
+	pub struct AssetHub {
+		Runtime = asset_hub::Runtime,
+		XcmpMessageHandler = FaultyHandler<asset_hub::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<asset_hub::MsgQueue>,
+		new_ext = asset_hub_ext(),
+	}
+}
+
+decl_test_parachain! {
+	pub struct BridgeHub {
+		Runtime = bridge_hub::Runtime,
+		XcmpMessageHandler = FaultyHandler<bridge_hub::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<bridge_hub::MsgQueue>,
+		new_ext = para_ext(BRIDGE_HUB_PARA_ID),
+	}
+}
+
+decl_test_parachain! {
+	pub struct EvmPara {
+		Runtime = evm_parachain::Runtime,
+		XcmpMessageHandler = FaultyHandler<evm_parachain::MsgQueue>,
+		DmpMessageHandler = FaultyHandler<evm_parachain::MsgQueue>,
+		new_ext = para_ext(EVM_PARA_ID),
 	}
 }
 
@@ -88,18 +190,53 @@ decl_test_network! {
 			(1, ParaA),
 			(2, ParaB),
 			(3, ParaC),
+			(ASSET_HUB_PARA_ID, AssetHub),
+			(BRIDGE_HUB_PARA_ID, BridgeHub),
+			(EVM_PARA_ID, EvmPara),
 		],
 	}
 }
 
+/// Router of the parachain `ParachainId` of the mock networks: it wraps messages with `Wrapper`,
+/// only sends them over open HRMP channels, captures them during dry runs, traces them, injects
+/// faults and schedules them when the network is stepped block by block.
+pub type ParachainRouter<ParachainId, Wrapper> = VersionedRouter<
+	HrmpChannelGuard<
+		DryRunRouter<
+			TraceRouter<
+				FaultRouter<
+					StepRouter<ParachainXcmRouter<ParachainId>, AsParachain<ParachainId>>,
+					AsParachain<ParachainId>,
+				>,
+				AsParachain<ParachainId>,
+			>,
+		>,
+		ParachainId,
+	>,
+	Wrapper,
+	AsParachain<ParachainId>,
+>;
+
+/// Router of the relay chain of the mock networks, see `ParachainRouter`.
+pub type RelayRouter<Wrapper> = VersionedRouter<
+	DryRunRouter<
+		TraceRouter<FaultRouter<StepRouter<RelayChainXcmRouter, AsRelay>, AsRelay>, AsRelay>,
+	>,
+	Wrapper,
+	AsRelay,
+>;
+
+// Sovereign accounts of common locations, derived like `sovereign_account_on` does for any other
+// location.
+
 pub fn relay_sovereign_account_id() -> parachain::AccountId {
 	let location = (Parent,);
-	parachain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
 }
 
 pub fn parachain_sovereign_account_id(para: u32) -> relay_chain::AccountId {
 	let location = (Parachain(para),);
-	relay_chain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<relay_chain::SovereignAccountOf, _>(location)
 }
 
 pub fn parachain_account_sovereign_account_id(
@@ -110,12 +247,12 @@ pub fn parachain_account_sovereign_account_id(
 		Parachain(para),
 		AccountId32 { network: Some(relay_chain::RelayNetwork::get()), id: who.into() },
 	);
-	relay_chain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<relay_chain::SovereignAccountOf, _>(location)
 }
 
 pub fn sibling_sovereign_account_id(para: u32) -> parachain::AccountId {
 	let location = (Parent, Parachain(para));
-	parachain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
 }
 
 pub fn sibling_account_sovereign_account_id(
@@ -123,105 +260,75 @@ pub fn sibling_account_sovereign_account_id(
 	who: sp_runtime::AccountId32,
 ) -> parachain::AccountId {
 	let location = (Parent, Parachain(para), AccountId32 { network: None, id: who.into() });
-	parachain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
+}
+
+/// Sovereign account, on a parachain, of the account `key` of the EVM parachain.
+pub fn sibling_account_key20_sovereign_account_id(key: sp_core::H160) -> parachain::AccountId {
+	let location =
+		(Parent, Parachain(EVM_PARA_ID), AccountKey20 { network: None, key: key.into() });
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
 }
 
 pub fn relay_account_sovereign_account_id(who: sp_runtime::AccountId32) -> parachain::AccountId {
 	let location = (Parent, AccountId32 { network: None, id: who.into() });
-	parachain::SovereignAccountOf::convert(location.into()).unwrap()
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
 }
 
-pub fn para_ext(para_id: u32) -> sp_io::TestExternalities {
-	use parachain::{MsgQueue, Runtime, System};
-
-	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
-	let other_para_ids = match para_id {
-		1 => [2, 3],
-		2 => [1, 3],
-		3 => [1, 2],
-		_ => panic!("No parachain exists with para_id = {para_id}"),
-	};
-
-	pallet_balances::GenesisConfig::<Runtime> {
-		balances: vec![
-			(ALICE, INITIAL_BALANCE),
-			(relay_sovereign_account_id(), INITIAL_BALANCE),
-			(BOB, INITIAL_BALANCE),
-		]
-		.into_iter()
-		.chain(other_para_ids.iter().map(
-			// Initial balance of native token for ALICE on all sibling sovereign accounts
-			|&para_id| (sibling_account_sovereign_account_id(para_id, ALICE), INITIAL_BALANCE),
-		))
-		.chain(other_para_ids.iter().map(
-			// Initial balance of native token all sibling sovereign accounts
-			|&para_id| (sibling_sovereign_account_id(para_id), INITIAL_BALANCE),
-		))
-		.collect(),
-	}
-	.assimilate_storage(&mut t)
-	.unwrap();
-
-	pallet_assets::GenesisConfig::<Runtime> {
-		assets: vec![
-			(0u128, ADMIN, false, 1u128), // Create derivative asset for relay's native token
-		]
-		.into_iter()
-		.chain(other_para_ids.iter().map(|&para_id| (para_id as u128, ADMIN, false, 1u128))) // Derivative assets for the other parachains' native tokens
-		.collect(),
-		metadata: Default::default(),
-		accounts: vec![
-			(0u128, ALICE, INITIAL_BALANCE),
-			(0u128, relay_sovereign_account_id(), INITIAL_BALANCE),
-		]
-		.into_iter()
-		.chain(other_para_ids.iter().map(|&para_id| (para_id as u128, ALICE, INITIAL_BALANCE))) // Initial balance for derivatives of other parachains' tokens
-		.chain(other_para_ids.iter().map(|&para_id| {
-			(0u128, sibling_account_sovereign_account_id(para_id, ALICE), INITIAL_BALANCE)
-		})) // Initial balance for sovereign accounts (for fee payment)
-		.chain(
-			other_para_ids
-				.iter()
-				.map(|&para_id| (0u128, sibling_sovereign_account_id(para_id), INITIAL_BALANCE)),
-		) // Initial balance for sovereign accounts (for fee payment)
-		.collect(),
+/// Sovereign account, on a parachain, of the parachain `para` of the consensus system `network`.
+pub fn remote_parachain_sovereign_account_id(
+	network: NetworkId,
+	para: u32,
+) -> parachain::AccountId {
+	let location = MultiLocation::new(2, X2(GlobalConsensus(network), Parachain(para)));
+	sovereign_account_with::<parachain::SovereignAccountOf, _>(location)
+}
+
+thread_local! {
+	/// Overrides the network `MockNet` is reset to while `MockNet::reset_with` resets it.
+	static MOCK_NET: RefCell<Option<TestNetBuilder>> = RefCell::new(None);
+}
+
+/// The network `MockNet` is reset to by default.
+pub fn default_mock_net() -> TestNetBuilder {
+	TestNetBuilder::new()
+		.relay(GenesisSpec::relay_default())
+		.parachain(1, ParaKind::Parachain)
+		.parachain(2, ParaKind::Parachain)
+		.parachain(3, ParaKind::Parachain)
+		.parachain(ASSET_HUB_PARA_ID, ParaKind::AssetHub)
+		.parachain(BRIDGE_HUB_PARA_ID, ParaKind::BridgeHub)
+		.parachain(EVM_PARA_ID, ParaKind::Evm)
+}
+
+/// The network `MockNet` is being reset to, `default_mock_net()` outside of
+/// `MockNet::reset_with`.
+pub fn mock_net() -> TestNetBuilder {
+	MOCK_NET.with(|net| net.borrow().clone()).unwrap_or_else(default_mock_net)
+}
+
+impl MockNet {
+	/// Resets the network with the genesis described by `net`.
+	///
+	/// The para ids of `net` must be the ones `MockNet` is declared with. The override only
+	/// applies to this reset: `MockNet::reset` resets the network to `default_mock_net()`.
+	pub fn reset_with(net: TestNetBuilder) {
+		MOCK_NET.with(|mock_net| *mock_net.borrow_mut() = Some(net));
+		Self::reset();
+		MOCK_NET.with(|mock_net| *mock_net.borrow_mut() = None);
 	}
-	.assimilate_storage(&mut t)
-	.unwrap();
-
-	let mut ext = sp_io::TestExternalities::new(t);
-	ext.execute_with(|| {
-		sp_tracing::try_init_simple();
-		System::set_block_number(1);
-		MsgQueue::set_para_id(para_id.into());
-	});
-	ext
+}
+
+pub fn para_ext(para_id: u32) -> sp_io::TestExternalities {
+	mock_net().para_ext(para_id)
+}
+
+pub fn asset_hub_ext() -> sp_io::TestExternalities {
+	mock_net().para_ext(ASSET_HUB_PARA_ID)
 }
 
 pub fn relay_ext() -> sp_io::TestExternalities {
-	use relay_chain::{Runtime, System};
-
-	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
-
-	pallet_balances::GenesisConfig::<Runtime> {
-		balances: vec![
-			(ALICE, INITIAL_BALANCE),
-			(parachain_sovereign_account_id(1), INITIAL_BALANCE),
-			(parachain_sovereign_account_id(2), INITIAL_BALANCE),
-			(parachain_sovereign_account_id(3), INITIAL_BALANCE),
-			(parachain_account_sovereign_account_id(1, ALICE), INITIAL_BALANCE),
-			(parachain_account_sovereign_account_id(2, ALICE), INITIAL_BALANCE),
-			(parachain_account_sovereign_account_id(3, ALICE), INITIAL_BALANCE),
-		],
-	}
-	.assimilate_storage(&mut t)
-	.unwrap();
-
-	let mut ext = sp_io::TestExternalities::new(t);
-	ext.execute_with(|| {
-		System::set_block_number(1);
-	});
-	ext
+	mock_net().relay_ext()
 }
 
 pub fn print_para_events() {
@@ -234,14 +341,13 @@ pub fn print_relay_events() {
 	System::events().iter().for_each(|r| println!(">>> {:?}", r.event));
 }
 
-pub fn relay_successful_execution() -> bool {
-	use relay_chain::System;
-	System::events().iter().any(|e| match &e.event {
-		relay_chain::RuntimeEvent::MessageQueue(
-			pallet_message_queue::Event::Processed{id: _, origin: _, weight_used: _, success: true},
-		) => true,
-		_ => false,
-	})
+/// The response to the query `query_id` of `pallet_xcm` of the chain whose externalities are
+/// set, `None` if the query is unknown or still pending.
+pub fn query_response<T: pallet_xcm::Config>(query_id: QueryId) -> Option<Response> {
+	match pallet_xcm::Pallet::<T>::query(query_id)? {
+		pallet_xcm::QueryStatus::Ready { response, .. } => response.try_into().ok(),
+		_ => None,
+	}
 }
 
 pub type RelaychainPalletXcm = pallet_xcm::Pallet<relay_chain::Runtime>;
@@ -249,17 +355,32 @@ pub type ParachainPalletXcm = pallet_xcm::Pallet<parachain::Runtime>;
 pub type RelaychainBalances = pallet_balances::Pallet<relay_chain::Runtime>;
 pub type ParachainBalances = pallet_balances::Pallet<parachain::Runtime>;
 pub type ParachainAssets = pallet_assets::Pallet<parachain::Runtime>;
+pub type AssetHubPalletXcm = pallet_xcm::Pallet<asset_hub::Runtime>;
+pub type AssetHubBalances = pallet_balances::Pallet<asset_hub::Runtime>;
+pub type AssetHubAssets = pallet_assets::Pallet<asset_hub::Runtime>;
+pub type BridgeHubPalletXcm = pallet_xcm::Pallet<bridge_hub::Runtime>;
+pub type BridgeHubBalances = pallet_balances::Pallet<bridge_hub::Runtime>;
+pub type EvmParaPalletXcm = pallet_xcm::Pallet<evm_parachain::Runtime>;
+pub type EvmParaBalances = pallet_balances::Pallet<evm_parachain::Runtime>;
 
 /// Prefix for generating alias account for accounts coming  
 /// from chains that use 32 byte long representations.
 pub const FOREIGN_CHAIN_PREFIX_PARA_32: [u8; 37] = *b"ForeignChainAliasAccountPrefix_Para32";
 
+/// Prefix for generating alias account for accounts coming  
+/// from chains that use 20 byte long representations.
+pub const FOREIGN_CHAIN_PREFIX_PARA_20: [u8; 37] = *b"ForeignChainAliasAccountPrefix_Para20";
+
 /// Prefix for generating alias account for accounts coming  
 /// from the relay chain using 32 byte long representations.
 pub const FOREIGN_CHAIN_PREFIX_RELAY: [u8; 36] = *b"ForeignChainAliasAccountPrefix_Relay";
 
+/// Alias accounts of the accounts of other chains, the hash of their location.
+///
+/// The alias is decoded from the hash like `ParentIsPreset` does, so chains with 20 byte accounts
+/// get the first 20 bytes of it.
 pub struct ForeignChainAliasAccount<AccountId>(PhantomData<AccountId>);
-impl<AccountId: From<[u8; 32]> + Clone> Convert<MultiLocation, AccountId>
+impl<AccountId: Decode + Clone> Convert<MultiLocation, AccountId>
 	for ForeignChainAliasAccount<AccountId>
 {
 	fn convert_ref(location: impl Borrow<MultiLocation>) -> Result<AccountId, ()> {
@@ -270,12 +391,24 @@ impl<AccountId: From<[u8; 32]> + Clone> Convert<MultiLocation, AccountId>
 				interior: X2(Parachain(para_id), AccountId32 { id, .. }),
 			} => ForeignChainAliasAccount::<AccountId>::from_para_32(para_id, id, 0),
 
+			// Used on the relay chain for sending paras that use 20 byte accounts
+			MultiLocation {
+				parents: 0,
+				interior: X2(Parachain(para_id), AccountKey20 { key, .. }),
+			} => ForeignChainAliasAccount::<AccountId>::from_para_20(para_id, key, 0),
+
 			// Used on para-chain for sending paras that use 32 byte accounts
 			MultiLocation {
 				parents: 1,
 				interior: X2(Parachain(para_id), AccountId32 { id, .. }),
 			} => ForeignChainAliasAccount::<AccountId>::from_para_32(para_id, id, 1),
 
+			// Used on para-chain for sending paras that use 20 byte accounts
+			MultiLocation {
+				parents: 1,
+				interior: X2(Parachain(para_id), AccountKey20 { key, .. }),
+			} => ForeignChainAliasAccount::<AccountId>::from_para_20(para_id, key, 1),
+
 			// Used on para-chain for sending from the relay chain
 			MultiLocation { parents: 1, interior: X1(AccountId32 { id, .. }) } =>
 				ForeignChainAliasAccount::<AccountId>::from_relay_32(id, 1),
@@ -284,9 +417,11 @@ impl<AccountId: From<[u8; 32]> + Clone> Convert<MultiLocation, AccountId>
 			_ => return Err(()),
 		};
 
-		Ok(entropy.into())
+		Ok(AccountId::decode(&mut TrailingZeroInput::new(&entropy))
+			.expect("infinite length input; no invalid inputs for type; qed"))
 	}
 
+	/// Aliases are hashes of the location they stand for, so they cannot be reversed.
 	fn reverse_ref(_: impl Borrow<AccountId>) -> Result<MultiLocation, ()> {
 		Err(())
 	}
@@ -297,6 +432,10 @@ impl<AccountId> ForeignChainAliasAccount<AccountId> {
 		(FOREIGN_CHAIN_PREFIX_PARA_32, para_id, id, parents).using_encoded(blake2_256)
 	}
 
+	fn from_para_20(para_id: &u32, key: &[u8; 20], parents: u8) -> [u8; 32] {
+		(FOREIGN_CHAIN_PREFIX_PARA_20, para_id, key, parents).using_encoded(blake2_256)
+	}
+
 	fn from_relay_32(id: &[u8; 32], parents: u8) -> [u8; 32] {
 		(FOREIGN_CHAIN_PREFIX_RELAY, id, parents).using_encoded(blake2_256)
 	}
@@ -17,12 +17,17 @@
 //! Parachain runtime mock.
 
 use super::{
-	mock_msg_queue::pallet as mock_msg_queue, AllowNoteUnlockables, Balance,
-	ForeignChainAliasAccount,
+	accounts::RecordDerivations,
+	bridge::{AliasesFromBridgeHub, RemoteConsensusAccount, ThisNetwork},
+	mock_dex::pallet as mock_dex,
+	mock_msg_queue::pallet as mock_msg_queue,
+	mock_notifier::pallet as mock_notifier,
+	trader::{fee_for_weight, trader_fee, DepositToAccount, FeeRate, FeeRates, MultiAssetTrader},
+	AllowNoteUnlockables, Balance, ForeignChainAliasAccount, BRIDGE_HUB_PARA_ID,
 };
 use core::marker::PhantomData;
 use frame_support::{
-	construct_runtime, ensure, parameter_types,
+	construct_runtime, parameter_types, storage_alias,
 	traits::{
 		AsEnsureOriginWithArg, Contains, ContainsPair, EnsureOrigin, EnsureOriginWithArg,
 		Everything, EverythingBut, Nothing,
@@ -31,6 +36,7 @@ use frame_support::{
 		constants::{WEIGHT_PROOF_SIZE_PER_MB, WEIGHT_REF_TIME_PER_SECOND},
 		Weight,
 	},
+	Blake2_128Concat, PalletId,
 };
 use frame_system::{EnsureRoot, EnsureSigned};
 use pallet_xcm::XcmPassthrough;
@@ -38,34 +44,38 @@ use polkadot_parachain::primitives::Sibling;
 use sp_core::{ConstU128, ConstU32, H256};
 use sp_runtime::{
 	testing::Header,
-	traits::{Get, IdentityLookup},
+	traits::{AccountIdConversion, Get, IdentityLookup},
 	AccountId32,
 };
 
-use sp_std::{cell::RefCell, prelude::*};
+use sp_std::prelude::*;
 use xcm::latest::prelude::*;
 use xcm_builder::{
-	AccountId32Aliases, AllowExplicitUnpaidExecutionFrom, AllowTopLevelPaidExecutionFrom,
-	AsPrefixedGeneralIndex, ConvertedConcreteId, CurrencyAdapter as XcmCurrencyAdapter,
-	EnsureXcmOrigin, FixedRateOfFungible, FixedWeightBounds, FungiblesAdapter, IsConcrete,
-	NativeAsset, NoChecking, NonFungiblesAdapter, ParentAsSuperuser, ParentIsPreset,
-	SiblingParachainConvertsVia, SignedAccountId32AsNative, SignedToAccountId32,
-	SovereignSignedViaLocation, WithComputedOrigin,
+	AccountId32Aliases, AllowExplicitUnpaidExecutionFrom, AllowKnownQueryResponses,
+	AllowSubscriptionsFrom, AllowTopLevelPaidExecutionFrom, AsPrefixedGeneralIndex,
+	ConvertedConcreteId, CurrencyAdapter as XcmCurrencyAdapter, EnsureXcmOrigin,
+	FixedRateOfFungible, FixedWeightBounds, FungiblesAdapter, IsConcrete, NativeAsset, NoChecking,
+	NonFungiblesAdapter, ParentAsSuperuser, ParentIsPreset, SiblingParachainConvertsVia,
+	SignedAccountId32AsNative, SignedToAccountId32, SovereignSignedViaLocation, WithComputedOrigin,
 };
 use xcm_executor::{
-	traits::{AssetExchange, Convert, JustTry},
-	Assets as HoldingAssets, Config, XcmExecutor,
+	traits::{Convert, JustTry, WeightBounds},
+	Config, XcmExecutor,
 };
 
 pub type AccountId = AccountId32;
 pub type AssetIdForAssets = u128;
 
-pub type SovereignAccountOf = (
-	ForeignChainAliasAccount<AccountId>,
-	SiblingParachainConvertsVia<Sibling, AccountId>,
-	AccountId32Aliases<RelayNetwork, AccountId>,
-	ParentIsPreset<AccountId>,
-);
+pub type SovereignAccountOf = RecordDerivations<
+	(
+		ForeignChainAliasAccount<AccountId>,
+		SiblingParachainConvertsVia<Sibling, AccountId>,
+		AccountId32Aliases<RelayNetwork, AccountId>,
+		ParentIsPreset<AccountId>,
+		RemoteConsensusAccount<AccountId>,
+	),
+	super::AsParachain<MsgQueue>,
+>;
 
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -200,11 +210,14 @@ parameter_types! {
 	pub const ReservedDmpWeight: Weight = Weight::from_parts(WEIGHT_REF_TIME_PER_SECOND.saturating_div(4), 0);
 }
 
+pub type RelayNetwork = ThisNetwork;
+
 parameter_types! {
 	pub const KsmLocation: MultiLocation = MultiLocation::parent();
 	pub const TokenLocation: MultiLocation = Here.into_location();
-	pub const RelayNetwork: NetworkId = ByGenesis([0; 32]);
-	pub UniversalLocation: InteriorMultiLocation = Parachain(MsgQueue::parachain_id().into()).into();
+	pub UniversalLocation: InteriorMultiLocation =
+		X2(GlobalConsensus(RelayNetwork::get()), Parachain(MsgQueue::parachain_id().into()));
+	pub BridgeHubLocation: MultiLocation = (Parent, Parachain(BRIDGE_HUB_PARA_ID)).into();
 }
 
 pub type XcmOriginToCallOrigin = (
@@ -242,6 +255,33 @@ pub fn estimate_fee_for_weight(weight: Weight) -> u128 {
 		units_per_mb * (weight.proof_size() as u128) / (WEIGHT_PROOF_SIZE_PER_MB as u128)
 }
 
+/// Fee for `weight` when paid in `asset`, `None` if fees cannot be paid in `asset`.
+pub fn estimate_fee_for_weight_in(asset: impl Into<MultiLocation>, weight: Weight) -> Option<u128> {
+	let asset = asset.into();
+	if Concrete(asset) == TokensPerSecondPerMegabyte::get().0 {
+		return Some(estimate_fee_for_weight(weight))
+	}
+	StoredFeeRates::fee_rate(&asset).map(|rate| fee_for_weight(rate, weight))
+}
+
+/// Fee charged by the `Trader` for executing `message`, in the relay token, as `BuyExecution`
+/// would charge it.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_fee_for(message: &Xcm<RuntimeCall>) -> u128 {
+	let weight = estimate_weight_for(message);
+	trader_fee::<<XcmConfig as Config>::Trader>(TokensPerSecondPerMegabyte::get().0, weight)
+		.expect("The `Trader` takes fees in the relay token")
+}
+
+/// Weight of `message` according to the `Weigher`.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_weight_for(message: &Xcm<RuntimeCall>) -> Weight {
+	<XcmConfig as Config>::Weigher::weight(&mut message.clone())
+		.expect("message has at most `MaxInstructions` instructions")
+}
+
 pub type LocalBalancesTransactor =
 	XcmCurrencyAdapter<Balances, IsConcrete<TokenLocation>, SovereignAccountOf, AccountId, ()>;
 
@@ -293,15 +333,52 @@ impl Contains<MultiLocation> for ParentRelay {
 	}
 }
 
-pub type XcmRouter = super::ParachainXcmRouter<MsgQueue>;
+parameter_types! {
+	pub const TreasuryPalletId: PalletId = PalletId(*b"py/trsry");
+	pub TreasuryAccount: AccountId = TreasuryPalletId::get().into_account_truncating();
+	pub TreasuryLocation: MultiLocation =
+		Junction::AccountId32 { network: None, id: TreasuryAccount::get().into() }.into();
+	/// Default fee rate of the native token, twice the rate of the relay token.
+	pub const NativeFeeRate: FeeRate = (2_000_000_000_000, 2 * 1024 * 1024);
+	/// Default fee rate of the tokens of the siblings, the same as the relay token.
+	pub const SiblingFeeRate: FeeRate = (1_000_000_000_000, 1024 * 1024);
+}
+
+/// Fee rates of the assets fees can be paid in besides the relay token.
+#[storage_alias]
+pub type AssetFeeRates = StorageMap<MockFeeRates, Blake2_128Concat, MultiLocation, FeeRate>;
+
+/// Sets the fee rate of `asset`, in units per second of ref time and per megabyte of proof size.
+pub fn set_fee_rate(asset: impl Into<MultiLocation>, rate: FeeRate) {
+	AssetFeeRates::insert(asset.into(), rate);
+}
+
+pub struct StoredFeeRates;
+impl FeeRates for StoredFeeRates {
+	fn fee_rate(asset: &MultiLocation) -> Option<FeeRate> {
+		AssetFeeRates::get(asset)
+	}
+}
+
+pub type FeesToTreasury = DepositToAccount<AssetTransactors, TreasuryLocation>;
+
+/// Takes fees in the relay token, or in any asset with a rate in `AssetFeeRates`.
+pub type Trader = (
+	FixedRateOfFungible<TokensPerSecondPerMegabyte, FeesToTreasury>,
+	MultiAssetTrader<StoredFeeRates, FeesToTreasury>,
+);
+
+pub type XcmRouter = super::ParachainRouter<MsgQueue, PolkadotXcm>;
 pub type Barrier = WithComputedOrigin<
 	(
 		AllowNoteUnlockables,
 		AllowExplicitUnpaidExecutionFrom<ParentRelay>,
 		AllowTopLevelPaidExecutionFrom<Everything>,
+		AllowKnownQueryResponses<PolkadotXcm>,
+		AllowSubscriptionsFrom<Everything>,
 	),
 	UniversalLocation,
-	ConstU32<1>,
+	ConstU32<8>,
 >;
 
 parameter_types! {
@@ -316,40 +393,6 @@ pub type TrustedTeleporters =
 	(xcm_builder::Case<NftCollectionOneForRelay>, xcm_builder::Case<RelayNativeAssetForRelay>);
 pub type TrustedReserves = EverythingBut<xcm_builder::Case<NftCollectionOneForRelay>>;
 
-thread_local! {
-	pub static EXCHANGE_ASSETS: RefCell<HoldingAssets> = RefCell::new(HoldingAssets::new());
-}
-pub fn set_exchange_assets(assets: impl Into<MultiAssets>) {
-	EXCHANGE_ASSETS.with(|a| a.replace(assets.into().into()));
-}
-pub fn exchange_assets() -> MultiAssets {
-	EXCHANGE_ASSETS.with(|a| a.borrow().clone().into())
-}
-/// Simple implementation of AssetExchange.
-/// If maximal is true we take all assets in the exchange
-/// for the assets we want to give.
-/// If maximal is false, we take exactly what we want in return for all assets in give.
-pub struct TestAssetExchange;
-impl AssetExchange for TestAssetExchange {
-	fn exchange_asset(
-		_origin: Option<&MultiLocation>,
-		give: HoldingAssets,
-		want: &MultiAssets,
-		maximal: bool,
-	) -> Result<HoldingAssets, HoldingAssets> {
-		let mut have = EXCHANGE_ASSETS.with(|l| l.borrow().clone());
-		ensure!(have.contains_assets(want), give);
-		let get = if maximal {
-			std::mem::replace(&mut have, HoldingAssets::new())
-		} else {
-			have.saturating_take(want.clone().into())
-		};
-		have.subsume_assets(give);
-		EXCHANGE_ASSETS.with(|l| l.replace(have));
-		Ok(get)
-	}
-}
-
 pub struct XcmConfig;
 impl Config for XcmConfig {
 	type RuntimeCall = RuntimeCall;
@@ -361,25 +404,36 @@ impl Config for XcmConfig {
 	type UniversalLocation = UniversalLocation;
 	type Barrier = Barrier;
 	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
-	type Trader = FixedRateOfFungible<TokensPerSecondPerMegabyte, ()>;
-	type ResponseHandler = ();
+	type Trader = Trader;
+	type ResponseHandler = PolkadotXcm;
 	type AssetTrap = PolkadotXcm;
 	type AssetLocker = PolkadotXcm;
-	type AssetExchanger = TestAssetExchange;
+	type AssetExchanger = Dex;
 	type AssetClaims = PolkadotXcm;
 	type SubscriptionService = PolkadotXcm;
 	type PalletInstancesInfo = AllPalletsWithSystem;
 	type FeeManager = ();
 	type MaxAssetsIntoHolding = MaxAssetsIntoHolding;
 	type MessageExporter = ();
-	type UniversalAliases = Nothing;
+	type UniversalAliases = AliasesFromBridgeHub<BridgeHubLocation>;
 	type CallDispatcher = RuntimeCall;
 	type SafeCallFilter = Everything;
 }
 
 impl mock_msg_queue::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
-	type XcmExecutor = XcmExecutor<XcmConfig>;
+	type XcmExecutor = super::TraceExecutor<XcmExecutor<XcmConfig>, super::AsParachain<MsgQueue>>;
+	type ExecuteOverweightOrigin = EnsureRoot<AccountId>;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
+}
+
+impl mock_dex::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+}
+
+impl mock_notifier::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type ResponseOrigin = pallet_xcm::EnsureResponse<Everything>;
 }
 
 pub type LocalOriginToLocation = SignedToAccountId32<RuntimeOrigin, AccountId, RelayNetwork>;
@@ -413,7 +467,7 @@ impl pallet_xcm::Config for Runtime {
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
-	type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
 	type Currency = Balances;
 	type CurrencyMatcher = IsConcrete<TokenLocation>;
 	type TrustedLockers = TrustedLockerCase<TrustedLockPairs>;
@@ -438,9 +492,11 @@ construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Storage, Config, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		MsgQueue: mock_msg_queue::{Pallet, Storage, Event<T>},
+		MsgQueue: mock_msg_queue::{Pallet, Call, Storage, Event<T>},
 		PolkadotXcm: pallet_xcm::{Pallet, Call, Event<T>, Origin},
 		Assets: pallet_assets,
 		ForeignUniques: pallet_uniques,
+		Dex: mock_dex::{Pallet, Storage, Event<T>},
+		Notifier: mock_notifier::{Pallet, Call, Storage, Event<T>},
 	}
 );
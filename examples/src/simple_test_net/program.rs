@@ -0,0 +1,331 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fluent builder for XCM programs.
+//!
+//! ```ignore
+//! let message = XcmBuilder::<parachain::RuntimeCall>::new()
+//!     .withdraw((Parent, amount))
+//!     .buy_execution((Parent, fee), Unlimited)
+//!     .initiate_reserve_withdraw(All, Parent, |inner| {
+//!         inner.buy_execution((Here, fee_in_relay), Unlimited).deposit(All, beneficiary)
+//!     })
+//!     .build();
+//! ```
+//!
+//! The builder follows the rules of `AllowTopLevelPaidExecutionFrom` with its type: a program
+//! starts with an instruction loading the holding register, like `withdraw`, then may clear its
+//! origin, then buys its execution; only then does it take any other instruction. Programs that
+//! do not pay for their execution start with `unpaid_execution` instead.
+//!
+//! ```
+//! use frame_support::weights::Weight;
+//! use xcm::latest::prelude::*;
+//! use xcm_examples::{parachain, program::XcmBuilder};
+//!
+//! let remark = vec![1, 2, 3];
+//! let call = parachain::RuntimeCall::System(frame_system::Call::remark_with_event { remark });
+//! let message = XcmBuilder::<parachain::RuntimeCall>::new()
+//!     .withdraw((Parent, 100))
+//!     .buy_execution((Parent, 100), Unlimited)
+//!     .transact(OriginKind::SovereignAccount, Weight::from_parts(1_000_000, 1_024), call)
+//!     .build();
+//! assert_eq!(message.len(), 3);
+//! ```
+//!
+//! Programs breaking these rules do not compile, like one transacting before buying its
+//! execution:
+//!
+//! ```compile_fail
+//! # use frame_support::weights::Weight;
+//! # use xcm::latest::prelude::*;
+//! # use xcm_examples::{parachain, program::XcmBuilder};
+//! # let remark = vec![1, 2, 3];
+//! # let call = parachain::RuntimeCall::System(frame_system::Call::remark_with_event { remark });
+//! let message = XcmBuilder::<parachain::RuntimeCall>::new()
+//!     .withdraw((Parent, 100))
+//!     .transact(OriginKind::SovereignAccount, Weight::from_parts(1_000_000, 1_024), call)
+//!     .build();
+//! ```
+//!
+//! or one buying its execution without loading the holding register first:
+//!
+//! ```compile_fail
+//! # use xcm::latest::prelude::*;
+//! # use xcm_examples::{parachain, program::XcmBuilder};
+//! let message = XcmBuilder::<parachain::RuntimeCall>::new()
+//!     .buy_execution((Parent, 100), Unlimited)
+//!     .build();
+//! ```
+//!
+//! The calls of `transact` are the ones of the chain executing the program:
+//!
+//! ```compile_fail
+//! # use frame_support::weights::Weight;
+//! # use xcm::latest::prelude::*;
+//! # use xcm_examples::{parachain, program::XcmBuilder, relay_chain};
+//! # let remark = vec![1, 2, 3];
+//! let call = relay_chain::RuntimeCall::System(frame_system::Call::remark_with_event { remark });
+//! let message = XcmBuilder::<parachain::RuntimeCall>::new()
+//!     .withdraw((Parent, 100))
+//!     .buy_execution((Parent, 100), Unlimited)
+//!     .transact(OriginKind::SovereignAccount, Weight::from_parts(1_000_000, 1_024), call)
+//!     .build();
+//! ```
+//!
+//! The programs sent to other chains by `initiate_reserve_withdraw`, `deposit_reserve_asset`,
+//! `transfer_reserve_asset` and `initiate_teleport` are prefixed with an instruction loading the
+//! holding register and `ClearOrigin` by the executor, so they start by buying their execution.
+
+use core::marker::PhantomData;
+
+use codec::Encode;
+use frame_support::weights::Weight;
+use xcm::latest::prelude::*;
+
+/// The states of an `XcmBuilder`.
+pub mod state {
+	/// No instruction yet.
+	pub struct Empty;
+	/// The holding register is loaded, the execution is not bought yet.
+	pub struct Loaded;
+	/// The execution is bought, or does not need to be, so any instruction can follow.
+	pub struct Paid;
+}
+
+use state::{Empty, Loaded, Paid};
+
+/// Builder of a `Xcm<Call>`, where `Call` is the `RuntimeCall` of the chain executing it. See
+/// the module documentation.
+pub struct XcmBuilder<Call = (), State = Empty> {
+	instructions: Vec<Instruction<Call>>,
+	state: PhantomData<State>,
+}
+
+/// Builder of the program sent to another chain, whose holding register the executor loads.
+pub type InnerBuilder = XcmBuilder<(), Loaded>;
+
+impl<Call> Default for XcmBuilder<Call, Empty> {
+	fn default() -> Self {
+		XcmBuilder { instructions: Vec::new(), state: PhantomData }
+	}
+}
+
+impl<Call, State> XcmBuilder<Call, State> {
+	fn push<Next>(mut self, instruction: Instruction<Call>) -> XcmBuilder<Call, Next> {
+		self.instructions.push(instruction);
+		XcmBuilder { instructions: self.instructions, state: PhantomData }
+	}
+}
+
+impl<Call> XcmBuilder<Call, Empty> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// `WithdrawAsset`
+	pub fn withdraw(self, assets: impl Into<MultiAssets>) -> XcmBuilder<Call, Loaded> {
+		self.push(WithdrawAsset(assets.into()))
+	}
+
+	/// `ReserveAssetDeposited`
+	pub fn reserve_asset_deposited(
+		self,
+		assets: impl Into<MultiAssets>,
+	) -> XcmBuilder<Call, Loaded> {
+		self.push(ReserveAssetDeposited(assets.into()))
+	}
+
+	/// `ReceiveTeleportedAsset`
+	pub fn receive_teleported_asset(
+		self,
+		assets: impl Into<MultiAssets>,
+	) -> XcmBuilder<Call, Loaded> {
+		self.push(ReceiveTeleportedAsset(assets.into()))
+	}
+
+	/// `ClaimAsset`
+	pub fn claim_asset(
+		self,
+		assets: impl Into<MultiAssets>,
+		ticket: impl Into<MultiLocation>,
+	) -> XcmBuilder<Call, Loaded> {
+		self.push(ClaimAsset { assets: assets.into(), ticket: ticket.into() })
+	}
+
+	/// `UnpaidExecution`, for the programs the barrier lets through without paying.
+	pub fn unpaid_execution(
+		self,
+		weight_limit: WeightLimit,
+		check_origin: Option<MultiLocation>,
+	) -> XcmBuilder<Call, Paid> {
+		self.push(UnpaidExecution { weight_limit, check_origin })
+	}
+}
+
+impl<Call> XcmBuilder<Call, Loaded> {
+	/// `ClearOrigin`
+	pub fn clear_origin(self) -> Self {
+		self.push(ClearOrigin)
+	}
+
+	/// `BuyExecution`
+	pub fn buy_execution(
+		self,
+		fees: impl Into<MultiAsset>,
+		weight_limit: WeightLimit,
+	) -> XcmBuilder<Call, Paid> {
+		self.push(BuyExecution { fees: fees.into(), weight_limit })
+	}
+}
+
+impl<Call> XcmBuilder<Call, Paid> {
+	/// A builder for the instructions of an error handler or appendix.
+	fn body() -> Self {
+		XcmBuilder { instructions: Vec::new(), state: PhantomData }
+	}
+
+	/// Any instruction, for the ones the builder has no method for.
+	pub fn instruction(self, instruction: Instruction<Call>) -> Self {
+		self.push(instruction)
+	}
+
+	/// `WithdrawAsset`
+	pub fn withdraw(self, assets: impl Into<MultiAssets>) -> Self {
+		self.push(WithdrawAsset(assets.into()))
+	}
+
+	/// `ClearOrigin`
+	pub fn clear_origin(self) -> Self {
+		self.push(ClearOrigin)
+	}
+
+	/// `DescendOrigin`
+	pub fn descend_origin(self, interior: impl Into<InteriorMultiLocation>) -> Self {
+		self.push(DescendOrigin(interior.into()))
+	}
+
+	/// `DepositAsset`
+	pub fn deposit(
+		self,
+		assets: impl Into<MultiAssetFilter>,
+		beneficiary: impl Into<MultiLocation>,
+	) -> Self {
+		self.push(DepositAsset { assets: assets.into(), beneficiary: beneficiary.into() })
+	}
+
+	/// `DepositReserveAsset`, with the program `inner` builds sent to `dest`.
+	pub fn deposit_reserve_asset(
+		self,
+		assets: impl Into<MultiAssetFilter>,
+		dest: impl Into<MultiLocation>,
+		inner: impl FnOnce(InnerBuilder) -> XcmBuilder<(), Paid>,
+	) -> Self {
+		let xcm = inner(InnerBuilder::loaded()).build();
+		self.push(DepositReserveAsset { assets: assets.into(), dest: dest.into(), xcm })
+	}
+
+	/// `InitiateReserveWithdraw`, with the program `inner` builds sent to `reserve`.
+	pub fn initiate_reserve_withdraw(
+		self,
+		assets: impl Into<MultiAssetFilter>,
+		reserve: impl Into<MultiLocation>,
+		inner: impl FnOnce(InnerBuilder) -> XcmBuilder<(), Paid>,
+	) -> Self {
+		let xcm = inner(InnerBuilder::loaded()).build();
+		self.push(InitiateReserveWithdraw { assets: assets.into(), reserve: reserve.into(), xcm })
+	}
+
+	/// `TransferReserveAsset`, with the program `inner` builds sent to `dest`.
+	pub fn transfer_reserve_asset(
+		self,
+		assets: impl Into<MultiAssets>,
+		dest: impl Into<MultiLocation>,
+		inner: impl FnOnce(InnerBuilder) -> XcmBuilder<(), Paid>,
+	) -> Self {
+		let xcm = inner(InnerBuilder::loaded()).build();
+		self.push(TransferReserveAsset { assets: assets.into(), dest: dest.into(), xcm })
+	}
+
+	/// `InitiateTeleport`, with the program `inner` builds sent to `dest`.
+	pub fn initiate_teleport(
+		self,
+		assets: impl Into<MultiAssetFilter>,
+		dest: impl Into<MultiLocation>,
+		inner: impl FnOnce(InnerBuilder) -> XcmBuilder<(), Paid>,
+	) -> Self {
+		let xcm = inner(InnerBuilder::loaded()).build();
+		self.push(InitiateTeleport { assets: assets.into(), dest: dest.into(), xcm })
+	}
+
+	/// `Transact` of `call`, a `RuntimeCall` of the chain executing the program.
+	pub fn transact(
+		self,
+		origin_kind: OriginKind,
+		require_weight_at_most: Weight,
+		call: Call,
+	) -> Self
+	where
+		Call: Encode,
+	{
+		self.push(Transact { origin_kind, require_weight_at_most, call: call.encode().into() })
+	}
+
+	/// `RefundSurplus`
+	pub fn refund_surplus(self) -> Self {
+		self.push(RefundSurplus)
+	}
+
+	/// `SetErrorHandler`, with the instructions `handler` builds.
+	pub fn set_error_handler(self, handler: impl FnOnce(Self) -> Self) -> Self {
+		let handler = handler(Self::body()).build();
+		self.push(SetErrorHandler(handler))
+	}
+
+	/// `SetAppendix`, with the instructions `appendix` builds.
+	pub fn set_appendix(self, appendix: impl FnOnce(Self) -> Self) -> Self {
+		let appendix = appendix(Self::body()).build();
+		self.push(SetAppendix(appendix))
+	}
+
+	/// `ReportError`
+	pub fn report_error(self, response_info: QueryResponseInfo) -> Self {
+		self.push(ReportError(response_info))
+	}
+
+	/// `ExpectOrigin`
+	pub fn expect_origin(self, origin: Option<MultiLocation>) -> Self {
+		self.push(ExpectOrigin(origin))
+	}
+
+	/// `Trap`
+	pub fn trap(self, code: u64) -> Self {
+		self.push(Trap(code))
+	}
+
+	/// The program built so far. Only programs that load the holding register and buy their
+	/// execution, or do not need to, can be built.
+	pub fn build(self) -> Xcm<Call> {
+		Xcm(self.instructions)
+	}
+}
+
+impl XcmBuilder<(), Loaded> {
+	/// The builder of a program whose holding register the executor loads.
+	fn loaded() -> Self {
+		XcmBuilder { instructions: Vec::new(), state: PhantomData }
+	}
+}
@@ -16,38 +16,54 @@
 
 //! Relay chain runtime mock.
 
+use codec::Decode;
 use frame_support::{
-	construct_runtime, parameter_types,
-	traits::{AsEnsureOriginWithArg, Contains, Everything, Nothing},
+	construct_runtime,
+	pallet_prelude::ValueQuery,
+	parameter_types,
+	traits::{AsEnsureOriginWithArg, Contains, Everything, QueueFootprint, QueueFootprinter},
 	weights::{
 		constants::{WEIGHT_PROOF_SIZE_PER_MB, WEIGHT_REF_TIME_PER_SECOND},
 		Weight,
 	},
+	Twox64Concat,
 };
 
 use frame_system::EnsureRoot;
 use sp_core::{ConstU128, ConstU32, H256};
-use sp_runtime::{testing::Header, traits::IdentityLookup, AccountId32};
+use sp_runtime::{
+	testing::Header, traits::IdentityLookup, transaction_validity::TransactionPriority, AccountId32,
+};
 
-use polkadot_parachain::primitives::Id as ParaId;
-use polkadot_runtime_parachains::{configuration, origin, shared};
-use xcm::latest::prelude::*;
+use polkadot_parachain::primitives::{Id as ParaId, IsSystem};
+use polkadot_runtime_parachains::{configuration, dmp, hrmp, origin, paras, shared};
+use xcm::{latest::prelude::*, VersionedXcm};
 use xcm_builder::{
 	AccountId32Aliases, AllowExplicitUnpaidExecutionFrom, AllowSubscriptionsFrom,
-	AllowTopLevelPaidExecutionFrom, AsPrefixedGeneralIndex, ChildParachainAsNative,
+	AllowTopLevelPaidExecutionFrom, AsPrefixedGeneralIndex, Case, ChildParachainAsNative,
 	ChildParachainConvertsVia, ChildSystemParachainAsSuperuser, ConvertedConcreteId,
 	CurrencyAdapter as XcmCurrencyAdapter, FixedRateOfFungible, FixedWeightBounds, IsConcrete,
 	NoChecking, NonFungiblesAdapter, SiblingParachainConvertsVia, SignedAccountId32AsNative,
 	SignedToAccountId32, SovereignSignedViaLocation, WithComputedOrigin,
 };
-use xcm_executor::{traits::JustTry, Config, XcmExecutor};
+use xcm_executor::{
+	traits::{JustTry, WeightBounds},
+	Config, XcmExecutor,
+};
 use xcm_simulator::{
 	AggregateMessageOrigin, ProcessMessage, ProcessMessageError, UmpQueueId, WeightMeter,
 };
 
-use super::{AllowNoteUnlockables, AllowUnlocks, Balance, ForeignChainAliasAccount};
+use super::{
+	accounts::RecordDerivations,
+	bridge::{AliasesFromBridgeHub, RemoteConsensusAccount, ThisNetwork},
+	trader::trader_fee,
+	AllowNoteUnlockables, AllowUnlocks, Balance, ForeignChainAliasAccount, ASSET_HUB_PARA_ID,
+	BRIDGE_HUB_PARA_ID,
+};
 
 pub type AccountId = AccountId32;
+pub type BlockNumber = u64;
 
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -57,7 +73,7 @@ impl frame_system::Config for Runtime {
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	type Index = u64;
-	type BlockNumber = u64;
+	type BlockNumber = BlockNumber;
 	type Hash = H256;
 	type Hashing = ::sp_runtime::traits::BlakeTwo256;
 	type AccountId = AccountId;
@@ -129,19 +145,168 @@ impl configuration::Config for Runtime {
 	type WeightInfo = configuration::TestWeightInfo;
 }
 
+impl<C> frame_system::offchain::SendTransactionTypes<C> for Runtime
+where
+	RuntimeCall: From<C>,
+{
+	type Extrinsic = UncheckedExtrinsic;
+	type OverarchingCall = RuntimeCall;
+}
+
+/// Footprint of the UMP queues in `MessageQueue`, which the inclusion pallet provides in a real
+/// relay chain.
+pub struct UmpQueueFootprinter;
+impl QueueFootprinter for UmpQueueFootprinter {
+	type Origin = UmpQueueId;
+
+	fn get(origin: Self::Origin) -> QueueFootprint {
+		<MessageQueue as QueueFootprinter>::get(AggregateMessageOrigin::Ump(origin))
+	}
+}
+
+parameter_types! {
+	pub const ParasUnsignedPriority: TransactionPriority = TransactionPriority::max_value();
+}
+
+impl paras::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	type WeightInfo = paras::TestWeightInfo;
+	type UnsignedPriority = ParasUnsignedPriority;
+	type QueueFootprinter = UmpQueueFootprinter;
+	type NextSessionRotation = ();
+	type OnNewHead = ();
+}
+
+impl dmp::Config for Runtime {}
+
+/// Weight of every call of the `hrmp` pallet.
+pub const HRMP_CALL_WEIGHT: Weight = Weight::from_parts(1_000_000, 1_000);
+
+/// Weights of the `hrmp` pallet. `hrmp::TestWeightInfo` weighs every call `Weight::MAX`, which
+/// would make any `Transact` of an HRMP call overweight.
+pub struct HrmpWeightInfo;
+impl hrmp::WeightInfo for HrmpWeightInfo {
+	fn hrmp_init_open_channel() -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn hrmp_accept_open_channel() -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn hrmp_close_channel() -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn force_clean_hrmp(_: u32, _: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn force_process_hrmp_open(_: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn force_process_hrmp_close(_: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn hrmp_cancel_open_request(_: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn clean_open_channel_requests(_: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+	fn force_open_hrmp_channel(_: u32) -> Weight {
+		HRMP_CALL_WEIGHT
+	}
+}
+
+impl hrmp::Config for Runtime {
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type WeightInfo = HrmpWeightInfo;
+}
+
+/// Capacity of the HRMP channels opened at genesis, also the maximum capacity of a channel.
+pub const HRMP_CHANNEL_MAX_CAPACITY: u32 = 8;
+/// Maximum message size of the HRMP channels opened at genesis, also the maximum message size of
+/// a channel.
+pub const HRMP_CHANNEL_MAX_MESSAGE_SIZE: u32 = 64 * 1024;
+
+/// The host configuration of the relay chain, with HRMP channels between all parachains allowed
+/// and no deposits required to open one.
+pub fn host_configuration() -> configuration::HostConfiguration<BlockNumber> {
+	configuration::HostConfiguration {
+		max_downward_message_size: 1024 * 1024,
+		hrmp_channel_max_capacity: HRMP_CHANNEL_MAX_CAPACITY,
+		hrmp_channel_max_message_size: HRMP_CHANNEL_MAX_MESSAGE_SIZE,
+		hrmp_channel_max_total_size: HRMP_CHANNEL_MAX_CAPACITY * HRMP_CHANNEL_MAX_MESSAGE_SIZE,
+		hrmp_max_parachain_outbound_channels: 32,
+		hrmp_max_parachain_inbound_channels: 32,
+		hrmp_sender_deposit: 0,
+		hrmp_recipient_deposit: 0,
+		..Default::default()
+	}
+}
+
+/// Enacts the pending HRMP channel open and close requests, like a session change would.
+///
+/// Must be called within the externalities of the relay chain.
+pub fn enact_hrmp_requests() {
+	let open_requests = hrmp::HrmpOpenChannelRequestsList::<Runtime>::decode_len().unwrap_or(0);
+	Hrmp::force_process_hrmp_open(RuntimeOrigin::root(), open_requests as u32).unwrap();
+	let close_requests = hrmp::HrmpCloseChannelRequestsList::<Runtime>::decode_len().unwrap_or(0);
+	Hrmp::force_process_hrmp_close(RuntimeOrigin::root(), close_requests as u32).unwrap();
+}
+
+#[frame_support::storage_alias]
+type ForwardedDownwardMessages =
+	StorageMap<MockDownwardMessages, Twox64Concat, ParaId, u32, ValueQuery>;
+
+/// Sends the messages queued in the `dmp` pallet since the last call, like the HRMP notifications,
+/// to their parachain through the `XcmRouter`.
+///
+/// The simulator delivers downward messages on its own message bus, so without this they would
+/// stay in the queue of the `dmp` pallet. Must be called within the externalities of the relay
+/// chain.
+pub fn forward_downward_messages() {
+	for para_id in paras::Pallet::<Runtime>::parachains() {
+		let queue = dmp::Pallet::<Runtime>::dmq_contents(para_id);
+		let forwarded = ForwardedDownwardMessages::get(para_id) as usize;
+		for message in queue.iter().skip(forwarded) {
+			let xcm = VersionedXcm::<()>::decode(&mut &message.msg[..])
+				.ok()
+				.and_then(|xcm| Xcm::<()>::try_from(xcm).ok())
+				.expect("the `dmp` pallet only queues XCM encoded by this relay chain; qed");
+			send_xcm::<XcmRouter>(Parachain(para_id.into()).into(), xcm)
+				.expect("the simulator router delivers to every parachain; qed");
+		}
+		ForwardedDownwardMessages::insert(para_id, queue.len() as u32);
+	}
+}
+
+/// Marks the messages queued in the `dmp` pallet as forwarded, without sending them.
+pub(crate) fn discard_downward_messages() {
+	for para_id in paras::Pallet::<Runtime>::parachains() {
+		let queued = dmp::Pallet::<Runtime>::dmq_contents(para_id).len();
+		ForwardedDownwardMessages::insert(para_id, queued as u32);
+	}
+}
+
+pub type RelayNetwork = ThisNetwork;
+
 parameter_types! {
-	pub RelayNetwork: NetworkId = ByGenesis([0; 32]);
 	pub const TokenLocation: MultiLocation = Here.into_location();
-	pub UniversalLocation: InteriorMultiLocation = Here;
+	pub UniversalLocation: InteriorMultiLocation = X1(GlobalConsensus(RelayNetwork::get()));
 	pub UnitWeightCost: u64 = 1_000;
+	pub BridgeHubLocation: MultiLocation = Parachain(BRIDGE_HUB_PARA_ID).into();
 }
 
-pub type SovereignAccountOf = (
-	ForeignChainAliasAccount<AccountId>,
-	AccountId32Aliases<RelayNetwork, AccountId>,
-	ChildParachainConvertsVia<ParaId, AccountId>,
-	SiblingParachainConvertsVia<ParaId, AccountId>,
-);
+pub type SovereignAccountOf = RecordDerivations<
+	(
+		ForeignChainAliasAccount<AccountId>,
+		AccountId32Aliases<RelayNetwork, AccountId>,
+		ChildParachainConvertsVia<ParaId, AccountId>,
+		SiblingParachainConvertsVia<ParaId, AccountId>,
+		RemoteConsensusAccount<AccountId>,
+	),
+	super::AsRelay,
+>;
 
 pub type LocalBalancesTransactor =
 	XcmCurrencyAdapter<Balances, IsConcrete<TokenLocation>, SovereignAccountOf, AccountId, ()>;
@@ -157,11 +322,26 @@ pub type LocalUniquesTransactor = NonFungiblesAdapter<
 
 pub type AssetTransactors = (LocalBalancesTransactor, LocalUniquesTransactor);
 
+/// Para id of a child parachain, considering every id below `2000` a system parachain.
+///
+/// `ParaId::is_system` only covers the ids below `1000`, which would leave out the asset hub.
+pub struct SystemParachainId(u32);
+impl From<u32> for SystemParachainId {
+	fn from(id: u32) -> Self {
+		Self(id)
+	}
+}
+impl IsSystem for SystemParachainId {
+	fn is_system(&self) -> bool {
+		self.0 < 2000
+	}
+}
+
 type LocalOriginConverter = (
 	SovereignSignedViaLocation<SovereignAccountOf, RuntimeOrigin>,
 	ChildParachainAsNative<origin::Origin, RuntimeOrigin>,
 	SignedAccountId32AsNative<RelayNetwork, RuntimeOrigin>,
-	ChildSystemParachainAsSuperuser<ParaId, RuntimeOrigin>,
+	ChildSystemParachainAsSuperuser<SystemParachainId, RuntimeOrigin>,
 );
 
 parameter_types! {
@@ -189,6 +369,24 @@ pub fn estimate_fee_for_weight(weight: Weight) -> u128 {
 		units_per_mb * (weight.proof_size() as u128) / (WEIGHT_PROOF_SIZE_PER_MB as u128)
 }
 
+/// Fee charged by the `Trader` for executing `message`, in the relay token, as `BuyExecution`
+/// would charge it.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_fee_for(message: &Xcm<RuntimeCall>) -> u128 {
+	let weight = estimate_weight_for(message);
+	trader_fee::<<XcmConfig as Config>::Trader>(TokensPerSecondPerMegabyte::get().0, weight)
+		.expect("The `Trader` takes fees in the relay token")
+}
+
+/// Weight of `message` according to the `Weigher`.
+///
+/// Panics if `message` has more instructions than `MaxInstructions`.
+pub fn estimate_weight_for(message: &Xcm<RuntimeCall>) -> Weight {
+	<XcmConfig as Config>::Weigher::weight(&mut message.clone())
+		.expect("message has at most `MaxInstructions` instructions")
+}
+
 pub struct ChildrenParachains;
 impl Contains<MultiLocation> for ChildrenParachains {
 	fn contains(location: &MultiLocation) -> bool {
@@ -196,7 +394,16 @@ impl Contains<MultiLocation> for ChildrenParachains {
 	}
 }
 
-pub type XcmRouter = super::RelayChainXcmRouter;
+parameter_types! {
+	pub AssetHubLocation: MultiLocation = Parachain(ASSET_HUB_PARA_ID).into();
+	pub TokenForAssetHub: (MultiAssetFilter, MultiLocation) =
+		(Wild(AllOf { id: Concrete(TokenLocation::get()), fun: WildFungible }), AssetHubLocation::get());
+}
+
+/// The asset hub is trusted to teleport the native token of the relay chain.
+pub type TrustedTeleporters = Case<TokenForAssetHub>;
+
+pub type XcmRouter = super::RelayRouter<XcmPallet>;
 pub type Barrier = WithComputedOrigin<
 	(
 		AllowNoteUnlockables,
@@ -206,7 +413,7 @@ pub type Barrier = WithComputedOrigin<
 		AllowSubscriptionsFrom<Everything>,
 	),
 	UniversalLocation,
-	ConstU32<1>,
+	ConstU32<8>,
 >;
 
 pub struct XcmConfig;
@@ -216,7 +423,7 @@ impl Config for XcmConfig {
 	type AssetTransactor = AssetTransactors;
 	type OriginConverter = LocalOriginConverter;
 	type IsReserve = ();
-	type IsTeleporter = ();
+	type IsTeleporter = TrustedTeleporters;
 	type UniversalLocation = UniversalLocation;
 	type Barrier = Barrier;
 	type Weigher = FixedWeightBounds<XcmInstructionWeight, RuntimeCall, MaxInstructions>;
@@ -231,7 +438,7 @@ impl Config for XcmConfig {
 	type FeeManager = ();
 	type MaxAssetsIntoHolding = MaxAssetsIntoHolding;
 	type MessageExporter = ();
-	type UniversalAliases = Nothing;
+	type UniversalAliases = AliasesFromBridgeHub<BridgeHubLocation>;
 	type CallDispatcher = RuntimeCall;
 	type SafeCallFilter = Everything;
 }
@@ -258,7 +465,7 @@ impl pallet_xcm::Config for Runtime {
 	type RuntimeOrigin = RuntimeOrigin;
 	type RuntimeCall = RuntimeCall;
 	const VERSION_DISCOVERY_QUEUE_SIZE: u32 = 100;
-	type AdvertisedXcmVersion = pallet_xcm::CurrentXcmVersion;
+	type AdvertisedXcmVersion = super::AdvertisedXcmVersion;
 	type Currency = Balances;
 	type CurrencyMatcher = IsConcrete<TokenLocation>;
 	type TrustedLockers = ();
@@ -302,11 +509,15 @@ impl ProcessMessage for MessageProcessor {
 		let para = match origin {
 			AggregateMessageOrigin::Ump(UmpQueueId::Para(para)) => para,
 		};
-		xcm_builder::ProcessXcmMessage::<
+		let message = super::corrupt_upward_message(para.into(), message);
+		let result = xcm_builder::ProcessXcmMessage::<
 			Junction,
-			xcm_executor::XcmExecutor<XcmConfig>,
+			super::TraceExecutor<xcm_executor::XcmExecutor<XcmConfig>, super::AsRelay>,
 			RuntimeCall,
-		>::process_message(message, Junction::Parachain(para.into()), meter, id)
+		>::process_message(&message, Junction::Parachain(para.into()), meter, id);
+		// A `Transact` of an HRMP call queues notifications for the parachains involved.
+		forward_downward_messages();
+		result
 	}
 }
 
@@ -331,6 +542,11 @@ construct_runtime!(
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 		Uniques: pallet_uniques,
 		ParasOrigin: origin::{Pallet, Origin},
+		Configuration: configuration::{Pallet, Call, Storage, Config<T>},
+		ParasShared: shared::{Pallet, Call, Storage},
+		Paras: paras::{Pallet, Call, Storage, Event, Config, ValidateUnsigned},
+		Dmp: dmp::{Pallet, Storage},
+		Hrmp: hrmp::{Pallet, Call, Storage, Event<T>, Config},
 		XcmPallet: pallet_xcm::{Pallet, Call, Storage, Event<T>, Origin},
 		MessageQueue: pallet_message_queue::{Pallet, Event<T>},
 	}
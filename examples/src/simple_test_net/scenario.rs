@@ -0,0 +1,880 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Scenarios described in JSON, run against `MockNet`.
+//!
+//! A scenario sets balances over the default genesis of the network, executes steps on its
+//! chains in order, then checks balances, events and received messages:
+//!
+//! ```json
+//! {
+//!   "name": "...",
+//!   "description": "...",
+//!   "genesis": [{ "chain": "relay", "account": "BOB", "amount": 100000000000 }],
+//!   "steps": [{ "chain": "relay", "origin": "BOB", "execute": { "xcm": [...] } }],
+//!   "expect": {
+//!     "balances": [{ "chain": "para-b", "account": "BOB", "asset": 0, "amount": 4999992000 }],
+//!     "events": [{ "chain": "para-b", "contains": ["ExecutedDownward", "Complete"] }],
+//!     "received": [{ "chain": "para-b", "from": "relay", "messages": [[...]] }]
+//!   }
+//! }
+//! ```
+//!
+//! - Chains are `relay`, `para-a`, `para-b`, `para-c`, `asset-hub`, `bridge-hub` and
+//!   `evm-para`. The 20 byte accounts of `evm-para` are not supported, so it only has events and
+//!   received messages.
+//! - Accounts are `ADMIN`, `ALICE`, `BOB` or 32 hex-encoded bytes, or `{ "sovereign": location }`
+//!   in the expected balances. `asset` is the id of an asset class of `pallet_assets`, the
+//!   native token when missing. Amounts are numbers or strings of digits.
+//! - Locations are written like `describe_location` prints them: `Here`, `Parent`,
+//!   `Parent/Parachain(2)`, `Parachain(1)/AccountId32(ALICE)`, with the junctions `Parachain`,
+//!   `AccountId32`, `AccountKey20`, `PalletInstance` and `GeneralIndex`.
+//! - A step has an `origin`, an account, `Here` or `Root`, and one of:
+//!   - `execute`: `{ "xcm": program, "max_weight": weight }`, `pallet_xcm::execute`;
+//!   - `send`: `{ "dest": location, "xcm": program }`, `pallet_xcm::send`, or `send_xcm` from
+//!     the chain itself when the origin is `Here`;
+//!   - `call`: a hex-encoded `RuntimeCall` of the chain, dispatched from an account or `Root`.
+//! - A program is a hex-encoded `VersionedXcm` or a list of instructions, either the name of an
+//!   instruction without operands, like `"ClearOrigin"`, or an object with its name as only key,
+//!   like `{ "WithdrawAsset": [{ "id": "Here", "amount": 6000 }] }`. Operands are named like the
+//!   fields of the instruction. Filters of assets are `"All"` or a list of assets, weights
+//!   `{ "ref_time": n, "proof_size": n }` and weight limits `"Unlimited"` or a weight.
+//! - Events are matched by their `Debug` output, which must contain all the strings of
+//!   `contains`. Received messages are compared to all the messages the chain received from
+//!   `from`, in order.
+
+use core::fmt;
+
+use codec::Decode;
+use frame_support::weights::Weight;
+use frame_system::{pallet_prelude::OriginFor, RawOrigin};
+use polkadot_parachain::primitives::Id as ParaId;
+use serde_json::Value;
+use sp_runtime::{traits::Dispatchable, AccountId32};
+use xcm::{latest::prelude::*, VersionedMultiLocation, VersionedXcm};
+use xcm_simulator::TestExt;
+
+use super::{
+	asset_hub, bridge_hub, default_mock_net,
+	events::{events_since, MockChain},
+	evm_parachain,
+	inspect::{from_hex, Decoded, Kind},
+	mock_msg_queue::pallet as mock_msg_queue,
+	parachain::{self, AssetIdForAssets},
+	relay_chain,
+	sovereign::{sovereign_account_on, AnyAccountId, SovereignAccount},
+	AssetHub, Balance, BridgeHub, Chain, EvmPara, GenesisSpec, MockNet, ParaA, ParaB, ParaC, Relay,
+	TestNetBuilder, ADMIN, ALICE, ALITH, ASSET_HUB_PARA_ID, BOB, BRIDGE_HUB_PARA_ID, EVM_PARA_ID,
+};
+
+/// The maximum weight of the `execute` steps without one.
+const DEFAULT_MAX_WEIGHT: Weight = Weight::from_parts(100_000_000_000, 100_000_000_000);
+
+/// A scenario, see the module documentation for its JSON format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+	pub name: String,
+	/// What the scenario does, for the book to show next to it.
+	pub description: Option<String>,
+	/// Balances set over the default genesis of the network.
+	pub genesis: Vec<AccountBalance>,
+	pub steps: Vec<Step>,
+	pub expect: Expectations,
+}
+
+/// An account of a chain with 32 byte accounts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Account {
+	Id(AccountId32),
+	/// The sovereign account of a location, relative to the chain of the account.
+	Sovereign(MultiLocation),
+}
+
+/// A balance of the native token, or of an asset class of `pallet_assets`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountBalance {
+	pub chain: Chain,
+	pub account: Account,
+	/// `None` for the native token.
+	pub asset: Option<AssetIdForAssets>,
+	pub amount: Balance,
+}
+
+impl AccountBalance {
+	fn account_is_sovereign(&self) -> bool {
+		matches!(self.account, Account::Sovereign(_))
+	}
+}
+
+/// The origin of a step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+	Account(AccountId32),
+	/// The chain itself, for `send` steps.
+	Here,
+	/// For `call` steps.
+	Root,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+	/// `pallet_xcm::execute`
+	Execute { message: Xcm<()>, max_weight: Weight },
+	/// `pallet_xcm::send`, or `send_xcm` from the chain itself.
+	Send { dest: MultiLocation, message: Xcm<()> },
+	/// A SCALE-encoded `RuntimeCall` of the chain.
+	Call(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+	pub chain: Chain,
+	pub origin: Origin,
+	pub action: Action,
+}
+
+/// The messages a chain is expected to have received from another one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Received {
+	pub chain: Chain,
+	pub from: Chain,
+	pub messages: Vec<Xcm<()>>,
+}
+
+/// The state expected after the last step.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Expectations {
+	pub balances: Vec<AccountBalance>,
+	/// Chains and the strings the `Debug` output of one of their events contains.
+	pub events: Vec<(Chain, Vec<String>)>,
+	pub received: Vec<Received>,
+}
+
+#[derive(Debug)]
+pub enum ScenarioError {
+	Json(serde_json::Error),
+	/// The scenario does not follow the format at `path`, like `steps[0].execute.xcm[1]`.
+	Format {
+		path: String,
+		message: String,
+	},
+	/// The step at `index` failed.
+	Step {
+		index: usize,
+		message: String,
+	},
+	/// The expectations that do not hold.
+	Expectations(Vec<String>),
+}
+
+impl fmt::Display for ScenarioError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ScenarioError::Json(error) => write!(f, "invalid JSON: {error}"),
+			ScenarioError::Format { path, message } if path.is_empty() => write!(f, "{message}"),
+			ScenarioError::Format { path, message } => write!(f, "at {path}: {message}"),
+			ScenarioError::Step { index, message } => write!(f, "step {index} failed: {message}"),
+			ScenarioError::Expectations(failures) => {
+				write!(f, "{} expectations do not hold:", failures.len())?;
+				failures.iter().try_for_each(|failure| write!(f, "\n- {failure}"))
+			},
+		}
+	}
+}
+
+impl std::error::Error for ScenarioError {}
+
+/// Parses the scenario `json` and runs it, see `Scenario::run`.
+pub fn run_scenario(json: &str) -> Result<(), ScenarioError> {
+	Scenario::from_json(json)?.run()
+}
+
+impl Scenario {
+	pub fn from_json(json: &str) -> Result<Self, ScenarioError> {
+		let value: Value = serde_json::from_str(json).map_err(ScenarioError::Json)?;
+		let root = Node { value: &value, path: String::new() };
+		root.only(&["name", "description", "genesis", "steps", "expect"])?;
+		let genesis = root.items_of("genesis")?;
+		let genesis = genesis.iter().map(account_balance).collect::<Result<Vec<_>, _>>()?;
+		if let Some(index) = genesis.iter().position(|balance| balance.account_is_sovereign()) {
+			let path = format!("genesis[{index}].account");
+			return Err(invalid(&path, "sovereign accounts are funded by the default genesis"))
+		}
+		let expect = match root.optional("expect") {
+			Some(expect) => expectations(&expect)?,
+			None => Expectations::default(),
+		};
+		let description = match root.optional("description") {
+			Some(description) => Some(description.text()?.to_string()),
+			None => None,
+		};
+		Ok(Scenario {
+			name: root.field("name")?.text()?.to_string(),
+			description,
+			genesis,
+			steps: root.items_of("steps")?.iter().map(step).collect::<Result<_, _>>()?,
+			expect,
+		})
+	}
+
+	/// Resets `MockNet` with the genesis of the scenario, executes its steps, then checks its
+	/// expectations.
+	pub fn run(&self) -> Result<(), ScenarioError> {
+		MockNet::reset_with(self.network());
+		for (index, step) in self.steps.iter().enumerate() {
+			run_step(step).map_err(|message| ScenarioError::Step { index, message })?;
+		}
+		let failures = self.expect.failures();
+		if failures.is_empty() {
+			Ok(())
+		} else {
+			Err(ScenarioError::Expectations(failures))
+		}
+	}
+
+	/// The default network, with the genesis balances of the scenario.
+	fn network(&self) -> TestNetBuilder {
+		let default = default_mock_net();
+		let genesis_of = |chain: Chain, mut spec: GenesisSpec| {
+			for balance in self.genesis.iter().filter(|balance| balance.chain == chain) {
+				if let Account::Id(who) = &balance.account {
+					set_balance(&mut spec, who.clone(), balance.asset, balance.amount);
+				}
+			}
+			spec
+		};
+		let relay_genesis = genesis_of(Chain::Relay, default.relay_genesis());
+		let mut net = TestNetBuilder::new().relay(relay_genesis);
+		for para_id in default.para_ids() {
+			let kind = default.kind_of(para_id).expect("a parachain of the network; qed");
+			let genesis = genesis_of(Chain::Para(para_id), default.para_genesis(para_id));
+			net = net.parachain_with_genesis(para_id, kind, genesis);
+		}
+		net
+	}
+}
+
+impl Expectations {
+	/// The expectations that do not hold, described. Must be called outside of `execute_with`.
+	pub fn failures(&self) -> Vec<String> {
+		let mut failures = Vec::new();
+		for expected in &self.balances {
+			match balance_of(expected) {
+				Ok(amount) if amount == expected.amount => (),
+				Ok(amount) => {
+					let token = match expected.asset {
+						Some(id) => format!("asset {id}"),
+						None => "the native token".to_string(),
+					};
+					failures.push(format!(
+						"{}: the balance of {:?} in {token} is {amount}, expected {}",
+						chain_name(expected.chain),
+						expected.account,
+						expected.amount,
+					))
+				},
+				Err(failure) => failures.push(failure),
+			}
+		}
+		for (chain, contains) in &self.events {
+			let events = events_of(*chain);
+			let matches = |event: &String| contains.iter().all(|part| event.contains(part));
+			if !events.iter().any(matches) {
+				let (name, events) = (chain_name(*chain), events.join("\n  "));
+				failures.push(format!("{name}: no event contains {contains:?}:\n  {events}"));
+			}
+		}
+		for expected in &self.received {
+			match received_on(expected.chain, expected.from) {
+				Ok(messages) if messages == expected.messages => (),
+				Ok(messages) => failures.push(format!(
+					"{}: received {messages:?} from {}, expected {:?}",
+					chain_name(expected.chain),
+					chain_name(expected.from),
+					expected.messages,
+				)),
+				Err(failure) => failures.push(failure),
+			}
+		}
+		failures
+	}
+}
+
+/// Sets the balance of `who` in `spec`, replacing the one of the default genesis.
+fn set_balance(
+	spec: &mut GenesisSpec,
+	who: AccountId32,
+	asset: Option<AssetIdForAssets>,
+	amount: Balance,
+) {
+	match asset {
+		None => {
+			spec.balances.retain(|(account, _)| *account != who);
+			spec.balances.push((who, amount));
+		},
+		Some(id) => {
+			spec.asset_balances
+				.retain(|(asset, account, _)| (*asset, account) != (id, &who));
+			spec.asset_balances.push((id, who, amount));
+		},
+	}
+}
+
+// Running
+
+fn run_step(Step { chain, origin, action }: &Step) -> Result<(), String> {
+	match chain {
+		Chain::Relay => Relay::execute_with(|| dispatch::<relay_chain::Runtime>(origin, action)),
+		Chain::Para(1) => ParaA::execute_with(|| dispatch::<parachain::Runtime>(origin, action)),
+		Chain::Para(2) => ParaB::execute_with(|| dispatch::<parachain::Runtime>(origin, action)),
+		Chain::Para(3) => ParaC::execute_with(|| dispatch::<parachain::Runtime>(origin, action)),
+		Chain::Para(ASSET_HUB_PARA_ID) =>
+			AssetHub::execute_with(|| dispatch::<asset_hub::Runtime>(origin, action)),
+		Chain::Para(BRIDGE_HUB_PARA_ID) =>
+			BridgeHub::execute_with(|| dispatch::<bridge_hub::Runtime>(origin, action)),
+		chain => Err(format!("{} has no steps", chain_name(*chain))),
+	}
+}
+
+fn dispatch<Runtime>(origin: &Origin, action: &Action) -> Result<(), String>
+where
+	Runtime: pallet_xcm::Config + frame_system::Config<AccountId = AccountId32>,
+{
+	let signed =
+		|who: &AccountId32| -> OriginFor<Runtime> { RawOrigin::Signed(who.clone()).into() };
+	match (origin, action) {
+		(Origin::Account(who), Action::Execute { message, max_weight }) => {
+			let message = Box::new(VersionedXcm::V3(Xcm::from(message.clone())));
+			pallet_xcm::Pallet::<Runtime>::execute(signed(who), message, *max_weight)
+				.map(|_| ())
+				.map_err(|error| format!("{:?}", error.error))
+		},
+		(Origin::Account(who), Action::Send { dest, message }) => {
+			let dest = Box::new(VersionedMultiLocation::V3(*dest));
+			let message = Box::new(VersionedXcm::V3(message.clone()));
+			pallet_xcm::Pallet::<Runtime>::send(signed(who), dest, message)
+				.map_err(|error| format!("{error:?}"))
+		},
+		(Origin::Here, Action::Send { dest, message }) =>
+			pallet_xcm::Pallet::<Runtime>::send_xcm(Here, *dest, message.clone())
+				.map(|_| ())
+				.map_err(|error| format!("{error:?}")),
+		(Origin::Account(_) | Origin::Root, Action::Call(encoded)) => {
+			let call = <Runtime as frame_system::Config>::RuntimeCall::decode(&mut &encoded[..])
+				.map_err(|error| format!("cannot decode the call: {error}"))?;
+			let origin = match origin {
+				Origin::Account(who) => signed(who),
+				_ => RawOrigin::Root.into(),
+			};
+			call.dispatch(origin).map(|_| ()).map_err(|error| format!("{:?}", error.error))
+		},
+		(origin, action) => Err(format!("{origin:?} is not an origin of {action:?}")),
+	}
+}
+
+fn balance_of(expected: &AccountBalance) -> Result<Balance, String> {
+	let chain = expected.chain;
+	let who = match &expected.account {
+		Account::Id(who) => who.clone(),
+		Account::Sovereign(location) => match sovereign_account_on(chain, *location) {
+			Ok(SovereignAccount { account: AnyAccountId::Id32(who), .. }) => who,
+			Ok(_) => return Err(format!("{}: 20 byte accounts are unsupported", chain_name(chain))),
+			Err(error) => return Err(format!("{}: {location:?}: {error:?}", chain_name(chain))),
+		},
+	};
+	let who = &who;
+	Ok(match (chain, expected.asset) {
+		(Chain::Relay, None) => Relay::execute_with(|| native_balance::<relay_chain::Runtime>(who)),
+		(Chain::Para(1), None) => ParaA::execute_with(|| native_balance::<parachain::Runtime>(who)),
+		(Chain::Para(2), None) => ParaB::execute_with(|| native_balance::<parachain::Runtime>(who)),
+		(Chain::Para(3), None) => ParaC::execute_with(|| native_balance::<parachain::Runtime>(who)),
+		(Chain::Para(ASSET_HUB_PARA_ID), None) =>
+			AssetHub::execute_with(|| native_balance::<asset_hub::Runtime>(who)),
+		(Chain::Para(BRIDGE_HUB_PARA_ID), None) =>
+			BridgeHub::execute_with(|| native_balance::<bridge_hub::Runtime>(who)),
+		(Chain::Para(1), Some(id)) =>
+			ParaA::execute_with(|| asset_balance::<parachain::Runtime>(id, who)),
+		(Chain::Para(2), Some(id)) =>
+			ParaB::execute_with(|| asset_balance::<parachain::Runtime>(id, who)),
+		(Chain::Para(3), Some(id)) =>
+			ParaC::execute_with(|| asset_balance::<parachain::Runtime>(id, who)),
+		(Chain::Para(ASSET_HUB_PARA_ID), Some(id)) =>
+			AssetHub::execute_with(|| asset_balance::<asset_hub::Runtime>(id, who)),
+		(chain, _) => return Err(format!("{} has no such balances", chain_name(chain))),
+	})
+}
+
+fn native_balance<Runtime>(who: &AccountId32) -> Balance
+where
+	Runtime:
+		pallet_balances::Config<Balance = Balance> + frame_system::Config<AccountId = AccountId32>,
+{
+	pallet_balances::Pallet::<Runtime>::free_balance(who)
+}
+
+fn asset_balance<Runtime>(id: AssetIdForAssets, who: &AccountId32) -> Balance
+where
+	Runtime: pallet_assets::Config<AssetId = AssetIdForAssets, Balance = Balance>
+		+ frame_system::Config<AccountId = AccountId32>,
+{
+	pallet_assets::Pallet::<Runtime>::balance(id, who)
+}
+
+/// The `Debug` output of the events of `chain`.
+fn events_of(chain: Chain) -> Vec<String> {
+	fn events<Ext: MockChain>() -> Vec<String> {
+		events_since::<Ext>(0).iter().map(|event| format!("{event:?}")).collect()
+	}
+	match chain {
+		Chain::Relay => events::<Relay>(),
+		Chain::Para(1) => events::<ParaA>(),
+		Chain::Para(2) => events::<ParaB>(),
+		Chain::Para(3) => events::<ParaC>(),
+		Chain::Para(ASSET_HUB_PARA_ID) => events::<AssetHub>(),
+		Chain::Para(BRIDGE_HUB_PARA_ID) => events::<BridgeHub>(),
+		Chain::Para(EVM_PARA_ID) => events::<EvmPara>(),
+		Chain::Para(para_id) => panic!("No parachain exists with para_id = {para_id}"),
+	}
+}
+
+/// The messages `chain` received from `from`, in order.
+fn received_on(chain: Chain, from: Chain) -> Result<Vec<Xcm<()>>, String> {
+	fn received<Runtime: mock_msg_queue::Config>(from: Chain) -> Vec<Xcm<()>> {
+		let messages = match from {
+			Chain::Relay => mock_msg_queue::Pallet::<Runtime>::received_dmp(),
+			Chain::Para(para_id) => {
+				let sender = ParaId::from(para_id);
+				let received = mock_msg_queue::Pallet::<Runtime>::received_xcmp(sender);
+				received.into_iter().map(|(message, ..)| message).collect()
+			},
+		};
+		messages.into_iter().map(Xcm::<()>::from).collect()
+	}
+	Ok(match chain {
+		Chain::Relay => return Err("relay: upward messages are not recorded".into()),
+		Chain::Para(1) => ParaA::execute_with(|| received::<parachain::Runtime>(from)),
+		Chain::Para(2) => ParaB::execute_with(|| received::<parachain::Runtime>(from)),
+		Chain::Para(3) => ParaC::execute_with(|| received::<parachain::Runtime>(from)),
+		Chain::Para(ASSET_HUB_PARA_ID) =>
+			AssetHub::execute_with(|| received::<asset_hub::Runtime>(from)),
+		Chain::Para(BRIDGE_HUB_PARA_ID) =>
+			BridgeHub::execute_with(|| received::<bridge_hub::Runtime>(from)),
+		Chain::Para(EVM_PARA_ID) =>
+			EvmPara::execute_with(|| received::<evm_parachain::Runtime>(from)),
+		Chain::Para(para_id) => panic!("No parachain exists with para_id = {para_id}"),
+	})
+}
+
+// Parsing
+
+/// A value of the scenario, with its path for the errors.
+struct Node<'a> {
+	value: &'a Value,
+	path: String,
+}
+
+fn invalid(path: &str, message: impl Into<String>) -> ScenarioError {
+	ScenarioError::Format { path: path.to_string(), message: message.into() }
+}
+
+impl<'a> Node<'a> {
+	fn error(&self, message: impl Into<String>) -> ScenarioError {
+		invalid(&self.path, message)
+	}
+
+	fn optional(&self, name: &str) -> Option<Node<'a>> {
+		let path =
+			if self.path.is_empty() { name.to_string() } else { format!("{}.{name}", self.path) };
+		self.value.get(name).map(|value| Node { value, path })
+	}
+
+	fn field(&self, name: &str) -> Result<Node<'a>, ScenarioError> {
+		self.optional(name).ok_or_else(|| self.error(format!("missing `{name}`")))
+	}
+
+	/// Checks that the object has no other fields than `names`, to catch misspelled ones.
+	fn only(&self, names: &[&str]) -> Result<(), ScenarioError> {
+		let object = self.value.as_object().ok_or_else(|| self.error("expected an object"))?;
+		match object.keys().find(|key| !names.contains(&key.as_str())) {
+			Some(key) => Err(self.error(format!("unknown field `{key}`, expected {names:?}"))),
+			None => Ok(()),
+		}
+	}
+
+	fn items(&self) -> Result<Vec<Node<'a>>, ScenarioError> {
+		let items = self.value.as_array().ok_or_else(|| self.error("expected a list"))?;
+		let path = &self.path;
+		Ok(items
+			.iter()
+			.enumerate()
+			.map(|(index, value)| Node { value, path: format!("{path}[{index}]") })
+			.collect())
+	}
+
+	/// The items of the list `name`, none if it is missing.
+	fn items_of(&self, name: &str) -> Result<Vec<Node<'a>>, ScenarioError> {
+		self.optional(name).map_or(Ok(Vec::new()), |list| list.items())
+	}
+
+	fn text(&self) -> Result<&'a str, ScenarioError> {
+		self.value.as_str().ok_or_else(|| self.error("expected a string"))
+	}
+
+	/// A number, or a string of digits for the ones JSON numbers cannot hold.
+	fn number<N: TryFrom<u128>>(&self) -> Result<N, ScenarioError> {
+		let number = match self.value {
+			Value::Number(number) => number.as_u64().map(u128::from),
+			Value::String(digits) => digits.parse().ok(),
+			_ => None,
+		};
+		let number = number.ok_or_else(|| self.error("expected an unsigned integer"))?;
+		N::try_from(number).map_err(|_| self.error(format!("{number} is too large")))
+	}
+
+	fn parse<T>(&self, parse: impl FnOnce(&str) -> Result<T, String>) -> Result<T, ScenarioError> {
+		parse(self.text()?).map_err(|message| self.error(message))
+	}
+
+	fn bytes(&self) -> Result<Vec<u8>, ScenarioError> {
+		from_hex(self.text()?).map_err(|error| self.error(error.to_string()))
+	}
+}
+
+fn account_balance(node: &Node) -> Result<AccountBalance, ScenarioError> {
+	node.only(&["chain", "account", "asset", "amount"])?;
+	Ok(AccountBalance {
+		chain: account_chain(&node.field("chain")?)?,
+		account: account(&node.field("account")?)?,
+		asset: node.optional("asset").map(|asset| asset.number()).transpose()?,
+		amount: node.field("amount")?.number()?,
+	})
+}
+
+fn step(node: &Node) -> Result<Step, ScenarioError> {
+	node.only(&["chain", "origin", "execute", "send", "call"])?;
+	let chain = account_chain(&node.field("chain")?)?;
+	let origin = node.field("origin")?;
+	let origin = match origin.text()? {
+		"Here" => Origin::Here,
+		"Root" => Origin::Root,
+		_ => Origin::Account(origin.parse(account_id)?),
+	};
+	let action = if let Some(execute) = node.optional("execute") {
+		execute.only(&["xcm", "max_weight"])?;
+		let max_weight = execute.optional("max_weight").map(|weight| weight_of(&weight));
+		Action::Execute {
+			message: xcm(&execute.field("xcm")?)?,
+			max_weight: max_weight.transpose()?.unwrap_or(DEFAULT_MAX_WEIGHT),
+		}
+	} else if let Some(send) = node.optional("send") {
+		send.only(&["dest", "xcm"])?;
+		let dest = send.field("dest")?.parse(location)?;
+		Action::Send { dest, message: xcm(&send.field("xcm")?)? }
+	} else if let Some(call) = node.optional("call") {
+		Action::Call(call.bytes()?)
+	} else {
+		return Err(node.error("expected one of `execute`, `send` or `call`"))
+	};
+	Ok(Step { chain, origin, action })
+}
+
+fn expectations(node: &Node) -> Result<Expectations, ScenarioError> {
+	node.only(&["balances", "events", "received"])?;
+	let (balances, events) = (node.items_of("balances")?, node.items_of("events")?);
+	let received = node.items_of("received")?;
+	Ok(Expectations {
+		balances: balances.iter().map(account_balance).collect::<Result<_, _>>()?,
+		events: events.iter().map(expected_event).collect::<Result<_, _>>()?,
+		received: received.iter().map(expected_received).collect::<Result<_, _>>()?,
+	})
+}
+
+fn expected_event(node: &Node) -> Result<(Chain, Vec<String>), ScenarioError> {
+	node.only(&["chain", "contains"])?;
+	let contains = node.field("contains")?;
+	let contains = match contains.value {
+		Value::String(part) => vec![part.clone()],
+		_ => {
+			let parts = contains.items()?;
+			parts
+				.iter()
+				.map(|part| part.text().map(str::to_string))
+				.collect::<Result<_, _>>()?
+		},
+	};
+	Ok((chain(&node.field("chain")?)?, contains))
+}
+
+fn expected_received(node: &Node) -> Result<Received, ScenarioError> {
+	node.only(&["chain", "from", "messages"])?;
+	Ok(Received {
+		chain: chain(&node.field("chain")?)?,
+		from: chain(&node.field("from")?)?,
+		messages: node.field("messages")?.items()?.iter().map(xcm).collect::<Result<_, _>>()?,
+	})
+}
+
+fn chain(node: &Node) -> Result<Chain, ScenarioError> {
+	node.parse(|name| match name {
+		"relay" => Ok(Chain::Relay),
+		"para-a" => Ok(Chain::Para(1)),
+		"para-b" => Ok(Chain::Para(2)),
+		"para-c" => Ok(Chain::Para(3)),
+		"asset-hub" => Ok(Chain::Para(ASSET_HUB_PARA_ID)),
+		"bridge-hub" => Ok(Chain::Para(BRIDGE_HUB_PARA_ID)),
+		"evm-para" => Ok(Chain::Para(EVM_PARA_ID)),
+		_ => Err(format!(
+			"unknown chain `{name}`, expected relay, para-a, para-b, para-c, asset-hub, \
+			bridge-hub or evm-para"
+		)),
+	})
+}
+
+/// A chain with 32 byte accounts.
+fn account_chain(node: &Node) -> Result<Chain, ScenarioError> {
+	match chain(node)? {
+		Chain::Para(EVM_PARA_ID) =>
+			Err(node.error("the 20 byte accounts of evm-para are not supported")),
+		chain => Ok(chain),
+	}
+}
+
+/// The name of `chain` in scenarios.
+fn chain_name(chain: Chain) -> String {
+	match chain {
+		Chain::Relay => "relay".into(),
+		Chain::Para(1) => "para-a".into(),
+		Chain::Para(2) => "para-b".into(),
+		Chain::Para(3) => "para-c".into(),
+		Chain::Para(ASSET_HUB_PARA_ID) => "asset-hub".into(),
+		Chain::Para(BRIDGE_HUB_PARA_ID) => "bridge-hub".into(),
+		Chain::Para(EVM_PARA_ID) => "evm-para".into(),
+		Chain::Para(para_id) => format!("Para({para_id})"),
+	}
+}
+
+fn account(node: &Node) -> Result<Account, ScenarioError> {
+	match node.value {
+		Value::Object(_) => {
+			node.only(&["sovereign"])?;
+			Ok(Account::Sovereign(node.field("sovereign")?.parse(location)?))
+		},
+		_ => node.parse(account_id).map(Account::Id),
+	}
+}
+
+fn account_id(text: &str) -> Result<AccountId32, String> {
+	match text {
+		"ADMIN" => Ok(ADMIN),
+		"ALICE" => Ok(ALICE),
+		"BOB" => Ok(BOB),
+		_ => from_hex(text)
+			.ok()
+			.and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+			.map(AccountId32::new)
+			.ok_or_else(|| format!("expected ADMIN, ALICE, BOB or 32 hex-encoded bytes: `{text}`")),
+	}
+}
+
+fn account_key(text: &str) -> Result<[u8; 20], String> {
+	match text {
+		"ALITH" => Ok(ALITH.0),
+		_ => from_hex(text)
+			.ok()
+			.and_then(|bytes| <[u8; 20]>::try_from(bytes).ok())
+			.ok_or_else(|| format!("expected ALITH or 20 hex-encoded bytes: `{text}`")),
+	}
+}
+
+/// A location written like `describe_location` prints it, e.g. `Parent/Parachain(2)`.
+fn location(text: &str) -> Result<MultiLocation, String> {
+	let mut location = MultiLocation::here();
+	if text == "Here" {
+		return Ok(location)
+	}
+	for part in text.split('/') {
+		match part {
+			"Parent" if location.interior == Here => location.parents += 1,
+			"Parent" => return Err(format!("`Parent` after a junction in `{text}`")),
+			part => location
+				.push_interior(junction(part)?)
+				.map_err(|_| format!("too many junctions in `{text}`"))?,
+		}
+	}
+	Ok(location)
+}
+
+fn junction(text: &str) -> Result<Junction, String> {
+	let (name, operand) = text
+		.strip_suffix(')')
+		.and_then(|text| text.split_once('('))
+		.ok_or_else(|| format!("expected a junction like `Parachain(1)`, found `{text}`"))?;
+	let not_a_number = |_| format!("`{operand}` is not a number in `{text}`");
+	match name {
+		"Parachain" => operand.parse().map(Parachain).map_err(not_a_number),
+		"PalletInstance" => operand.parse().map(PalletInstance).map_err(not_a_number),
+		"GeneralIndex" => operand.parse().map(GeneralIndex).map_err(not_a_number),
+		"AccountId32" =>
+			Ok(Junction::AccountId32 { network: None, id: account_id(operand)?.into() }),
+		"AccountKey20" => Ok(AccountKey20 { network: None, key: account_key(operand)? }),
+		_ => Err(format!("unknown junction `{name}` in `{text}`")),
+	}
+}
+
+fn asset(node: &Node) -> Result<MultiAsset, ScenarioError> {
+	node.only(&["id", "amount"])?;
+	let amount: u128 = node.field("amount")?.number()?;
+	Ok((node.field("id")?.parse(location)?, amount).into())
+}
+
+fn assets(node: &Node) -> Result<MultiAssets, ScenarioError> {
+	let assets = node.items()?.iter().map(asset).collect::<Result<Vec<_>, _>>()?;
+	Ok(assets.into())
+}
+
+fn filter(node: &Node) -> Result<MultiAssetFilter, ScenarioError> {
+	match node.value {
+		Value::String(all) if all == "All" => Ok(Wild(All)),
+		_ => assets(node).map(Definite),
+	}
+}
+
+fn weight_of(node: &Node) -> Result<Weight, ScenarioError> {
+	node.only(&["ref_time", "proof_size"])?;
+	let ref_time = node.field("ref_time")?.number()?;
+	Ok(Weight::from_parts(ref_time, node.field("proof_size")?.number()?))
+}
+
+/// An optional weight limit, `Unlimited` when missing.
+fn weight_limit(node: Option<Node>) -> Result<WeightLimit, ScenarioError> {
+	match node {
+		None => Ok(Unlimited),
+		Some(node) if node.value == "Unlimited" => Ok(Unlimited),
+		Some(node) => weight_of(&node).map(Limited),
+	}
+}
+
+/// A list of instructions, or a hex-encoded `VersionedXcm`.
+fn xcm(node: &Node) -> Result<Xcm<()>, ScenarioError> {
+	if node.value.is_string() {
+		return match Decoded::decode(Kind::Xcm, &node.bytes()?) {
+			Ok(Decoded::Xcm(xcm)) =>
+				Xcm::try_from(xcm).map_err(|_| node.error("cannot be converted to version 3")),
+			Ok(_) => unreachable!("decoded as `Kind::Xcm`; qed"),
+			Err(error) => Err(node.error(error.to_string())),
+		}
+	}
+	Ok(Xcm(node.items()?.iter().map(instruction).collect::<Result<_, _>>()?))
+}
+
+fn instruction(node: &Node) -> Result<Instruction<()>, ScenarioError> {
+	static NO_OPERANDS: Value = Value::Null;
+	let (name, operands) = match node.value {
+		Value::String(name) => {
+			let operands = Node { value: &NO_OPERANDS, path: node.path.clone() };
+			(name.as_str(), operands)
+		},
+		Value::Object(object) if object.len() == 1 => {
+			let (name, _) = object.iter().next().expect("the object has one field; qed");
+			(name.as_str(), node.field(name)?)
+		},
+		_ => {
+			let message = "expected the name of an instruction, or an object with the name of an \
+				instruction as only field";
+			return Err(node.error(message))
+		},
+	};
+	let operands = &operands;
+	let field = |name| operands.field(name);
+	Ok(match name {
+		"WithdrawAsset" => WithdrawAsset(assets(operands)?),
+		"ReserveAssetDeposited" => ReserveAssetDeposited(assets(operands)?),
+		"ReceiveTeleportedAsset" => ReceiveTeleportedAsset(assets(operands)?),
+		"ClearOrigin" => ClearOrigin,
+		"BuyExecution" => BuyExecution {
+			fees: asset(&field("fees")?)?,
+			weight_limit: weight_limit(operands.optional("weight_limit"))?,
+		},
+		"UnpaidExecution" => UnpaidExecution {
+			weight_limit: weight_limit(operands.optional("weight_limit"))?,
+			check_origin: match operands.optional("check_origin") {
+				Some(origin) => Some(origin.parse(location)?),
+				None => None,
+			},
+		},
+		"DepositAsset" => DepositAsset {
+			assets: filter(&field("assets")?)?,
+			beneficiary: field("beneficiary")?.parse(location)?,
+		},
+		"DepositReserveAsset" => DepositReserveAsset {
+			assets: filter(&field("assets")?)?,
+			dest: field("dest")?.parse(location)?,
+			xcm: xcm(&field("xcm")?)?,
+		},
+		"InitiateReserveWithdraw" => InitiateReserveWithdraw {
+			assets: filter(&field("assets")?)?,
+			reserve: field("reserve")?.parse(location)?,
+			xcm: xcm(&field("xcm")?)?,
+		},
+		"InitiateTeleport" => InitiateTeleport {
+			assets: filter(&field("assets")?)?,
+			dest: field("dest")?.parse(location)?,
+			xcm: xcm(&field("xcm")?)?,
+		},
+		"TransferAsset" => TransferAsset {
+			assets: assets(&field("assets")?)?,
+			beneficiary: field("beneficiary")?.parse(location)?,
+		},
+		"TransferReserveAsset" => TransferReserveAsset {
+			assets: assets(&field("assets")?)?,
+			dest: field("dest")?.parse(location)?,
+			xcm: xcm(&field("xcm")?)?,
+		},
+		"DescendOrigin" => match operands.parse(location)? {
+			MultiLocation { parents: 0, interior } => DescendOrigin(interior),
+			_ => return Err(operands.error("expected an interior location")),
+		},
+		"Transact" => Transact {
+			origin_kind: field("origin_kind")?.parse(origin_kind)?,
+			require_weight_at_most: weight_of(&field("require_weight_at_most")?)?,
+			call: field("call")?.bytes()?.into(),
+		},
+		"RefundSurplus" => RefundSurplus,
+		"SetErrorHandler" => SetErrorHandler(xcm(operands)?),
+		"SetAppendix" => SetAppendix(xcm(operands)?),
+		"ClearError" => ClearError,
+		"ExpectAsset" => ExpectAsset(assets(operands)?),
+		"Trap" => Trap(operands.number()?),
+		"SetTopic" => SetTopic(
+			<[u8; 32]>::try_from(operands.bytes()?)
+				.map_err(|_| operands.error("expected 32 hex-encoded bytes"))?,
+		),
+		"ClearTopic" => ClearTopic,
+		_ => return Err(node.error(format!("unsupported instruction `{name}`"))),
+	})
+}
+
+fn origin_kind(text: &str) -> Result<OriginKind, String> {
+	match text {
+		"Native" => Ok(OriginKind::Native),
+		"SovereignAccount" => Ok(OriginKind::SovereignAccount),
+		"Superuser" => Ok(OriginKind::Superuser),
+		"Xcm" => Ok(OriginKind::Xcm),
+		_ => Err(format!(
+			"unknown origin kind `{text}`, expected Native, SovereignAccount, Superuser or Xcm"
+		)),
+	}
+}
@@ -0,0 +1,200 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Sovereign accounts of any location, on any chain of the network.
+//!
+//! `sovereign_account_on` evaluates the `SovereignAccountOf` of a chain within its
+//! externalities, like its XCM executor does, so it must not be called within `execute_with` of
+//! that chain. `sovereign_account_with` derives accounts the same way without entering the
+//! externalities, for the sovereign account helpers of the network.
+
+use codec::{Decode, Encode};
+use sp_core::H160;
+use sp_runtime::{traits::Get, AccountId32};
+use xcm::latest::prelude::*;
+use xcm_executor::traits::Convert;
+use xcm_simulator::TestExt;
+
+use super::{
+	accounts::{self, RecordDerivations},
+	asset_hub, bridge_hub, evm_parachain,
+	locations::location_on,
+	parachain, relay_chain, AssetHub, BridgeHub, Chain, EvmPara, ParaA, ParaB, ParaC, Relay,
+	ASSET_HUB_PARA_ID, BRIDGE_HUB_PARA_ID, EVM_PARA_ID,
+};
+
+/// An account of any runtime mock of the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnyAccountId {
+	Id32(AccountId32),
+	Key20(H160),
+}
+
+impl From<AccountId32> for AnyAccountId {
+	fn from(account: AccountId32) -> Self {
+		AnyAccountId::Id32(account)
+	}
+}
+
+impl From<H160> for AnyAccountId {
+	fn from(account: H160) -> Self {
+		AnyAccountId::Key20(account)
+	}
+}
+
+/// The sovereign account of a location on a chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SovereignAccount {
+	/// The location, relative to the chain.
+	pub location: MultiLocation,
+	pub account: AnyAccountId,
+	/// The converter of the `SovereignAccountOf` tuple of the chain that matched the location,
+	/// like `SiblingParachainConvertsVia`.
+	pub converter: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SovereignAccountError {
+	/// The location cannot be expressed relative to the chain.
+	Unreachable,
+	/// No converter of the `SovereignAccountOf` of the chain matches the location.
+	NoConverter(MultiLocation),
+}
+
+/// The sovereign account on `chain` of `location`, relative to `chain`.
+pub fn sovereign_account_on(
+	chain: Chain,
+	location: impl Into<MultiLocation>,
+) -> Result<SovereignAccount, SovereignAccountError> {
+	sovereign_account_seen_from(chain, chain, location)
+}
+
+/// The sovereign account on `chain` of `location`, relative to the chain `from`.
+///
+/// `location` is reanchored to `chain` with the `UniversalLocation` of both chains, e.g. the
+/// account `AccountId32(ALICE)` of the relay chain is `(Parent, AccountId32(ALICE))` on a
+/// parachain.
+pub fn sovereign_account_seen_from(
+	chain: Chain,
+	from: Chain,
+	location: impl Into<MultiLocation>,
+) -> Result<SovereignAccount, SovereignAccountError> {
+	let location =
+		location_on(chain, from, location).map_err(|()| SovereignAccountError::Unreachable)?;
+	let (account, converter) =
+		convert_on(chain, &location).ok_or(SovereignAccountError::NoConverter(location))?;
+	Ok(SovereignAccount { location, account, converter })
+}
+
+fn convert_on(chain: Chain, location: &MultiLocation) -> Option<(AnyAccountId, &'static str)> {
+	match chain {
+		Chain::Relay => convert_with::<Relay, relay_chain::SovereignAccountOf, _>(location),
+		Chain::Para(1) => convert_with::<ParaA, parachain::SovereignAccountOf, _>(location),
+		Chain::Para(2) => convert_with::<ParaB, parachain::SovereignAccountOf, _>(location),
+		Chain::Para(3) => convert_with::<ParaC, parachain::SovereignAccountOf, _>(location),
+		Chain::Para(ASSET_HUB_PARA_ID) =>
+			convert_with::<AssetHub, asset_hub::SovereignAccountOf, _>(location),
+		Chain::Para(BRIDGE_HUB_PARA_ID) =>
+			convert_with::<BridgeHub, bridge_hub::SovereignAccountOf, _>(location),
+		Chain::Para(EVM_PARA_ID) =>
+			convert_with::<EvmPara, evm_parachain::SovereignAccountOf, _>(location),
+		Chain::Para(para_id) => panic!("No parachain exists with para_id = {para_id}"),
+	}
+}
+
+fn convert_with<Ext, Converters, AccountId>(
+	location: &MultiLocation,
+) -> Option<(AnyAccountId, &'static str)>
+where
+	Ext: TestExt,
+	Converters: NamedConverters<AccountId>,
+	AccountId: Into<AnyAccountId>,
+{
+	Ext::execute_with(|| Converters::convert_named(location))
+		.map(|(account, converter)| (account.into(), converter))
+}
+
+/// The sovereign account of `location`, relative to a chain, with `Converters`, the
+/// `SovereignAccountOf` of that chain.
+///
+/// Unlike `sovereign_account_on`, it does not enter the externalities of the chain, so it can be
+/// called within `execute_with`. Since it cannot tell which chain it derives for, the account is
+/// not recorded in the registry of derivations. Panics if no converter matches `location`.
+pub fn sovereign_account_with<Converters, AccountId>(
+	location: impl Into<MultiLocation>,
+) -> AccountId
+where
+	Converters: NamedConverters<AccountId>,
+{
+	let location = location.into();
+	match accounts::without_recording(|| Converters::convert_named(&location)) {
+		Some((account, _)) => account,
+		None => panic!("No converter matches {location:?}"),
+	}
+}
+
+/// Converters of a `SovereignAccountOf` tuple, which can tell the converter that matched.
+pub trait NamedConverters<AccountId> {
+	/// The account `location` converts into, and the name of the first converter that matches
+	/// it, the one the tuple converts with.
+	fn convert_named(location: &MultiLocation) -> Option<(AccountId, &'static str)>;
+}
+
+impl<AccountId, Converters, ThisChain> NamedConverters<AccountId>
+	for RecordDerivations<Converters, ThisChain>
+where
+	AccountId: Encode + Decode + Clone,
+	Converters: NamedConverters<AccountId> + Convert<MultiLocation, AccountId>,
+	ThisChain: Get<Chain>,
+{
+	fn convert_named(location: &MultiLocation) -> Option<(AccountId, &'static str)> {
+		let (_, converter) = Converters::convert_named(location)?;
+		// Converts again to record the derivation
+		let account = <Self as Convert<MultiLocation, AccountId>>::convert_ref(location).ok()?;
+		Some((account, converter))
+	}
+}
+
+macro_rules! impl_named_converters {
+	($($converter:ident),+) => {
+		impl<AccountId, $($converter: Convert<MultiLocation, AccountId>),+>
+			NamedConverters<AccountId> for ($($converter,)+)
+		{
+			fn convert_named(location: &MultiLocation) -> Option<(AccountId, &'static str)> {
+				$(
+					if let Ok(account) = $converter::convert_ref(location) {
+						return Some((account, converter_name::<$converter>()))
+					}
+				)+
+				None
+			}
+		}
+	};
+}
+
+impl_named_converters!(A);
+impl_named_converters!(A, B);
+impl_named_converters!(A, B, C);
+impl_named_converters!(A, B, C, D);
+impl_named_converters!(A, B, C, D, E);
+impl_named_converters!(A, B, C, D, E, F);
+
+/// The name of the type `Converter`, without its path and generic parameters.
+fn converter_name<Converter>() -> &'static str {
+	let name = core::any::type_name::<Converter>();
+	let name = name.split('<').next().unwrap_or(name);
+	name.rsplit("::").next().unwrap_or(name)
+}
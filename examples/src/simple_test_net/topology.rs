@@ -0,0 +1,450 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Declarative description of the chains that make up a test network.
+//!
+//! The chain types themselves are still declared with `decl_test_parachain!` and
+//! `decl_test_network!`. The builder provides their `new_ext`, so that the genesis of every
+//! chain, the list of its siblings and the funding of sovereign accounts follow from one
+//! description of the network instead of being written out per chain:
+//!
+//! ```ignore
+//! fn my_net() -> TestNetBuilder {
+//! 	TestNetBuilder::new()
+//! 		.relay(GenesisSpec::relay_default())
+//! 		.parachain(2000, ParaKind::Parachain)
+//! 		.parachain_with_genesis(
+//! 			2001,
+//! 			ParaKind::Parachain,
+//! 			GenesisSpec::new().balance(ALICE, INITIAL_BALANCE),
+//! 		)
+//! }
+//!
+//! decl_test_parachain! {
+//! 	pub struct Para2000 {
+//! 		Runtime = parachain::Runtime,
+//! 		XcmpMessageHandler = parachain::MsgQueue,
+//! 		DmpMessageHandler = parachain::MsgQueue,
+//! 		new_ext = my_net().para_ext(2000),
+//! 	}
+//! }
+//! ```
+
+use frame_support::{sp_tracing, traits::GenesisBuild};
+use polkadot_parachain::primitives::{HeadData, ValidationCode};
+use polkadot_runtime_parachains::{configuration, paras};
+use xcm::latest::prelude::*;
+use xcm_executor::traits::Convert;
+
+use super::{
+	accounts, asset_hub,
+	bridge::ThisNetwork,
+	bridge_hub,
+	clock::{self, BlockStepping},
+	evm_parachain, faults,
+	genesis::{assimilate_assets, assimilate_balances, create_collections, GenesisSpec},
+	parachain, relay_chain, trace, versions, RELAY_NETWORK,
+};
+
+/// The runtime mock a parachain of the network is built from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParaKind {
+	/// The general purpose `parachain` runtime mock.
+	Parachain,
+	/// The `asset_hub` runtime mock, a system parachain using the relay chain token as its
+	/// native token.
+	AssetHub,
+	/// The `bridge_hub` runtime mock, a system parachain exporting messages to the other network
+	/// of the simulator.
+	BridgeHub,
+	/// The `evm_parachain` runtime mock, a parachain with 20 byte accounts.
+	Evm,
+}
+
+/// A chain of the network, identified the way the other chains of the network see it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chain {
+	Relay,
+	Para(u32),
+}
+
+impl Chain {
+	/// The chain `location` points to, relative to this chain, if it is the relay chain or a
+	/// parachain.
+	pub fn resolve(&self, location: &MultiLocation) -> Option<Chain> {
+		match (self, location.parents, location.interior) {
+			(Chain::Relay, 0, Here) => Some(Chain::Relay),
+			(Chain::Relay, 0, X1(Parachain(para_id))) => Some(Chain::Para(para_id)),
+			(Chain::Para(para_id), 0, Here) => Some(Chain::Para(*para_id)),
+			(Chain::Para(_), 1, Here) => Some(Chain::Relay),
+			(Chain::Para(_), 1, X1(Parachain(para_id))) => Some(Chain::Para(para_id)),
+			_ => None,
+		}
+	}
+
+	/// The location of this chain, relative to the chain `from`.
+	pub fn location_from(&self, from: Chain) -> MultiLocation {
+		match (from, self) {
+			(Chain::Relay, Chain::Relay) => Here.into(),
+			(Chain::Relay, Chain::Para(para_id)) => Parachain(*para_id).into(),
+			(Chain::Para(from), Chain::Para(para_id)) if from == *para_id => Here.into(),
+			(Chain::Para(_), Chain::Relay) => Parent.into(),
+			(Chain::Para(_), Chain::Para(para_id)) => (Parent, Parachain(*para_id)).into(),
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct ParaSpec {
+	para_id: u32,
+	kind: ParaKind,
+	/// `None` for the default genesis of `kind`.
+	genesis: Option<GenesisSpec>,
+}
+
+/// Builder for the genesis of a relay chain and any number of parachains connected to it.
+#[derive(Clone, Debug, Default)]
+pub struct TestNetBuilder {
+	/// `None` for `RELAY_NETWORK`.
+	network: Option<NetworkId>,
+	/// `None` for `GenesisSpec::relay_default()`.
+	relay_genesis: Option<GenesisSpec>,
+	parachains: Vec<ParaSpec>,
+	/// `None` for channels between all parachains.
+	hrmp_channels: Option<Vec<(u32, u32)>>,
+	block_stepping: bool,
+}
+
+impl TestNetBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The consensus system of the network, `RELAY_NETWORK` if not set.
+	pub fn network(mut self, network: NetworkId) -> Self {
+		self.network = Some(network);
+		self
+	}
+
+	/// Genesis of the relay chain, `GenesisSpec::relay_default()` if not set.
+	pub fn relay(mut self, genesis: GenesisSpec) -> Self {
+		self.relay_genesis = Some(genesis);
+		self
+	}
+
+	/// Adds a parachain with the given id, built from the runtime mock of `kind` with the default
+	/// genesis of that runtime mock.
+	pub fn parachain(self, para_id: u32, kind: ParaKind) -> Self {
+		self.add_parachain(ParaSpec { para_id, kind, genesis: None })
+	}
+
+	/// Adds a parachain with the given id, built from the runtime mock of `kind` with `genesis`.
+	pub fn parachain_with_genesis(
+		self,
+		para_id: u32,
+		kind: ParaKind,
+		genesis: GenesisSpec,
+	) -> Self {
+		self.add_parachain(ParaSpec { para_id, kind, genesis: Some(genesis) })
+	}
+
+	fn add_parachain(mut self, para: ParaSpec) -> Self {
+		assert!(
+			self.kind_of(para.para_id).is_none(),
+			"Parachain with para_id = {} is declared twice",
+			para.para_id
+		);
+		self.parachains.push(para);
+		self
+	}
+
+	/// Opens an HRMP channel from `sender` to `recipient` at genesis.
+	///
+	/// Without any declared channel, channels between all parachains are opened.
+	pub fn hrmp_channel(mut self, sender: u32, recipient: u32) -> Self {
+		self.hrmp_channels.get_or_insert_with(Vec::new).push((sender, recipient));
+		self
+	}
+
+	/// Opens no HRMP channel at genesis.
+	pub fn without_hrmp_channels(mut self) -> Self {
+		self.hrmp_channels = Some(Vec::new());
+		self
+	}
+
+	/// Delivers the messages sent in a relay block at the next one, see `MockNet::advance_blocks`.
+	///
+	/// Without it, messages are delivered as soon as the `execute_with` sending them returns.
+	pub fn block_stepping(mut self) -> Self {
+		self.block_stepping = true;
+		self
+	}
+
+	/// The HRMP channels opened at genesis, as `(sender, recipient)`.
+	pub fn hrmp_channels(&self) -> Vec<(u32, u32)> {
+		self.hrmp_channels.clone().unwrap_or_else(|| {
+			let para_ids = self.para_ids();
+			para_ids
+				.iter()
+				.flat_map(|&sender| self.siblings(sender).into_iter().map(move |r| (sender, r)))
+				.collect()
+		})
+	}
+
+	/// The consensus system of the network.
+	pub fn network_id(&self) -> NetworkId {
+		self.network.unwrap_or(RELAY_NETWORK)
+	}
+
+	/// The ids of all parachains in the network, in declaration order.
+	pub fn para_ids(&self) -> Vec<u32> {
+		self.parachains.iter().map(|para| para.para_id).collect()
+	}
+
+	/// The runtime mock the parachain is built from, if it is part of the network.
+	pub fn kind_of(&self, para_id: u32) -> Option<ParaKind> {
+		self.para(para_id).map(|para| para.kind)
+	}
+
+	fn para(&self, para_id: u32) -> Option<&ParaSpec> {
+		self.parachains.iter().find(|para| para.para_id == para_id)
+	}
+
+	/// The ids of all parachains in the network except `para_id`.
+	pub fn siblings(&self, para_id: u32) -> Vec<u32> {
+		self.para_ids().into_iter().filter(|&id| id != para_id).collect()
+	}
+
+	/// Genesis of the relay chain.
+	pub fn relay_genesis(&self) -> GenesisSpec {
+		self.relay_genesis.clone().unwrap_or_else(GenesisSpec::relay_default)
+	}
+
+	/// Genesis of the parachain `para_id`.
+	///
+	/// Panics if the parachain is not part of the network.
+	pub fn para_genesis(&self, para_id: u32) -> GenesisSpec {
+		let para = self
+			.para(para_id)
+			.unwrap_or_else(|| panic!("No parachain exists with para_id = {para_id}"));
+		para.genesis.clone().unwrap_or_else(|| match para.kind {
+			ParaKind::Parachain => GenesisSpec::parachain_default(&self.siblings(para_id)),
+			ParaKind::AssetHub => GenesisSpec::asset_hub_default(),
+			ParaKind::BridgeHub => GenesisSpec::bridge_hub_default(),
+			ParaKind::Evm => GenesisSpec::evm_parachain_default(),
+		})
+	}
+
+	/// Externalities of the parachain `para_id`.
+	///
+	/// Panics if the parachain is not part of the network.
+	pub fn para_ext(&self, para_id: u32) -> sp_io::TestExternalities {
+		let genesis = self.para_genesis(para_id);
+		let siblings = self.siblings(para_id);
+		let mut ext = match self.kind_of(para_id) {
+			Some(ParaKind::Parachain) => parachain_ext(para_id, &siblings, &genesis),
+			Some(ParaKind::AssetHub) => asset_hub_ext(para_id, &siblings, &genesis),
+			Some(ParaKind::BridgeHub) => bridge_hub_ext(para_id, &siblings, &genesis),
+			Some(ParaKind::Evm) => evm_parachain_ext(para_id, &siblings, &genesis),
+			None => unreachable!("para_genesis panics for unknown parachains; qed"),
+		};
+		ext.execute_with(|| {
+			ThisNetwork::set(self.network_id());
+			BlockStepping::set(self.block_stepping);
+		});
+		ext
+	}
+
+	/// Externalities of the relay chain.
+	pub fn relay_ext(&self) -> sp_io::TestExternalities {
+		use relay_chain::{
+			Hrmp, Runtime, RuntimeOrigin, SovereignAccountOf, System, HRMP_CHANNEL_MAX_CAPACITY,
+			HRMP_CHANNEL_MAX_MESSAGE_SIZE,
+		};
+
+		let genesis = self.relay_genesis();
+		let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+		let (balances, _) = accounts::deriving_on(Chain::Relay, || {
+			genesis.resolve_balances(0, &self.para_ids(), |location| {
+				SovereignAccountOf::convert(location).unwrap()
+			})
+		});
+		assimilate_balances::<Runtime>(&mut t, balances);
+
+		configuration::GenesisConfig::<Runtime> { config: relay_chain::host_configuration() }
+			.assimilate_storage(&mut t)
+			.unwrap();
+
+		let paras = self
+			.para_ids()
+			.into_iter()
+			.map(|para_id| {
+				let args = paras::ParaGenesisArgs {
+					genesis_head: HeadData(Vec::new()),
+					validation_code: ValidationCode(vec![1]),
+					para_kind: paras::ParaKind::Parachain,
+				};
+				(para_id.into(), args)
+			})
+			.collect();
+		<paras::GenesisConfig as GenesisBuild<Runtime>>::assimilate_storage(
+			&paras::GenesisConfig { paras },
+			&mut t,
+		)
+		.unwrap();
+
+		clock::reset_clock(self.network_id());
+		trace::reset_trace(self.network_id());
+		faults::reset_faults(self.network_id());
+		versions::reset_wrapped(self.network_id());
+		let mut ext = sp_io::TestExternalities::new(t);
+		ext.execute_with(|| {
+			System::set_block_number(1);
+			ThisNetwork::set(self.network_id());
+			BlockStepping::set(self.block_stepping);
+			versions::set_xcm_versions::<Runtime>(genesis.xcm_version, genesis.safe_xcm_version);
+			create_collections::<Runtime>(&genesis.collections);
+
+			for (sender, recipient) in self.hrmp_channels() {
+				Hrmp::force_open_hrmp_channel(
+					RuntimeOrigin::root(),
+					sender.into(),
+					recipient.into(),
+					HRMP_CHANNEL_MAX_CAPACITY,
+					HRMP_CHANNEL_MAX_MESSAGE_SIZE,
+				)
+				.unwrap();
+			}
+			relay_chain::enact_hrmp_requests();
+			// The notifications of channels open from genesis are not delivered
+			relay_chain::discard_downward_messages();
+		});
+		ext
+	}
+}
+
+fn parachain_ext(
+	para_id: u32,
+	other_para_ids: &[u32],
+	genesis: &GenesisSpec,
+) -> sp_io::TestExternalities {
+	use parachain::{MsgQueue, Runtime, SovereignAccountOf, System};
+
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	let (balances, asset_balances) = accounts::deriving_on(Chain::Para(para_id), || {
+		genesis.resolve_balances(1, other_para_ids, |location| {
+			SovereignAccountOf::convert(location).unwrap()
+		})
+	});
+	assimilate_balances::<Runtime>(&mut t, balances);
+	assimilate_assets::<Runtime>(&mut t, &genesis.assets, asset_balances);
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		sp_tracing::try_init_simple();
+		System::set_block_number(1);
+		MsgQueue::set_para_id(para_id.into());
+		versions::set_xcm_versions::<Runtime>(genesis.xcm_version, genesis.safe_xcm_version);
+		create_collections::<Runtime>(&genesis.collections);
+		for (asset, rate) in &genesis.fee_rates {
+			parachain::set_fee_rate(*asset, *rate);
+		}
+	});
+	ext
+}
+
+fn asset_hub_ext(
+	para_id: u32,
+	other_para_ids: &[u32],
+	genesis: &GenesisSpec,
+) -> sp_io::TestExternalities {
+	use asset_hub::{MsgQueue, Runtime, SovereignAccountOf, System};
+
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	let (balances, asset_balances) = accounts::deriving_on(Chain::Para(para_id), || {
+		genesis.resolve_balances(1, other_para_ids, |location| {
+			SovereignAccountOf::convert(location).unwrap()
+		})
+	});
+	assimilate_balances::<Runtime>(&mut t, balances);
+	assimilate_assets::<Runtime>(&mut t, &genesis.assets, asset_balances);
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		sp_tracing::try_init_simple();
+		System::set_block_number(1);
+		MsgQueue::set_para_id(para_id.into());
+		versions::set_xcm_versions::<Runtime>(genesis.xcm_version, genesis.safe_xcm_version);
+		create_collections::<Runtime>(&genesis.collections);
+	});
+	ext
+}
+
+fn bridge_hub_ext(
+	para_id: u32,
+	other_para_ids: &[u32],
+	genesis: &GenesisSpec,
+) -> sp_io::TestExternalities {
+	use bridge_hub::{MsgQueue, Runtime, SovereignAccountOf, System};
+
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	let (balances, _) = accounts::deriving_on(Chain::Para(para_id), || {
+		genesis.resolve_balances(1, other_para_ids, |location| {
+			SovereignAccountOf::convert(location).unwrap()
+		})
+	});
+	assimilate_balances::<Runtime>(&mut t, balances);
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		sp_tracing::try_init_simple();
+		System::set_block_number(1);
+		MsgQueue::set_para_id(para_id.into());
+		versions::set_xcm_versions::<Runtime>(genesis.xcm_version, genesis.safe_xcm_version);
+	});
+	ext
+}
+
+fn evm_parachain_ext(
+	para_id: u32,
+	other_para_ids: &[u32],
+	genesis: &GenesisSpec,
+) -> sp_io::TestExternalities {
+	use evm_parachain::{MsgQueue, Runtime, SovereignAccountOf, System};
+
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Runtime>().unwrap();
+
+	let (sovereign_balances, _) = accounts::deriving_on(Chain::Para(para_id), || {
+		genesis.resolve_sovereign_balances(1, other_para_ids, |location| {
+			SovereignAccountOf::convert(location).unwrap()
+		})
+	});
+	let balances = genesis.evm_balances.iter().cloned().chain(sovereign_balances).collect();
+	assimilate_balances::<Runtime>(&mut t, balances);
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		sp_tracing::try_init_simple();
+		System::set_block_number(1);
+		MsgQueue::set_para_id(para_id.into());
+		versions::set_xcm_versions::<Runtime>(genesis.xcm_version, genesis.safe_xcm_version);
+	});
+	ext
+}
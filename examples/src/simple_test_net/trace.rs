@@ -0,0 +1,315 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Trace of the messages sent between the chains of the simulator.
+//!
+//! `TraceRouter` records every message a chain sends as a hop of the trace, in the order they
+//! are sent. `TraceExecutor` completes the hop with the weight used and the outcome of the
+//! message on its destination, matching them by message id. Executions stopped because the message
+//! does not fit in the weight left, like deferred or overweight messages, are not recorded: the
+//! hop gets the outcome of the execution that runs the message.
+
+use core::{cell::RefCell, marker::PhantomData};
+
+use codec::Encode;
+use frame_support::weights::Weight;
+use polkadot_parachain::primitives::Id as ParaId;
+use serde_json::{json, Value};
+use sp_runtime::traits::Get;
+use xcm::{latest::prelude::*, VersionedXcm};
+use xcm_executor::traits::ExecuteXcm;
+
+use super::{bridge::ThisNetwork, versions, Chain, RELAY_NETWORK};
+
+/// The transport a message is sent with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HopKind {
+	/// Downward, from the relay chain to a parachain.
+	Dmp,
+	/// Upward, from a parachain to the relay chain.
+	Ump,
+	/// Horizontal, from a parachain to a sibling.
+	Xcmp,
+}
+
+/// A message sent from one chain of the network to another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hop {
+	/// The network of both chains.
+	pub network: NetworkId,
+	pub kind: HopKind,
+	pub from: Chain,
+	pub to: Chain,
+	pub message: Xcm<()>,
+	/// The hash of the message as sent, in the latest version. The destination executes the
+	/// messages wrapped in an older version under the hash of their encoding in that version.
+	pub id: XcmHash,
+	/// The topic of the message, set with `SetTopic`.
+	pub topic: Option<[u8; 32]>,
+	/// The weight used by the execution of the message, `None` until it is executed.
+	pub weight_used: Option<Weight>,
+	/// The outcome of the execution of the message, `None` until it is executed.
+	pub outcome: Option<Outcome>,
+}
+
+thread_local! {
+	/// The hops recorded since the last `clear_trace`, or the reset of their network.
+	static TRACE: RefCell<Vec<Hop>> = RefCell::new(Vec::new());
+}
+
+/// The messages sent between the chains of the simulator since the last `clear_trace`.
+pub fn trace() -> Trace {
+	Trace(TRACE.with(|trace| trace.borrow().clone()))
+}
+
+/// Forgets the recorded messages.
+pub fn clear_trace() {
+	TRACE.with(|trace| trace.borrow_mut().clear());
+}
+
+/// Forgets the hops of `network`, which is being reset.
+pub(super) fn reset_trace(network: NetworkId) {
+	TRACE.with(|trace| trace.borrow_mut().retain(|hop| hop.network != network));
+}
+
+fn record_send(from: Chain, destination: &MultiLocation, message: &Xcm<()>) {
+	let to = match from.resolve(destination) {
+		Some(to) => to,
+		None => return,
+	};
+	let kind = match (from, to) {
+		(Chain::Relay, _) => HopKind::Dmp,
+		(_, Chain::Relay) => HopKind::Ump,
+		_ => HopKind::Xcmp,
+	};
+	let id = VersionedXcm::from(message.clone()).using_encoded(sp_io::hashing::blake2_256);
+	let topic = message.0.iter().find_map(|instruction| match instruction {
+		SetTopic(topic) => Some(*topic),
+		_ => None,
+	});
+	let hop = Hop {
+		network: ThisNetwork::get(),
+		kind,
+		from,
+		to,
+		message: message.clone(),
+		id,
+		topic,
+		weight_used: None,
+		outcome: None,
+	};
+	TRACE.with(|trace| trace.borrow_mut().push(hop));
+}
+
+fn record_outcome(chain: Chain, id: XcmHash, outcome: &Outcome) {
+	// The message is executed again later, keep the hop for that execution.
+	if let Outcome::Error(XcmError::WeightLimitReached(_)) = outcome {
+		return
+	}
+	let network = ThisNetwork::get();
+	let id = versions::take_sent_id(network, chain, id).unwrap_or(id);
+	TRACE.with(|trace| {
+		let mut trace = trace.borrow_mut();
+		let hop = trace.iter_mut().find(|hop| {
+			hop.id == id && hop.to == chain && hop.network == network && hop.outcome.is_none()
+		});
+		if let Some(hop) = hop {
+			hop.weight_used = Some(outcome.weight_used());
+			hop.outcome = Some(outcome.clone());
+		}
+	});
+}
+
+/// The relay chain, for `TraceRouter`, `TraceExecutor` and `RecordDerivations`.
+pub struct AsRelay;
+impl Get<Chain> for AsRelay {
+	fn get() -> Chain {
+		Chain::Relay
+	}
+}
+
+/// The parachain `ParachainId`, for `TraceRouter`, `TraceExecutor` and `RecordDerivations`.
+pub struct AsParachain<ParachainId>(PhantomData<ParachainId>);
+impl<ParachainId: Get<ParaId>> Get<Chain> for AsParachain<ParachainId> {
+	fn get() -> Chain {
+		Chain::Para(ParachainId::get().into())
+	}
+}
+
+/// Router of the chain `ThisChain`, recording the messages `Router` delivers in the trace.
+pub struct TraceRouter<Router, ThisChain>(PhantomData<(Router, ThisChain)>);
+impl<Router: SendXcm, ThisChain: Get<Chain>> SendXcm for TraceRouter<Router, ThisChain> {
+	type Ticket = (Router::Ticket, Option<(MultiLocation, Xcm<()>)>);
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let traced = destination.zip(message.clone());
+		let (ticket, price) = Router::validate(destination, message)?;
+		Ok(((ticket, traced), price))
+	}
+
+	fn deliver((ticket, traced): Self::Ticket) -> Result<XcmHash, SendError> {
+		let hash = Router::deliver(ticket)?;
+		if let Some((destination, message)) = traced {
+			record_send(ThisChain::get(), &destination, &message);
+		}
+		Ok(hash)
+	}
+}
+
+/// Executor of the chain `ThisChain`, recording the outcome of the messages of the trace it
+/// executes.
+pub struct TraceExecutor<Executor, ThisChain>(PhantomData<(Executor, ThisChain)>);
+impl<Call, Executor: ExecuteXcm<Call>, ThisChain: Get<Chain>> ExecuteXcm<Call>
+	for TraceExecutor<Executor, ThisChain>
+{
+	type Prepared = Executor::Prepared;
+
+	fn prepare(message: Xcm<Call>) -> Result<Self::Prepared, Xcm<Call>> {
+		Executor::prepare(message)
+	}
+
+	fn execute(
+		origin: impl Into<MultiLocation>,
+		pre: Self::Prepared,
+		id: &mut XcmHash,
+		weight_credit: Weight,
+	) -> Outcome {
+		let message_id = *id;
+		let outcome = Executor::execute(origin, pre, id, weight_credit);
+		record_outcome(ThisChain::get(), message_id, &outcome);
+		outcome
+	}
+
+	fn charge_fees(location: impl Into<MultiLocation>, fees: MultiAssets) -> XcmResult {
+		Executor::charge_fees(location, fees)
+	}
+}
+
+/// Hops recorded by `TraceRouter`, in the order the messages were sent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace(pub Vec<Hop>);
+
+impl Trace {
+	/// The trace as a JSON array of hops.
+	pub fn to_json(&self) -> String {
+		let hops: Vec<Value> = self
+			.0
+			.iter()
+			.enumerate()
+			.map(|(index, hop)| {
+				json!({
+					"index": index,
+					"network": format!("{:?}", hop.network),
+					"kind": format!("{:?}", hop.kind),
+					"from": participant(hop.network, hop.from),
+					"to": participant(hop.network, hop.to),
+					"message": format!("{:?}", hop.message),
+					"id": format!("0x{}", hex::encode(hop.id)),
+					"topic": hop.topic.map(|topic| format!("0x{}", hex::encode(topic))),
+					"weight_used": hop.weight_used.map(|weight| json!({
+						"ref_time": weight.ref_time(),
+						"proof_size": weight.proof_size(),
+					})),
+					"outcome": hop.outcome.as_ref().map(|outcome| format!("{:?}", outcome)),
+				})
+			})
+			.collect();
+		serde_json::to_string_pretty(&hops).expect("values built with `json!` serialize; qed")
+	}
+
+	/// The trace as a Mermaid sequence diagram, with the instructions and the outcome of every
+	/// message.
+	pub fn to_mermaid(&self) -> String {
+		let mut diagram = String::from("sequenceDiagram\n");
+		for hop in &self.0 {
+			let (from, to) = (participant(hop.network, hop.from), participant(hop.network, hop.to));
+			diagram.push_str(&format!(
+				"    {}->>{}: {:?} {}\n",
+				from.replace(' ', "_"),
+				to.replace(' ', "_"),
+				hop.kind,
+				instructions(&hop.message),
+			));
+			diagram.push_str(&format!(
+				"    Note over {}: {}\n",
+				to.replace(' ', "_"),
+				outcome_label(hop)
+			));
+		}
+		diagram
+	}
+
+	/// The trace as a Graphviz digraph, with one numbered edge per message.
+	pub fn to_dot(&self) -> String {
+		let mut graph = String::from("digraph xcm {\n");
+		for (index, hop) in self.0.iter().enumerate() {
+			graph.push_str(&format!(
+				"    \"{}\" -> \"{}\" [label=\"{}. {:?} {}\\n{}\"];\n",
+				participant(hop.network, hop.from),
+				participant(hop.network, hop.to),
+				index,
+				hop.kind,
+				instructions(&hop.message),
+				outcome_label(hop),
+			));
+		}
+		graph.push_str("}\n");
+		graph
+	}
+}
+
+/// The name of `chain` of `network` in the exported traces.
+fn participant(network: NetworkId, chain: Chain) -> String {
+	let name = match chain {
+		Chain::Relay => "Relay".to_string(),
+		Chain::Para(para_id) => format!("Para {}", para_id),
+	};
+	if network == RELAY_NETWORK {
+		name
+	} else {
+		format!("Bridged {}", name)
+	}
+}
+
+/// The names of the instructions of `message`, without their operands.
+fn instructions(message: &Xcm<()>) -> String {
+	let names: Vec<String> = message
+		.0
+		.iter()
+		.map(|instruction| {
+			let debug = format!("{:?}", instruction);
+			debug
+				.split(|c: char| c == '(' || c == ' ')
+				.next()
+				.unwrap_or_default()
+				.to_string()
+		})
+		.collect();
+	names.join(", ")
+}
+
+fn outcome_label(hop: &Hop) -> String {
+	match &hop.outcome {
+		Some(Outcome::Complete(weight)) => format!("Complete ({})", weight.ref_time()),
+		Some(Outcome::Incomplete(weight, error)) =>
+			format!("Incomplete ({}): {:?}", weight.ref_time(), error),
+		Some(Outcome::Error(error)) => format!("Error: {:?}", error),
+		None => "Not executed".to_string(),
+	}
+}
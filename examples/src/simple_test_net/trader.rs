@@ -0,0 +1,134 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Weight trader taking fees in any asset with a fee rate.
+
+use core::marker::PhantomData;
+
+use frame_support::weights::{
+	constants::{WEIGHT_PROOF_SIZE_PER_MB, WEIGHT_REF_TIME_PER_SECOND},
+	Weight,
+};
+use sp_runtime::traits::Get;
+use xcm::latest::prelude::*;
+use xcm_builder::TakeRevenue;
+use xcm_executor::{
+	traits::{TransactAsset, WeightTrader},
+	Assets,
+};
+
+/// Fee rate of an asset, as `(units per second of ref time, units per megabyte of proof size)`.
+pub type FeeRate = (u128, u128);
+
+/// Fee rates of the assets a `MultiAssetTrader` accepts.
+pub trait FeeRates {
+	/// Fee rate of the concrete asset `asset`, `None` if fees cannot be paid in it.
+	fn fee_rate(asset: &MultiLocation) -> Option<FeeRate>;
+}
+
+/// Fee for `weight` at `rate`, computed like `FixedRateOfFungible` does.
+pub fn fee_for_weight((units_per_second, units_per_mb): FeeRate, weight: Weight) -> u128 {
+	units_per_second * (weight.ref_time() as u128) / (WEIGHT_REF_TIME_PER_SECOND as u128) +
+		units_per_mb * (weight.proof_size() as u128) / (WEIGHT_PROOF_SIZE_PER_MB as u128)
+}
+
+/// Fee `Trader` charges for `weight` when paid in `asset`, `None` if `Trader` does not take
+/// `asset`.
+///
+/// The trader is forgotten rather than dropped, so the fee is not handed to its revenue.
+pub fn trader_fee<Trader: WeightTrader>(asset: AssetId, weight: Weight) -> Option<u128> {
+	let mut trader = Trader::new();
+	let payment = MultiAsset { id: asset, fun: Fungible(u128::MAX) };
+	let unused = trader.buy_weight(weight, payment.into());
+	core::mem::forget(trader);
+	let unused = unused.ok()?.fungible.get(&asset).copied().unwrap_or(0);
+	Some(u128::MAX - unused)
+}
+
+/// Weight trader taking fees in the first asset of the payment with a fee rate in `Rates`.
+///
+/// Weight bought later in the same message is paid in the same asset. The fees are handed to
+/// `Revenue` when the trader is dropped.
+pub struct MultiAssetTrader<Rates: FeeRates, Revenue: TakeRevenue> {
+	weight: Weight,
+	/// The asset fees are paid in and the amount paid, once weight is bought.
+	paid: Option<(MultiLocation, u128)>,
+	_phantom: PhantomData<(Rates, Revenue)>,
+}
+
+impl<Rates: FeeRates, Revenue: TakeRevenue> WeightTrader for MultiAssetTrader<Rates, Revenue> {
+	fn new() -> Self {
+		Self { weight: Weight::zero(), paid: None, _phantom: PhantomData }
+	}
+
+	fn buy_weight(&mut self, weight: Weight, payment: Assets) -> Result<Assets, XcmError> {
+		let (asset, rate) = match &self.paid {
+			Some((asset, _)) => (*asset, Rates::fee_rate(asset).ok_or(XcmError::TooExpensive)?),
+			None => payment
+				.fungible
+				.keys()
+				.find_map(|id| match id {
+					Concrete(asset) => Rates::fee_rate(asset).map(|rate| (*asset, rate)),
+					Abstract(_) => None,
+				})
+				.ok_or(XcmError::TooExpensive)?,
+		};
+		let amount = fee_for_weight(rate, weight);
+		let unused = payment
+			.checked_sub((asset, amount).into())
+			.map_err(|_| XcmError::TooExpensive)?;
+		self.weight = self.weight.saturating_add(weight);
+		let (_, paid) = self.paid.get_or_insert((asset, 0));
+		*paid = paid.saturating_add(amount);
+		Ok(unused)
+	}
+
+	fn refund_weight(&mut self, weight: Weight) -> Option<MultiAsset> {
+		let (asset, paid) = self.paid.as_mut()?;
+		let weight = weight.min(self.weight);
+		let amount = fee_for_weight(Rates::fee_rate(asset)?, weight).min(*paid);
+		self.weight = self.weight.saturating_sub(weight);
+		*paid -= amount;
+		if amount > 0 {
+			Some((*asset, amount).into())
+		} else {
+			None
+		}
+	}
+}
+
+impl<Rates: FeeRates, Revenue: TakeRevenue> Drop for MultiAssetTrader<Rates, Revenue> {
+	fn drop(&mut self) {
+		if let Some((asset, amount)) = self.paid {
+			if amount > 0 {
+				Revenue::take_revenue((asset, amount).into());
+			}
+		}
+	}
+}
+
+/// Deposits the revenue of a trader in `Beneficiary` with the asset transactor `Transactor`.
+///
+/// Revenue that cannot be deposited is burned, as with `()`.
+pub struct DepositToAccount<Transactor, Beneficiary>(PhantomData<(Transactor, Beneficiary)>);
+impl<Transactor: TransactAsset, Beneficiary: Get<MultiLocation>> TakeRevenue
+	for DepositToAccount<Transactor, Beneficiary>
+{
+	fn take_revenue(revenue: MultiAsset) {
+		let context = XcmContext::with_message_hash([0; 32]);
+		let _ = Transactor::deposit_asset(&revenue, &Beneficiary::get(), &context);
+	}
+}
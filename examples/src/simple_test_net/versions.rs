@@ -0,0 +1,193 @@
+// Copyright Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! XCM versions spoken by the chains of the simulator.
+//!
+//! Every chain advertises the XCM version set in its genesis, see `GenesisSpec::xcm_version`,
+//! and its message queue rejects messages of later versions. `VersionedRouter` wraps the
+//! messages a chain sends in the version `pallet_xcm` knows the destination supports, or in the
+//! safe version of the chain for destinations whose version is unknown.
+//!
+//! The simulator only transports messages of the latest version. The messages wrapped in an
+//! older version are re-encoded in it before their destination receives them, see
+//! `FaultyHandler`. The destination executes them under the hash of that encoding, which the
+//! trace maps back to the id of the message as sent.
+
+use core::{cell::RefCell, marker::PhantomData};
+
+use codec::{Decode, Encode};
+use frame_support::{
+	storage::{storage_prefix, unhashed},
+	Blake2_128Concat, StorageHasher, Twox64Concat,
+};
+use sp_runtime::traits::Get;
+use xcm::{latest::prelude::*, IdentifyVersion, VersionedMultiLocation, VersionedXcm, WrapVersion};
+
+use super::{bridge::ThisNetwork, faults::Route, Chain};
+
+const XCM_VERSION_KEY: &[u8] = b":mock:xcm_version:";
+
+/// The XCM version the chain whose externalities are set advertises, the latest if not set in
+/// its genesis.
+pub struct AdvertisedXcmVersion;
+impl AdvertisedXcmVersion {
+	/// Sets the version of the chain. Must be called within the externalities of the chain.
+	pub fn set(version: XcmVersion) {
+		unhashed::put(XCM_VERSION_KEY, &version);
+	}
+}
+impl Get<XcmVersion> for AdvertisedXcmVersion {
+	fn get() -> XcmVersion {
+		unhashed::get(XCM_VERSION_KEY).unwrap_or(xcm::latest::VERSION)
+	}
+}
+
+/// Sets the version `pallet_xcm` of the runtime `T` advertises and, if `safe` is set, the
+/// version it wraps messages in for destinations whose version is unknown. Must be called within
+/// the externalities of the chain.
+pub(super) fn set_xcm_versions<T: pallet_xcm::Config>(
+	advertised: Option<XcmVersion>,
+	safe: Option<XcmVersion>,
+) {
+	AdvertisedXcmVersion::set(advertised.unwrap_or(xcm::latest::VERSION));
+	if let Some(safe) = safe {
+		pallet_xcm::Pallet::<T>::force_default_xcm_version(
+			frame_system::RawOrigin::Root.into(),
+			Some(safe),
+		)
+		.expect("Root can set the default version; qed");
+	}
+}
+
+/// The version `pallet_xcm` of the runtime `T` knows `location` supports, from the
+/// `SupportedVersion` storage of the pallet.
+pub fn supported_version<T: pallet_xcm::Config>(
+	location: impl Into<MultiLocation>,
+) -> Option<XcmVersion> {
+	// `SupportedVersion` is not public, its key is derived like `pallet_xcm` does.
+	let location = VersionedMultiLocation::from(location.into());
+	let mut key = storage_prefix(
+		<pallet_xcm::Pallet<T> as frame_support::traits::PalletInfoAccess>::name().as_bytes(),
+		b"SupportedVersion",
+	)
+	.to_vec();
+	key.extend(Twox64Concat::hash(&xcm::latest::VERSION.encode()));
+	key.extend(Blake2_128Concat::hash(&location.encode()));
+	unhashed::get(&key)
+}
+
+thread_local! {
+	/// The messages wrapped in an older version than the latest and not delivered yet, by route
+	/// and message id.
+	static WRAPPED: RefCell<Vec<(Route, XcmHash, XcmVersion)>> = RefCell::new(Vec::new());
+	/// The messages delivered in an older version and not executed yet, by route, message id and
+	/// hash of the message as delivered.
+	static DELIVERED: RefCell<Vec<(Route, XcmHash, XcmHash, XcmVersion)>> =
+		RefCell::new(Vec::new());
+}
+
+/// Forgets the messages of `network` wrapped in an older version, as `network` is being reset.
+pub(super) fn reset_wrapped(network: NetworkId) {
+	WRAPPED.with(|messages| messages.borrow_mut().retain(|(route, ..)| route.network != network));
+	DELIVERED.with(|messages| messages.borrow_mut().retain(|(route, ..)| route.network != network));
+}
+
+/// Router of the chain `ThisChain`, wrapping the messages `Router` delivers with `Wrapper`.
+///
+/// Messages that cannot be converted to the version of their destination are not sent.
+pub struct VersionedRouter<Router, Wrapper, ThisChain>(PhantomData<(Router, Wrapper, ThisChain)>);
+impl<Router: SendXcm, Wrapper: WrapVersion, ThisChain: Get<Chain>> SendXcm
+	for VersionedRouter<Router, Wrapper, ThisChain>
+{
+	/// The ticket of `Router`, and the route, id and version of a message wrapped in an older
+	/// version than the latest.
+	type Ticket = (Router::Ticket, Option<(Route, XcmHash, XcmVersion)>);
+
+	fn validate(
+		destination: &mut Option<MultiLocation>,
+		message: &mut Option<Xcm<()>>,
+	) -> SendResult<Self::Ticket> {
+		let sent = destination.zip(message.clone());
+		let (ticket, price) = Router::validate(destination, message)?;
+		let (destination, message) = match sent {
+			Some(sent) => sent,
+			None => return Ok(((ticket, None), price)),
+		};
+		let version = Wrapper::wrap_version(&destination, message.clone())
+			.map_err(|()| SendError::DestinationUnsupported)?
+			.identify_version();
+		let from = ThisChain::get();
+		let wrapped = match from.resolve(&destination) {
+			Some(to) if version < xcm::latest::VERSION => {
+				let route = Route { network: ThisNetwork::get(), from, to };
+				Some((route, super::faults::message_id(&message), version))
+			},
+			_ => None,
+		};
+		Ok(((ticket, wrapped), price))
+	}
+
+	fn deliver((ticket, wrapped): Self::Ticket) -> Result<XcmHash, SendError> {
+		let hash = Router::deliver(ticket)?;
+		if let Some(wrapped) = wrapped {
+			WRAPPED.with(|messages| messages.borrow_mut().push(wrapped));
+		}
+		Ok(hash)
+	}
+}
+
+/// `message`, a message of the latest version sent on `route`, encoded in the version it was
+/// wrapped in if that is an older one.
+///
+/// The message is then delivered until it is executed, see `take_sent_id`: a message processed
+/// again, like an overweight upward message, is encoded in the same version.
+pub(super) fn as_wrapped(route: Route, message: &[u8]) -> Option<Vec<u8>> {
+	let id = sp_io::hashing::blake2_256(message);
+	let wrapped = WRAPPED.with(|messages| {
+		let mut messages = messages.borrow_mut();
+		let index = messages.iter().position(|(of, of_id, _)| *of == route && *of_id == id)?;
+		Some(messages.remove(index).2)
+	});
+	let version = match wrapped {
+		Some(version) => version,
+		None => DELIVERED.with(|messages| {
+			let messages = messages.borrow();
+			messages
+				.iter()
+				.find(|(of, of_id, ..)| *of == route && *of_id == id)
+				.map(|m| m.3)
+		})?,
+	};
+	let versioned = VersionedXcm::<()>::decode(&mut &message[..]).ok()?;
+	let encoded = versioned.into_version(version).ok()?.encode();
+	if wrapped.is_some() {
+		let hash = sp_io::hashing::blake2_256(&encoded);
+		DELIVERED.with(|messages| messages.borrow_mut().push((route, id, hash, version)));
+	}
+	Some(encoded)
+}
+
+/// The id, as sent, of the message of `network` delivered to `to` in an older version whose
+/// hash is `hash`, as it is executed. The message is forgotten.
+pub(super) fn take_sent_id(network: NetworkId, to: Chain, hash: XcmHash) -> Option<XcmHash> {
+	DELIVERED.with(|messages| {
+		let mut messages = messages.borrow_mut();
+		let index = messages.iter().position(|(route, _, of, _)| {
+			route.network == network && route.to == to && *of == hash
+		})?;
+		Some(messages.remove(index).1)
+	})
+}